@@ -1,7 +1,7 @@
 import { fetch } from "@tauri-apps/plugin-http";
 import { invoke } from "@tauri-apps/api/core";
 import type { Ref } from "vue";
-import type { PoolInfoEntry } from "~/types/gacha";
+import type { PoolInfoEntry, PoolItemInfo } from "~/types/gacha";
 import { toUp6IdList } from "~/utils/gachaCalc";
 
 export const useGachaPoolInfo = (params: { userAgent: Ref<string> }) => {
@@ -16,6 +16,16 @@ export const useGachaPoolInfo = (params: { userAgent: Ref<string> }) => {
     return map;
   });
 
+  // 卡池完整奖励列表，仅保留图鉴统计需要的字段
+  const toPoolItemList = (all: any[]): PoolItemInfo[] =>
+    all
+      .filter((x: any) => x && String(x.id || "").trim())
+      .map((x: any) => ({
+        id: String(x.id).trim(),
+        name: String(x.name || ""),
+        rarity: Number(x.rarity) || 0,
+      }));
+
   const normalizePoolInfoEntry = (value: any): PoolInfoEntry | null => {
     if (!value || typeof value.pool_id !== "string" || !value.pool_id) {
       return null;
@@ -29,6 +39,7 @@ export const useGachaPoolInfo = (params: { userAgent: Ref<string> }) => {
       pool_type: String(value.pool_type || ""),
       up6_id: String(value.up6_id || "").trim(),
       up6_ids: up6Ids.length > 0 ? up6Ids : undefined,
+      all: Array.isArray(value.all) ? toPoolItemList(value.all) : undefined,
     };
   };
 
@@ -125,6 +136,7 @@ export const useGachaPoolInfo = (params: { userAgent: Ref<string> }) => {
         pool_type: String(pool.pool_type || ""),
         up6_id: isMultiUp ? "" : up6Ids[0] || "",
         up6_ids: isMultiUp ? up6Ids : undefined,
+        all: toPoolItemList(Array.isArray(pool.all) ? pool.all : []),
       };
       return entry;
     } catch (e) {
@@ -169,6 +181,7 @@ export const useGachaPoolInfo = (params: { userAgent: Ref<string> }) => {
         pool_name: String(pool.pool_name || ""),
         pool_type: isConstant ? "constant" : "special",
         up6_id: up6Ids[0] || "",
+        all: toPoolItemList(all),
       };
       return entry;
     } catch (e) {
@@ -196,7 +209,8 @@ export const useGachaPoolInfo = (params: { userAgent: Ref<string> }) => {
     let changed = false;
     for (const poolId of uniq) {
       const existing = poolInfoById.value[poolId];
-      if (existing?.up6_id || (existing?.up6_ids || []).length > 0) continue;
+      const hasUp6 = existing?.up6_id || (existing?.up6_ids || []).length > 0;
+      if (hasUp6 && existing?.all) continue;
 
       const entry = await fetchCharPoolInfoFromApi(
         p.provider,
@@ -228,7 +242,7 @@ export const useGachaPoolInfo = (params: { userAgent: Ref<string> }) => {
     const existing = (poolInfo.value || []).find(
       (x) => x?.pool_id === poolId && x?.pool_gacha_type === "weapon",
     );
-    if (existing) return;
+    if (existing?.all) return;
 
     const entry = await fetchWeaponPoolInfoFromApi({
       provider: p.provider,
@@ -1,18 +1,41 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { AppConfig, User, WebDavConfig, WebDavStateItem } from "~/types/gacha";
+import type { AppConfig, NetworkConfig, S3Config, User, WebDavConfig, WebDavStateItem } from "~/types/gacha";
 import {
   isSystemUid,
 } from "~/utils/systemAccount";
 
+const createDefaultS3Config = (): S3Config => ({
+  endpoint: "",
+  region: "us-east-1",
+  bucket: "",
+  accessKeyId: "",
+  secretAccessKey: "",
+  pathStyle: true,
+});
+
 const createDefaultWebDavConfig = (): WebDavConfig => ({
+  backend: "webdav",
   baseUrl: "",
   username: "",
   password: "",
+  authKind: "basic",
+  bearerToken: "",
+  extraHeaders: [],
+  folderPath: "",
+  s3: createDefaultS3Config(),
   basePath: "/endfield-gacha",
   autoSync: false,
   silentAutoSync: true,
 });
 
+const createDefaultNetworkConfig = (): NetworkConfig => ({
+  caBundle: "",
+  certPin: "",
+  proxyUrl: "",
+  timeoutSecs: 20,
+  connectTimeoutSecs: 10,
+});
+
 export const useUserStore = () => {
   const userList = useState<User[]>("global-user-list", () => []);
   const { isWindows } = usePlatform();
@@ -21,6 +44,7 @@ export const useUserStore = () => {
   const savabled = useState<boolean>("global-savabled", () => false);
   const webdavConfig = useState<WebDavConfig>("global-webdav-config", createDefaultWebDavConfig);
   const webdavState = useState<Record<string, WebDavStateItem>>("global-webdav-state", () => ({}));
+  const networkConfig = useState<NetworkConfig>("global-network-config", createDefaultNetworkConfig);
 
   const getUserKey = (u: User) =>
     u.key || (u.roleId?.roleId ? `${u.uid}_${u.roleId.roleId}` : u.uid);
@@ -90,8 +114,16 @@ export const useUserStore = () => {
       webdavConfig.value = {
         ...createDefaultWebDavConfig(),
         ...(config.webdav || {}),
+        s3: {
+          ...createDefaultS3Config(),
+          ...(config.webdav?.s3 || {}),
+        },
       };
       webdavState.value = config.webdavState || {};
+      networkConfig.value = {
+        ...createDefaultNetworkConfig(),
+        ...(config.network || {}),
+      };
     } catch (e) {
       console.error("加载配置失败", e);
       userList.value = [];
@@ -99,6 +131,7 @@ export const useUserStore = () => {
       updateSeenVersion.value = "";
       webdavConfig.value = createDefaultWebDavConfig();
       webdavState.value = {};
+      networkConfig.value = createDefaultNetworkConfig();
     } finally {
       savabled.value = true;
     }
@@ -120,6 +153,10 @@ export const useUserStore = () => {
           ...(toRaw(webdavConfig.value) || {}),
         },
         webdavState: toRaw(webdavState.value) || {},
+        network: {
+          ...createDefaultNetworkConfig(),
+          ...(toRaw(networkConfig.value) || {}),
+        },
       };
       await invoke("save_config", { data: configData });
       console.log("配置已同步到硬盘");
@@ -180,6 +217,7 @@ export const useUserStore = () => {
     saveConfig,
     webdavConfig,
     webdavState,
+    networkConfig,
     getUserKey,
   };
 };
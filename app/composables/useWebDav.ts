@@ -2,6 +2,7 @@ import { invoke } from "@tauri-apps/api/core";
 import type {
   WebDavBatchSyncResult,
   WebDavConfig,
+  WebDavEncryptionStatus,
   WebDavRestoreAccount,
   WebDavRestoreResult,
   WebDavSyncResult,
@@ -33,14 +34,36 @@ export const useWebDav = () => {
   const { currentUser, getUserKey, userList, webdavConfig, saveConfig, loadConfig } = useUserStore();
   const isBatchSyncing = useState<boolean>("webdav-batch-syncing", () => false);
 
+  const hasCredentials = (config: WebDavConfig) => {
+    switch (config.authKind || "basic") {
+      case "bearer":
+        return Boolean(String(config.bearerToken || "").trim());
+      case "headers":
+        return (config.extraHeaders || []).some((header) => String(header.name || "").trim());
+      default:
+        return Boolean(String(config.username || "").trim() && String(config.password || "").trim());
+    }
+  };
+
+  const hasTarget = (config: WebDavConfig) => {
+    switch (config.backend || "webdav") {
+      case "folder":
+        return Boolean(String(config.folderPath || "").trim());
+      case "s3": {
+        const s3 = config.s3;
+        return Boolean(
+          s3 &&
+          [s3.endpoint, s3.region, s3.bucket, s3.accessKeyId, s3.secretAccessKey].every((item) => String(item || "").trim()),
+        );
+      }
+      default:
+        return Boolean(String(config.baseUrl || "").trim() && hasCredentials(config));
+    }
+  };
+
   const isConfigured = computed(() => {
     const config = webdavConfig.value;
-    return Boolean(
-      String(config.baseUrl || "").trim() &&
-      String(config.username || "").trim() &&
-      String(config.password || "").trim() &&
-      String(config.basePath || "").trim(),
-    );
+    return Boolean(String(config.basePath || "").trim() && hasTarget(config));
   });
 
   const normalizeConfig = () => {
@@ -48,6 +71,20 @@ export const useWebDav = () => {
     config.baseUrl = String(config.baseUrl || "").trim().replace(/\/+$/, "");
     config.username = String(config.username || "").trim();
     config.password = String(config.password || "");
+    config.authKind = config.authKind || "basic";
+    config.bearerToken = String(config.bearerToken || "").trim();
+    config.extraHeaders = (config.extraHeaders || [])
+      .map((header) => ({ name: String(header.name || "").trim(), value: String(header.value || "").trim() }))
+      .filter((header) => header.name);
+    config.backend = config.backend || "webdav";
+    config.folderPath = String(config.folderPath || "").trim();
+    if (config.s3) {
+      config.s3.endpoint = String(config.s3.endpoint || "").trim().replace(/\/+$/, "");
+      config.s3.region = String(config.s3.region || "").trim();
+      config.s3.bucket = String(config.s3.bucket || "").trim();
+      config.s3.accessKeyId = String(config.s3.accessKeyId || "").trim();
+      config.s3.secretAccessKey = String(config.s3.secretAccessKey || "").trim();
+    }
     config.basePath = normalizeBasePath(config.basePath || "");
   };
 
@@ -76,7 +113,7 @@ export const useWebDav = () => {
 
   const syncAccount = async (
     accountKey?: string | null,
-    options?: { silentSuccess?: boolean; showErrorToast?: boolean; queueIfBusy?: boolean },
+    options?: { silentSuccess?: boolean; showErrorToast?: boolean; queueIfBusy?: boolean; reupload?: boolean },
   ) => {
     const targetKey = String(accountKey || currentUser.value || "").trim();
     if (!targetKey || targetKey === "none") {
@@ -102,6 +139,7 @@ export const useWebDav = () => {
 
       const result = await invoke<WebDavSyncResult>("webdav_sync_account", {
         userKey: targetKey,
+        reupload: Boolean(options?.reupload),
       });
 
       await loadConfig();
@@ -115,10 +153,21 @@ export const useWebDav = () => {
     })()
       .catch((error: any) => {
         if (options?.showErrorToast !== false) {
+          const description = error?.message || String(error || "未知错误");
+          // 账号已从远端删除时只能由用户确认后重新上传
+          const deletedRemotely = description.includes("从远端删除");
           toast.add({
             title: "WebDAV 同步失败",
-            description: error?.message || String(error || "未知错误"),
+            description,
             color: "error",
+            actions: deletedRemotely
+              ? [{
+                label: "重新上传",
+                onClick: () => {
+                  syncAccount(targetKey, { reupload: true }).catch(() => undefined);
+                },
+              }]
+              : undefined,
           });
         }
         throw error;
@@ -176,35 +225,22 @@ export const useWebDav = () => {
     }
 
     isBatchSyncing.value = true;
-    const results: WebDavSyncResult[] = [];
-    const failed: WebDavBatchSyncResult["failed"] = [];
+    let summary: WebDavBatchSyncResult;
 
     try {
-      for (const accountKey of accountKeys) {
-        try {
-          const result = await syncAccount(accountKey, {
-            silentSuccess: true,
-            showErrorToast: false,
-            queueIfBusy: true,
-          });
-          results.push(result);
-        } catch (error: any) {
-          failed.push({
-            accountKey,
-            message: error?.message || String(error || "未知错误"),
-          });
-        }
-      }
+      // 等待进行中的单账号同步，避免与批量同步并发写入
+      await Promise.allSettled([...activeSyncTasks.values()]);
+      summary = await invoke<WebDavBatchSyncResult>("webdav_sync_all");
+      await loadConfig();
     } finally {
       isBatchSyncing.value = false;
     }
 
-    const summary: WebDavBatchSyncResult = {
-      total: accountKeys.length,
-      skipped: 0,
-      failed,
-      results,
-    };
+    const { results, failed } = summary;
+    const changedKeys = results.filter((item) => item.localChanged).map((item) => item.accountKey);
+    if (changedKeys.length > 0) {
+      emitLocalChanged(changedKeys);
+    }
 
     const statusCounts = {
       uploaded: results.filter((item) => item.status === "uploaded").length,
@@ -220,6 +256,9 @@ export const useWebDav = () => {
       `合并 ${statusCounts.merged} 个`,
       `无变化 ${statusCounts.noop} 个`,
     ];
+    if (summary.skipped > 0) {
+      summaryParts.push(`跳过 ${summary.skipped} 个`);
+    }
     if (failed.length > 0) {
       summaryParts.push(`失败 ${failed.length} 个`);
     }
@@ -255,6 +294,26 @@ export const useWebDav = () => {
     });
   };
 
+  const getEncryptionStatus = async () => {
+    if (!isConfigured.value) {
+      throw new Error("请先填写完整的 WebDAV 配置");
+    }
+    await persistConfig();
+    const status = await invoke<WebDavEncryptionStatus>("webdav_encryption_status");
+    // 旧版明文口令会在解锁后被后端换成派生密钥
+    await loadConfig();
+    return status;
+  };
+
+  // 口令校验通过后由后端保存，重新加载避免下次保存配置时被旧值覆盖
+  const unlockEncryption = async (passphrase: string) => {
+    const status = await invoke<WebDavEncryptionStatus>("webdav_unlock_encryption", {
+      passphrase,
+    });
+    await loadConfig();
+    return status;
+  };
+
   const listRestoreAccounts = async () => {
     if (!isConfigured.value) {
       throw new Error("请先填写完整的 WebDAV 配置");
@@ -318,6 +377,8 @@ export const useWebDav = () => {
     testConnection,
     syncAccount,
     syncAllAccounts,
+    getEncryptionStatus,
+    unlockEncryption,
     listRestoreAccounts,
     restoreAccounts,
     scheduleAutoSync,
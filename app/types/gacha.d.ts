@@ -21,21 +21,75 @@ export interface AppConfig {
   updateSeenVersion?: string;
   webdav?: WebDavConfig;
   webdavState?: Record<string, WebDavStateItem>;
+  network?: NetworkConfig;
+}
+
+/** 所有出站 HTTP 请求共用的 TLS、代理与超时设置 */
+export interface NetworkConfig {
+  /** PEM 格式 CA 证书文件路径或证书内容 */
+  caBundle: string;
+  /** 服务器证书 SHA-256 指纹；设置后不再校验证书链 */
+  certPin: string;
+  /** http(s):// 或 socks5(h):// 代理地址 */
+  proxyUrl: string;
+  timeoutSecs: number;
+  connectTimeoutSecs: number;
 }
 
 export interface WebDavConfig {
+  /** 同步后端；folder 配合 Syncthing、Dropbox 或网络共享使用 */
+  backend?: RemoteBackendKind;
   baseUrl: string;
   username: string;
   password: string;
+  /** 认证方式；basic/digest 使用用户名与密码 */
+  authKind?: WebDavAuthKind;
+  bearerToken?: string;
+  /** 附加到每个请求上的静态请求头 */
+  extraHeaders?: WebDavHeader[];
+  /** 本地同步目录（绝对路径），basePath 位于其下 */
+  folderPath?: string;
+  s3?: S3Config;
   basePath: string;
   autoSync: boolean;
   silentAutoSync: boolean;
+  /** 端到端加密密钥（由后端在解锁后写入，不保存口令）；为空表示远端明文存储 */
+  encryptionKey?: WebDavStoredKey;
+  /** 旧版配置明文保存的口令，解锁成功后由后端换成 encryptionKey */
+  encryptionPassphrase?: string;
+}
+
+export interface WebDavStoredKey {
+  kdf: Record<string, unknown>;
+  key: string;
+}
+
+export type WebDavAuthKind = 'basic' | 'digest' | 'bearer' | 'headers';
+
+export type RemoteBackendKind = 'webdav' | 'folder' | 's3';
+
+export interface S3Config {
+  /** 例如 https://s3.amazonaws.com 或 http://127.0.0.1:9000 */
+  endpoint: string;
+  region: string;
+  bucket: string;
+  accessKeyId: string;
+  secretAccessKey: string;
+  /** 关闭后使用 <bucket>.<endpoint> 虚拟主机形式 */
+  pathStyle: boolean;
+}
+
+export interface WebDavHeader {
+  name: string;
+  value: string;
 }
 
 export interface WebDavStateItem {
   lastLocalHash: string;
   lastRemoteHash: string;
   lastSyncAt: string;
+  /** 本设备从远端删除该账号的时间；非空时停止同步，直到重新上传 */
+  deletedAt?: string;
 }
 
 export interface WebDavSyncResult {
@@ -56,6 +110,14 @@ export interface WebDavRestoreAccount {
   updatedAt: string;
 }
 
+export interface WebDavEncryptionStatus {
+  encrypted: boolean;
+  keyId: string;
+  passphraseConfigured: boolean;
+  /** 本地口令能否解密远端数据 */
+  unlocked: boolean;
+}
+
 export interface WebDavRestoreResult {
   restored: string[];
   currentUser?: string;
@@ -201,4 +263,476 @@ export interface PoolInfoEntry {
   pool_type: string;
   up6_id: string;
   up6_ids?: string[];
+  all?: PoolItemInfo[];
+}
+
+export interface PoolItemInfo {
+  id: string;
+  name: string;
+  rarity: number;
+}
+
+export type GachaKind = 'character' | 'weapon';
+
+export type TimelineGranularity = 'day' | 'week' | 'month' | 'version';
+
+export interface TimelineBucket {
+  key: string;
+  startTs: number;
+  endTs: number;
+  pulls: number;
+  paidPulls: number;
+  freePulls: number;
+  count6: number;
+  count5: number;
+  cumulativePulls: number;
+  cumulativeCount6: number;
+  cumulativeAvgPity: number;
+  bannerStarts: string[];
+}
+
+export interface TimelineBanner {
+  poolId: string;
+  poolName: string;
+  poolType: string;
+  gachaType: GachaKind;
+  firstTs: number;
+  lastTs: number;
+  bucketKey: string;
+}
+
+export interface TimelineReport {
+  userKey: string;
+  granularity: TimelineGranularity;
+  undatedPulls: number;
+  buckets: TimelineBucket[];
+  banners: TimelineBanner[];
+}
+
+export type SpendCurrency = 'oroberyl' | 'arsenalTicket';
+
+export interface TopUpTier {
+  price: number;
+  amount: number;
+  currency: SpendCurrency;
+}
+
+export interface SpendOptions {
+  characterPullPrice?: number;
+  weaponPullPrice?: number;
+  poolPullPrices?: Record<string, number>;
+  topUpTiers?: TopUpTier[];
+}
+
+export interface SpendSummary {
+  gachaType: GachaKind;
+  currency: SpendCurrency;
+  totalPulls: number;
+  paidPulls: number;
+  freePulls: number;
+  amount: number;
+  money: number | null;
+}
+
+export interface BannerSpend extends SpendSummary {
+  poolKey: string;
+  poolId: string;
+  poolName: string;
+}
+
+export interface SpendReport {
+  userKey: string;
+  summaries: SpendSummary[];
+  banners: BannerSpend[];
+  totalMoney: number | null;
+}
+
+export interface KindStats {
+  gachaType: GachaKind;
+  totalPulls: number;
+  paidPulls: number;
+  freePulls: number;
+  count6: number;
+  count5: number;
+  freeCount6: number;
+  freeCount5: number;
+  rate6: number;
+  rate5: number;
+  avgPity: number | null;
+  luckPercentile: number | null;
+}
+
+export interface AccountStatsBreakdown {
+  userKey: string;
+  stats: KindStats[];
+}
+
+export interface AggregateReport {
+  userKeys: string[];
+  combined: KindStats[];
+  accounts: AccountStatsBreakdown[];
+}
+
+export interface CollectionItem {
+  gachaType: GachaKind;
+  itemId: string;
+  itemName: string;
+  rarity: number;
+  count: number;
+  potential: number;
+  overflow: number;
+  firstObtainedTs: number;
+  firstPoolId: string;
+  firstPoolName: string;
+  lastObtainedTs: number;
+}
+
+export interface MissingItem {
+  gachaType: GachaKind;
+  itemId: string;
+  itemName: string;
+  rarity: number;
+  poolIds: string[];
+}
+
+export interface CollectionReport {
+  userKey: string;
+  items: CollectionItem[];
+  missing: MissingItem[];
+}
+
+export interface FreePullRule {
+  countsSmallPity: boolean;
+  countsBigPity: boolean;
+}
+
+export interface FreePullPoolStats {
+  gachaType: GachaKind;
+  poolKey: string;
+  poolId: string;
+  poolName: string;
+  rule: FreePullRule;
+  totalPulls: number;
+  freePulls: number;
+  freeCount6: number;
+  freeCount5: number;
+  freeUp6: number;
+  paidPulls: number;
+  paidCount6: number;
+  paidAvgPity: number | null;
+  paidLuckPercentile: number | null;
+  bigPityMax: number | null;
+  bigPityCount: number | null;
+}
+
+export interface FreePullReport {
+  userKey: string;
+  pools: FreePullPoolStats[];
+}
+
+export interface RecordConflict {
+  poolKey: string;
+  seqId: string;
+  local: Record<string, unknown>;
+  remote: Record<string, unknown>;
+}
+
+export interface InterchangeExportResult {
+  path: string;
+  characterCount: number;
+  weaponCount: number;
+}
+
+export interface InterchangeImportAccount {
+  userKey: string;
+  characterAdded: number;
+  weaponAdded: number;
+  duplicates: number;
+  conflicts: RecordConflict[];
+}
+
+export interface InterchangeImportResult {
+  dryRun: boolean;
+  accounts: InterchangeImportAccount[];
+}
+
+export interface XlsxExportOptions {
+  includeSummary?: boolean;
+  freePullRules?: Record<string, FreePullRule>;
+}
+
+export interface XlsxExportResult {
+  path: string;
+  sheetCount: number;
+  characterCount: number;
+  weaponCount: number;
+}
+
+export type CsvDelimiter = "comma" | "tab";
+
+export type CsvColumn =
+  | "time"
+  | "timestamp"
+  | "gachaType"
+  | "poolKey"
+  | "poolId"
+  | "poolName"
+  | "bannerName"
+  | "itemId"
+  | "itemName"
+  | "rarity"
+  | "pity"
+  | "bigPity"
+  | "isNew"
+  | "isUp"
+  | "isOffBanner"
+  | "isFree"
+  | "seqId";
+
+export interface CsvExportOptions {
+  delimiter?: CsvDelimiter;
+  columns?: CsvColumn[];
+  gachaType?: "char" | "weapon" | "all";
+  pools?: string[];
+  rarities?: number[];
+  startTs?: number;
+  endTs?: number;
+  bom?: boolean;
+  mergePools?: boolean;
+  freePullRules?: Record<string, FreePullRule>;
+}
+
+export interface CsvExportResult {
+  files: string[];
+  rowCount: number;
+}
+
+export interface XlsxImportResult {
+  dryRun: boolean;
+  userKey: string;
+  sheets: string[];
+  characterRows: number;
+  weaponRows: number;
+  skippedRows: number;
+  reconstructed: number;
+  characterAdded: number;
+  weaponAdded: number;
+  duplicates: number;
+  conflicts: RecordConflict[];
+}
+
+export interface ArchiveExportOptions {
+  includeTokens?: boolean;
+  /** 保留同步凭据：WebDAV 密码、Bearer 令牌、自定义请求头的值、S3 密钥与代理密码；加密密钥总是去除 */
+  includeSecrets?: boolean;
+}
+
+export interface ArchiveExportResult {
+  path: string;
+  accounts: string[];
+  fileCount: number;
+}
+
+export type ArchiveImportMode = "merge" | "replace" | "skip";
+
+export interface ArchiveImportOptions {
+  defaultMode?: ArchiveImportMode;
+  modes?: Record<string, ArchiveImportMode>;
+  importPoolInfo?: boolean;
+}
+
+export interface ArchiveImportAccount {
+  userKey: string;
+  mode: ArchiveImportMode;
+  characterAdded: number;
+  weaponAdded: number;
+  duplicates: number;
+  conflicts: RecordConflict[];
+}
+
+export interface ArchiveImportResult {
+  createdAt: string;
+  accounts: ArchiveImportAccount[];
+  poolInfoImported: boolean;
+}
+
+export type TimestampPrecision = "second" | "minute" | "hour" | "day";
+
+export interface ShareExportOptions {
+  salt?: string;
+  precision?: TimestampPrecision;
+}
+
+export interface ShareExportResult {
+  path: string;
+  key: string;
+  characterCount: number;
+  weaponCount: number;
+}
+
+export type SummaryCardTheme = "system" | "light" | "dark";
+
+export interface SummaryCardOptions {
+  theme?: SummaryCardTheme;
+  anonymize?: boolean;
+  salt?: string;
+  freePullRules?: Record<string, FreePullRule>;
+}
+
+export interface SummaryCardResult {
+  path: string;
+  width: number;
+  height: number;
+}
+
+export interface LogScanOptions {
+  path?: string;
+  provider?: "hypergryph" | "gryphline";
+  userAgent?: string;
+}
+
+export interface LogScanResult {
+  path: string;
+  provider: "hypergryph" | "gryphline";
+  url: string;
+  u8Token: string;
+  poolId: string;
+  serverId: string;
+  channelLabel: string;
+  params: Record<string, string>;
+}
+
+export interface LogImportResult {
+  userKey: string;
+  uid: string;
+  roleId: string;
+  nickName: string;
+  serverId: string;
+  serverName: string;
+  provider: "hypergryph" | "gryphline";
+  channelLabel: string;
+  created: boolean;
+  logPath: string;
+}
+
+export type SchemaKind = "record" | "manifest" | "accountBundle";
+
+export interface SchemaIssue {
+  path: string;
+  message: string;
+}
+
+export interface SchemaValidationResult {
+  path: string;
+  kind: SchemaKind;
+  valid: boolean;
+  issues: SchemaIssue[];
+}
+
+export interface RecordFieldDiff {
+  field: string;
+  a: unknown;
+  b: unknown;
+}
+
+export interface ChangedRecord {
+  seqId: string;
+  fields: RecordFieldDiff[];
+  a: Record<string, unknown>;
+  b: Record<string, unknown>;
+}
+
+export interface PoolDiff {
+  gachaType: "character" | "weapon";
+  poolKey: string;
+  onlyInA: Record<string, unknown>[];
+  onlyInB: Record<string, unknown>[];
+  changed: ChangedRecord[];
+  sameCount: number;
+  duplicatesInA: Record<string, unknown>[];
+  duplicatesInB: Record<string, unknown>[];
+}
+
+export interface RecordDiffResult {
+  aPath: string;
+  bPath: string;
+  identical: boolean;
+  onlyInACount: number;
+  onlyInBCount: number;
+  changedCount: number;
+  sameCount: number;
+  duplicateCount: number;
+  pools: PoolDiff[];
+}
+
+export type SyncConflictKind = "identity" | "record";
+
+export type ConflictResolution =
+  | { choice: "keepLocal" }
+  | { choice: "keepRemote" }
+  | { choice: "edited"; value: unknown };
+
+export interface SyncConflict {
+  id: string;
+  kind: SyncConflictKind;
+  field: string;
+  poolKey: string;
+  seqId: string;
+  local: unknown;
+  remote: unknown;
+  resolution: ConflictResolution | null;
+}
+
+export interface ConflictSet {
+  accountKey: string;
+  detectedAt: string;
+  conflicts: SyncConflict[];
+}
+
+export interface WebDavEncryptionStatus {
+  encrypted: boolean;
+  keyId: string;
+  passphraseConfigured: boolean;
+  unlocked: boolean;
+}
+
+export interface WebDavEncryptionResult {
+  encrypted: boolean;
+  keyId: string;
+  rewritten: string[];
+}
+
+export interface WebDavVersion {
+  path: string;
+  updatedAt: string;
+  contentHash: string;
+  savedAt: string;
+  current: boolean;
+}
+
+export interface WebDavVersionRestoreResult {
+  accountKey: string;
+  path: string;
+  updatedAt: string;
+  contentHash: string;
+}
+
+export interface WebDavDeleteResult {
+  accountKey: string;
+  deletedAt: string;
+  deletedFiles: string[];
+}
+
+export interface WebDavPruneResult {
+  orphans: string[];
+  deleted: string[];
+}
+
+export interface DavResource {
+  href: string;
+  path: string;
+  etag: string | null;
+  contentLength: number | null;
+  lastModified: string | null;
+  isCollection: boolean;
 }
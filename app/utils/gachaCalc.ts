@@ -15,6 +15,22 @@ export const POOL_INFO_CHAR_POOL_KEYS = [
 ] as const;
 const SPECIAL_BIG_PITY_MAX = 120;
 
+// gachaTs 为毫秒字符串，兼容 10 位秒级时间戳；与后端 parse_gacha_ts 一致
+const gachaTsMs = (value?: string) => {
+  const raw = String(value || "").trim();
+  if (!raw || !/^\d+$/.test(raw)) return 0;
+
+  const num = Number(raw);
+  if (raw.length <= 10) return num * 1000;
+  if (raw.length >= 13) return num;
+  return num < 1e11 ? num * 1000 : num;
+};
+
+// 本地记录按 seqId 倒序存储，表格导入生成的非数字 seqId 会被排到最旧一端；
+// 翻转后再按 gachaTs 稳定排序，同一时间戳（十连）内保持原始顺序
+const toChronological = <T extends { gachaTs?: string }>(rawData: T[]) =>
+  [...rawData].reverse().sort((a, b) => gachaTsMs(a.gachaTs) - gachaTsMs(b.gachaTs));
+
 export const POOL_NAME_MAP: Record<string, string> = {
   "E_CharacterGachaPoolType_Special": "特许寻访",
   "E_CharacterGachaPoolType_Joint": "辉光庆典",
@@ -59,7 +75,7 @@ export const parseGachaParams = (uri: string): EndfieldGachaParams | null => {
 }
 
 export const analyzePoolData = (poolKey: string, rawData: EndFieldCharInfo[]): GachaStatistics => {
-  const data = [...rawData].reverse();
+  const data = toChronological(rawData);
 
   let count6 = 0;
   let count5 = 0;
@@ -107,7 +123,7 @@ export const analyzeSpecialPoolData = (
   rawData: EndFieldCharInfo[],
   poolInfoById: Record<string, { pool_name?: string; up6_id?: string }> = {},
 ): GachaStatistics[] => {
-  const data = [...rawData].reverse();
+  const data = toChronological(rawData);
 
   let globalSmallPity = 0;
 
@@ -225,7 +241,7 @@ export const analyzeJointPoolData = (
     { pool_name?: string; up6_id?: string; up6_ids?: string[] }
   > = {},
 ): GachaStatistics[] => {
-  const data = [...rawData].reverse();
+  const data = toChronological(rawData);
 
   const results: GachaStatistics[] = [];
   let current: GachaStatistics | null = null;
@@ -330,7 +346,7 @@ export const analyzeWeaponPoolData = (
   rawData: EndFieldWeaponInfo[],
   up6Id?: string,
 ): GachaStatistics => {
-  const data = [...rawData].reverse();
+  const data = toChronological(rawData);
 
   let count6 = 0;
   let count5 = 0;
@@ -1,3 +1,18 @@
+use std::path::Path;
+
+/// 分享卡片的中文子集字体由 fonts/subset_cjk.py 生成；缺失时卡片回退到系统中文字体
+const CJK_FONT: &str = "fonts/NotoSansSC-Card.otf";
+
 fn main() {
+    println!("cargo:rustc-check-cfg=cfg(embedded_cjk_font)");
+    println!("cargo:rerun-if-changed=fonts");
+    if Path::new(CJK_FONT).exists() {
+        println!("cargo:rustc-cfg=embedded_cjk_font");
+    } else {
+        println!(
+            "cargo:warning={} 缺失，分享卡片中的中文将依赖系统字体；请运行 python fonts/subset_cjk.py 生成",
+            CJK_FONT
+        );
+    }
     tauri_build::build()
 }
@@ -0,0 +1,84 @@
+#!/usr/bin/env python3
+"""生成分享卡片内嵌的中文子集字体 fonts/NotoSansSC-Card.otf
+
+字符集：GB2312 一级汉字 + 源码（卡片文案、默认卡池信息）中出现的全部汉字与全角标点。
+生成后重新编译即可内嵌（见 build.rs）；字体按 SIL OFL 1.1 授权，许可证一并保存。
+
+用法：
+    pip install fonttools
+    python fonts/subset_cjk.py                       # 下载 Noto Sans SC Regular 后裁剪
+    python fonts/subset_cjk.py --source NotoSansSC-Regular.otf
+"""
+
+import argparse
+import re
+import sys
+import urllib.request
+from pathlib import Path
+
+from fontTools import subset
+
+ROOT = Path(__file__).resolve().parent.parent
+FONTS_DIR = ROOT / "fonts"
+OUTPUT = FONTS_DIR / "NotoSansSC-Card.otf"
+LICENSE_OUTPUT = FONTS_DIR / "LICENSE-NotoSansSC.txt"
+SOURCE_URL = "https://github.com/notofonts/noto-cjk/raw/main/Sans/SubsetOTF/SC/NotoSansSC-Regular.otf"
+LICENSE_URL = "https://github.com/notofonts/noto-cjk/raw/main/Sans/LICENSE"
+# 卡片文案与卡池 / 角色 / 武器名称所在的源码
+SOURCE_FILES = ["src/card.rs", "src/stats.rs", "src/lib.rs"]
+CJK_PATTERN = re.compile(r"[　-〿一-鿿＀-￯]")
+
+
+def gb2312_level1():
+    chars = []
+    for high in range(0xB0, 0xD8):
+        for low in range(0xA1, 0xFF):
+            try:
+                chars.append(bytes([high, low]).decode("gb2312"))
+            except UnicodeDecodeError:
+                continue
+    return chars
+
+
+def source_chars():
+    chars = set()
+    for name in SOURCE_FILES:
+        text = (ROOT / name).read_text(encoding="utf-8")
+        chars.update(CJK_PATTERN.findall(text))
+    return chars
+
+
+def download(url, target):
+    print(f"downloading {url}", file=sys.stderr)
+    with urllib.request.urlopen(url) as response:
+        target.write_bytes(response.read())
+
+
+def main():
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--source", type=Path, help="本地 Noto Sans SC Regular（OTF/TTF）")
+    args = parser.parse_args()
+
+    source = args.source
+    if source is None:
+        source = FONTS_DIR / "NotoSansSC-Regular.full.otf"
+        if not source.exists():
+            download(SOURCE_URL, source)
+    if not LICENSE_OUTPUT.exists():
+        download(LICENSE_URL, LICENSE_OUTPUT)
+
+    text = "".join(sorted(set(gb2312_level1()) | source_chars()))
+    options = subset.Options()
+    options.layout_features = []
+    options.name_IDs = ["*"]
+    options.notdef_outline = True
+    font = subset.load_font(str(source), options)
+    subsetter = subset.Subsetter(options)
+    subsetter.populate(text=text)
+    subsetter.subset(font)
+    subset.save_font(font, str(OUTPUT), options)
+    print(f"{OUTPUT} ({len(text)} chars, {OUTPUT.stat().st_size} bytes)")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,731 @@
+//! 抽卡统计分享卡片（PNG），使用 tiny-skia 光栅化，不依赖 WebView
+//!
+//! 拉丁字符与数字使用内嵌的 DejaVu Sans，中文使用内嵌的 Noto Sans SC 子集
+//! （fonts/subset_cjk.py 生成），子集缺字时再从系统字体中回退查找。
+
+use crate::share::{load_or_create_salt, pseudonym};
+use crate::stats::{
+    annotate_pity, is_off_banner, load_account_pulls, local_datetime, FreePullRule, GachaKind,
+    PityPull, PityRules, PoolCatalog, JOINT_POOL_KEY, SPECIAL_POOL_KEY,
+};
+use crate::webdav::{get_user_key, load_config_data};
+use rusttype::{point, Font, Scale};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use tauri::command;
+use tiny_skia::{
+    Color, FillRule, Paint, PathBuilder, Pixmap, PremultipliedColorU8, Rect, Transform,
+};
+
+const FONT_REGULAR: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+const FONT_BOLD: &[u8] = include_bytes!("../fonts/DejaVuSans-Bold.ttf");
+#[cfg(embedded_cjk_font)]
+const FONT_CJK: Option<&[u8]> = Some(include_bytes!("../fonts/NotoSansSC-Card.otf"));
+#[cfg(not(embedded_cjk_font))]
+const FONT_CJK: Option<&[u8]> = None;
+
+// 常见系统中文字体，按顺序尝试
+const CJK_FONT_CANDIDATES: [&str; 9] = [
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\simhei.ttf",
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/STHeiti Medium.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+];
+
+const CARD_WIDTH: u32 = 800;
+const PADDING: f32 = 32.0;
+const POOL_ROW_HEIGHT: f32 = 44.0;
+const SIX_STAR_ROW_HEIGHT: f32 = 34.0;
+const MAX_SIX_STAR_ROWS: usize = 10;
+
+const RARITY6_COLOR: u32 = 0xFF8904;
+const RARITY5_COLOR: u32 = 0xFDC700;
+const RARITY4_COLOR: u32 = 0xAD46FF;
+const PRIMARY_COLOR: u32 = 0x00C16A;
+const OFF_BANNER_COLOR: u32 = 0xFB2C36;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CardTheme {
+    /// 跟随应用设置（config.json 中的 theme），system 时使用亮色
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryCardOptions {
+    #[serde(default)]
+    theme: CardTheme,
+    /// 使用假名代替昵称与 roleId
+    #[serde(default)]
+    anonymize: bool,
+    #[serde(default)]
+    salt: Option<String>,
+    #[serde(default)]
+    free_pull_rules: HashMap<String, FreePullRule>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryCardResult {
+    path: String,
+    width: u32,
+    height: u32,
+}
+
+struct Palette {
+    background: u32,
+    surface: u32,
+    border: u32,
+    text: u32,
+    highlighted: u32,
+    muted: u32,
+}
+
+// 与 Nuxt UI 默认 neutral(slate) 配色一致
+const LIGHT_PALETTE: Palette = Palette {
+    background: 0xFFFFFF,
+    surface: 0xF8FAFC,
+    border: 0xE2E8F0,
+    text: 0x334155,
+    highlighted: 0x0F172A,
+    muted: 0x64748B,
+};
+
+const DARK_PALETTE: Palette = Palette {
+    background: 0x0F172A,
+    surface: 0x1E293B,
+    border: 0x334155,
+    text: 0xE2E8F0,
+    highlighted: 0xFFFFFF,
+    muted: 0x94A3B8,
+};
+
+struct PoolSummary {
+    name: String,
+    total: u32,
+    count6: u32,
+    count5: u32,
+    current_pity: u32,
+}
+
+struct SixStar {
+    name: String,
+    pool_name: String,
+    pity: u32,
+    is_up: bool,
+    is_off_banner: bool,
+    gacha_ts: i64,
+}
+
+struct CardData {
+    title: String,
+    subtitle: String,
+    pools: Vec<PoolSummary>,
+    six_stars: Vec<SixStar>,
+    counts: [u32; 3],
+}
+
+fn rgb(value: u32) -> Color {
+    Color::from_rgba8(
+        ((value >> 16) & 0xFF) as u8,
+        ((value >> 8) & 0xFF) as u8,
+        (value & 0xFF) as u8,
+        255,
+    )
+}
+
+fn character_pool_name(pool_key: &str) -> &str {
+    match pool_key {
+        SPECIAL_POOL_KEY => "特许寻访",
+        JOINT_POOL_KEY => "辉光庆典",
+        "E_CharacterGachaPoolType_Standard" => "基础寻访",
+        "E_CharacterGachaPoolType_Beginner" => "启程寻访",
+        other => other,
+    }
+}
+
+/// 当前垫抽：最后一次计入保底的抽数，出 6★ 后归零
+fn current_pity(items: &[PityPull]) -> u32 {
+    let mut pity = 0;
+    for item in items {
+        if item.counts_pity {
+            pity = if item.pull.rarity == 6 { 0 } else { item.pity };
+        }
+    }
+    pity
+}
+
+fn build_card_data(
+    user_key: &str,
+    options: &SummaryCardOptions,
+) -> Result<(CardData, String), String> {
+    let config = load_config_data()?;
+    let user = config
+        .users
+        .iter()
+        .find(|item| get_user_key(item) == user_key)
+        .ok_or_else(|| format!("Account not found: {}", user_key))?;
+    let role = user.role_id.clone().unwrap_or_default();
+
+    let (title, subtitle) = if options.anonymize {
+        let salt = match options.salt.as_deref().map(str::trim) {
+            Some(salt) if !salt.is_empty() => salt.to_string(),
+            _ => load_or_create_salt()?,
+        };
+        (
+            pseudonym(&salt, "nickName", &role.nick_name),
+            pseudonym(&salt, "roleId", &role.role_id),
+        )
+    } else {
+        (role.nick_name.clone(), role.role_id.clone())
+    };
+    let title = if title.is_empty() {
+        user_key.to_string()
+    } else {
+        title
+    };
+
+    let account = load_account_pulls(user_key)?;
+    let catalog = PoolCatalog::load()?;
+    let rules = PityRules::new(options.free_pull_rules.clone());
+
+    let mut pools = Vec::new();
+    let mut all_items = Vec::new();
+    for kind in [GachaKind::Character, GachaKind::Weapon] {
+        for (pool_key, pulls) in account.pools(kind).iter() {
+            if pulls.is_empty() {
+                continue;
+            }
+            let items = annotate_pity(kind, pool_key, pulls, &catalog, &rules);
+            let name = match kind {
+                GachaKind::Character => character_pool_name(pool_key).to_string(),
+                GachaKind::Weapon => catalog
+                    .get(pool_key)
+                    .map(|info| info.pool_name.clone())
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| pulls[0].pool_name.clone()),
+            };
+            pools.push(PoolSummary {
+                name,
+                total: items.len() as u32,
+                count6: items.iter().filter(|item| item.pull.rarity == 6).count() as u32,
+                count5: items.iter().filter(|item| item.pull.rarity == 5).count() as u32,
+                current_pity: current_pity(&items),
+            });
+            all_items.extend(items);
+        }
+    }
+
+    let mut counts = [0u32; 3];
+    for item in all_items.iter() {
+        match item.pull.rarity {
+            6 => counts[0] += 1,
+            5 => counts[1] += 1,
+            _ => counts[2] += 1,
+        }
+    }
+
+    let mut six_stars = all_items
+        .iter()
+        .filter(|item| item.pull.rarity == 6)
+        .map(|item| SixStar {
+            name: item.pull.item_name.clone(),
+            pool_name: catalog
+                .get(&item.pull.pool_id)
+                .map(|info| info.pool_name.clone())
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| item.pull.pool_name.clone()),
+            pity: item.pity,
+            is_up: item.is_up,
+            is_off_banner: is_off_banner(item, &catalog),
+            gacha_ts: item.pull.gacha_ts,
+        })
+        .collect::<Vec<_>>();
+    six_stars.sort_by_key(|item| std::cmp::Reverse(item.gacha_ts));
+    six_stars.truncate(MAX_SIX_STAR_ROWS);
+
+    Ok((
+        CardData {
+            title,
+            subtitle,
+            pools,
+            six_stars,
+            counts,
+        },
+        config.theme().to_string(),
+    ))
+}
+
+struct TextRenderer {
+    regular: Vec<Font<'static>>,
+    bold: Vec<Font<'static>>,
+}
+
+impl TextRenderer {
+    fn new() -> Result<Self, String> {
+        let regular = Font::try_from_bytes(FONT_REGULAR).ok_or("Invalid embedded font")?;
+        let bold = Font::try_from_bytes(FONT_BOLD).ok_or("Invalid embedded font")?;
+        let fallback = CJK_FONT_CANDIDATES.iter().find_map(|path| {
+            let data = fs::read(path).ok()?;
+            Font::try_from_vec_and_index(data, 0)
+        });
+
+        let mut renderer = Self {
+            regular: vec![regular],
+            bold: vec![bold],
+        };
+        if let Some(cjk) = FONT_CJK.and_then(Font::try_from_bytes) {
+            renderer.regular.push(cjk.clone());
+            renderer.bold.push(cjk);
+        }
+        if let Some(fallback) = fallback {
+            renderer.regular.push(fallback.clone());
+            renderer.bold.push(fallback);
+        }
+        Ok(renderer)
+    }
+
+    fn font_for(&self, ch: char, bold: bool) -> &Font<'static> {
+        let fonts = if bold { &self.bold } else { &self.regular };
+        fonts
+            .iter()
+            .find(|font| font.glyph(ch).id().0 != 0)
+            .unwrap_or(&fonts[0])
+    }
+
+    fn measure(&self, text: &str, size: f32, bold: bool) -> f32 {
+        let scale = Scale::uniform(size);
+        text.chars()
+            .map(|ch| {
+                self.font_for(ch, bold)
+                    .glyph(ch)
+                    .scaled(scale)
+                    .h_metrics()
+                    .advance_width
+            })
+            .sum()
+    }
+
+    /// 以基线 (x, y) 绘制文字，返回绘制宽度
+    fn draw(
+        &self,
+        pixmap: &mut Pixmap,
+        text: &str,
+        (x, y): (f32, f32),
+        size: f32,
+        color: u32,
+        bold: bool,
+    ) -> f32 {
+        let scale = Scale::uniform(size);
+        let (width, height) = (pixmap.width(), pixmap.height());
+        let pixels = pixmap.pixels_mut();
+        let mut cursor = x;
+
+        for ch in text.chars() {
+            let font = self.font_for(ch, bold);
+            let glyph = font.glyph(ch).scaled(scale);
+            let advance = glyph.h_metrics().advance_width;
+            let positioned = glyph.positioned(point(cursor, y));
+            if let Some(bounds) = positioned.pixel_bounding_box() {
+                positioned.draw(|gx, gy, coverage| {
+                    let px = bounds.min.x + gx as i32;
+                    let py = bounds.min.y + gy as i32;
+                    if px < 0 || py < 0 || px >= width as i32 || py >= height as i32 {
+                        return;
+                    }
+                    let index = (py as u32 * width + px as u32) as usize;
+                    pixels[index] = blend(pixels[index], color, coverage);
+                });
+            }
+            cursor += advance;
+        }
+        cursor - x
+    }
+}
+
+fn blend(dst: PremultipliedColorU8, color: u32, coverage: f32) -> PremultipliedColorU8 {
+    let alpha = coverage.clamp(0.0, 1.0);
+    let mix = |src: u32, dst: u8| -> u8 {
+        (src as f32 * alpha + dst as f32 * (1.0 - alpha)).round() as u8
+    };
+    let r = mix((color >> 16) & 0xFF, dst.red());
+    let g = mix((color >> 8) & 0xFF, dst.green());
+    let b = mix(color & 0xFF, dst.blue());
+    let a = (255.0 * alpha + dst.alpha() as f32 * (1.0 - alpha)).round() as u8;
+    PremultipliedColorU8::from_rgba(r.min(a), g.min(a), b.min(a), a).unwrap_or(dst)
+}
+
+fn fill_round_rect(pixmap: &mut Pixmap, rect: (f32, f32, f32, f32), radius: f32, color: u32) {
+    let (x, y, w, h) = rect;
+    let r = radius.min(w / 2.0).min(h / 2.0);
+    let mut builder = PathBuilder::new();
+    builder.move_to(x + r, y);
+    builder.line_to(x + w - r, y);
+    builder.quad_to(x + w, y, x + w, y + r);
+    builder.line_to(x + w, y + h - r);
+    builder.quad_to(x + w, y + h, x + w - r, y + h);
+    builder.line_to(x + r, y + h);
+    builder.quad_to(x, y + h, x, y + h - r);
+    builder.line_to(x, y + r);
+    builder.quad_to(x, y, x + r, y);
+    builder.close();
+    let Some(path) = builder.finish() else {
+        return;
+    };
+    let mut paint = Paint::default();
+    paint.set_color(rgb(color));
+    paint.anti_alias = true;
+    pixmap.fill_path(
+        &path,
+        &paint,
+        FillRule::Winding,
+        Transform::identity(),
+        None,
+    );
+}
+
+fn fill_rect(pixmap: &mut Pixmap, rect: (f32, f32, f32, f32), color: u32) {
+    let Some(rect) = Rect::from_xywh(rect.0, rect.1, rect.2, rect.3) else {
+        return;
+    };
+    let mut paint = Paint::default();
+    paint.set_color(rgb(color));
+    pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+}
+
+fn render_card(data: &CardData, palette: &Palette, text: &TextRenderer) -> Result<Pixmap, String> {
+    let content_width = CARD_WIDTH as f32 - PADDING * 2.0;
+    let pools_height = data.pools.len() as f32 * POOL_ROW_HEIGHT;
+    let six_star_height = data.six_stars.len().max(1) as f32 * SIX_STAR_ROW_HEIGHT;
+    let height = PADDING + 80.0 // 标题
+        + 40.0 + pools_height // 卡池
+        + 24.0 + 40.0 + six_star_height // 最近 6★
+        + 24.0 + 40.0 + 48.0 // 星级分布
+        + PADDING;
+
+    let mut pixmap =
+        Pixmap::new(CARD_WIDTH, height.ceil() as u32).ok_or("Unable to allocate image")?;
+    pixmap.fill(rgb(palette.background));
+
+    let left = PADDING;
+    let right = CARD_WIDTH as f32 - PADDING;
+    let mut y = PADDING;
+
+    // 标题
+    text.draw(
+        &mut pixmap,
+        &data.title,
+        (left, y + 34.0),
+        30.0,
+        palette.highlighted,
+        true,
+    );
+    if !data.subtitle.is_empty() {
+        text.draw(
+            &mut pixmap,
+            &data.subtitle,
+            (left, y + 62.0),
+            16.0,
+            palette.muted,
+            false,
+        );
+    }
+    let brand = "Endfield Gacha";
+    let brand_width = text.measure(brand, 16.0, true);
+    text.draw(
+        &mut pixmap,
+        brand,
+        (right - brand_width, y + 34.0),
+        16.0,
+        PRIMARY_COLOR,
+        true,
+    );
+    y += 80.0;
+
+    // 卡池汇总
+    text.draw(
+        &mut pixmap,
+        "卡池统计",
+        (left, y + 26.0),
+        18.0,
+        palette.highlighted,
+        true,
+    );
+    y += 40.0;
+    let columns = [
+        ("总抽数", 0.42),
+        ("6★", 0.58),
+        ("5★", 0.70),
+        ("当前垫抽", 0.84),
+    ];
+    for pool in data.pools.iter() {
+        fill_round_rect(
+            &mut pixmap,
+            (left, y, content_width, POOL_ROW_HEIGHT - 6.0),
+            8.0,
+            palette.surface,
+        );
+        let baseline = y + POOL_ROW_HEIGHT / 2.0 + 3.0;
+        text.draw(
+            &mut pixmap,
+            &pool.name,
+            (left + 14.0, baseline),
+            16.0,
+            palette.text,
+            true,
+        );
+        let values = [
+            (pool.total.to_string(), palette.text),
+            (pool.count6.to_string(), RARITY6_COLOR),
+            (pool.count5.to_string(), RARITY5_COLOR),
+            (pool.current_pity.to_string(), PRIMARY_COLOR),
+        ];
+        for ((label, ratio), (value, color)) in columns.iter().zip(values.iter()) {
+            let x = left + content_width * ratio;
+            let label_width = text.draw(
+                &mut pixmap,
+                label,
+                (x, baseline),
+                12.0,
+                palette.muted,
+                false,
+            );
+            text.draw(
+                &mut pixmap,
+                value,
+                (x + label_width + 6.0, baseline),
+                16.0,
+                *color,
+                true,
+            );
+        }
+        y += POOL_ROW_HEIGHT;
+    }
+
+    // 最近 6★
+    y += 24.0;
+    text.draw(
+        &mut pixmap,
+        "最近 6★",
+        (left, y + 26.0),
+        18.0,
+        palette.highlighted,
+        true,
+    );
+    y += 40.0;
+    if data.six_stars.is_empty() {
+        text.draw(
+            &mut pixmap,
+            "暂无 6★ 记录",
+            (left, y + 22.0),
+            14.0,
+            palette.muted,
+            false,
+        );
+    }
+    for item in data.six_stars.iter() {
+        let baseline = y + 22.0;
+        let date = local_datetime(item.gacha_ts)
+            .map(|time| time.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        text.draw(
+            &mut pixmap,
+            &item.name,
+            (left, baseline),
+            16.0,
+            RARITY6_COLOR,
+            true,
+        );
+        text.draw(
+            &mut pixmap,
+            &item.pool_name,
+            (left + content_width * 0.32, baseline),
+            14.0,
+            palette.text,
+            false,
+        );
+        text.draw(
+            &mut pixmap,
+            &date,
+            (left + content_width * 0.62, baseline),
+            14.0,
+            palette.muted,
+            false,
+        );
+
+        let tag = if item.is_up {
+            Some(("UP", PRIMARY_COLOR))
+        } else if item.is_off_banner {
+            Some(("歪", OFF_BANNER_COLOR))
+        } else {
+            None
+        };
+        if let Some((tag, color)) = tag {
+            text.draw(
+                &mut pixmap,
+                tag,
+                (left + content_width * 0.80, baseline),
+                13.0,
+                color,
+                true,
+            );
+        }
+
+        let pity = if item.pity > 0 {
+            format!("{} 抽", item.pity)
+        } else {
+            "免费".to_string()
+        };
+        let pity_width = text.measure(&pity, 16.0, true);
+        text.draw(
+            &mut pixmap,
+            &pity,
+            (right - pity_width, baseline),
+            16.0,
+            palette.highlighted,
+            true,
+        );
+        fill_rect(
+            &mut pixmap,
+            (left, y + SIX_STAR_ROW_HEIGHT - 1.0, content_width, 1.0),
+            palette.border,
+        );
+        y += SIX_STAR_ROW_HEIGHT;
+    }
+    if data.six_stars.is_empty() {
+        y += SIX_STAR_ROW_HEIGHT;
+    }
+
+    // 星级分布
+    y += 24.0;
+    text.draw(
+        &mut pixmap,
+        "星级分布",
+        (left, y + 26.0),
+        18.0,
+        palette.highlighted,
+        true,
+    );
+    y += 40.0;
+    let total = data.counts.iter().sum::<u32>();
+    fill_round_rect(
+        &mut pixmap,
+        (left, y, content_width, 16.0),
+        8.0,
+        palette.surface,
+    );
+    if total > 0 {
+        let colors = [RARITY6_COLOR, RARITY5_COLOR, RARITY4_COLOR];
+        let mut x = left;
+        for (count, color) in data.counts.iter().zip(colors.iter()) {
+            let width = content_width * *count as f32 / total as f32;
+            if width > 0.0 {
+                fill_rect(&mut pixmap, (x, y, width, 16.0), *color);
+            }
+            x += width;
+        }
+    }
+    let legend = [
+        ("6★", RARITY6_COLOR),
+        ("5★", RARITY5_COLOR),
+        ("4★", RARITY4_COLOR),
+    ];
+    let mut x = left;
+    for ((label, color), count) in legend.iter().zip(data.counts.iter()) {
+        let percent = if total > 0 {
+            *count as f32 * 100.0 / total as f32
+        } else {
+            0.0
+        };
+        let entry = format!("{} {} ({:.1}%)", label, count, percent);
+        fill_round_rect(&mut pixmap, (x, y + 29.0, 10.0, 10.0), 5.0, *color);
+        let width = text.draw(
+            &mut pixmap,
+            &entry,
+            (x + 16.0, y + 39.0),
+            13.0,
+            palette.text,
+            false,
+        );
+        x += width + 36.0;
+    }
+
+    Ok(pixmap)
+}
+
+#[command]
+pub fn export_summary_card(
+    user_key: String,
+    path: String,
+    options: Option<SummaryCardOptions>,
+) -> Result<SummaryCardResult, String> {
+    let path = path.trim().to_string();
+    if path.is_empty() {
+        return Err("Export path cannot be empty".into());
+    }
+    let options = options.unwrap_or_default();
+    let (data, config_theme) = build_card_data(user_key.trim(), &options)?;
+
+    let palette = match options.theme {
+        CardTheme::Light => &LIGHT_PALETTE,
+        CardTheme::Dark => &DARK_PALETTE,
+        CardTheme::System if config_theme == "dark" => &DARK_PALETTE,
+        CardTheme::System => &LIGHT_PALETTE,
+    };
+    let text = TextRenderer::new()?;
+    let pixmap = render_card(&data, palette, &text)?;
+    pixmap.save_png(&path).map_err(|e| e.to_string())?;
+
+    Ok(SummaryCardResult {
+        path,
+        width: pixmap.width(),
+        height: pixmap.height(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(embedded_cjk_font)]
+    fn has_glyph(font: &Font<'_>, ch: char) -> bool {
+        font.glyph(ch).id().0 != 0
+    }
+
+    #[test]
+    fn latin_digits_and_stars_use_dejavu() {
+        let renderer = TextRenderer::new().unwrap();
+        for ch in "Endfield Gacha 0123456789 ★".chars() {
+            assert!(std::ptr::eq(
+                renderer.font_for(ch, false),
+                &renderer.regular[0]
+            ));
+        }
+    }
+
+    #[cfg(embedded_cjk_font)]
+    #[test]
+    fn card_labels_are_covered_by_embedded_cjk_font() {
+        let cjk = Font::try_from_bytes(FONT_CJK.unwrap()).unwrap();
+        let labels =
+            "特许寻访辉光庆典基础寻访启程寻访卡池统计总抽数当前垫抽最近暂无记录歪抽免费星级分布";
+        for ch in labels.chars() {
+            assert!(has_glyph(&cjk, ch), "missing glyph {}", ch);
+        }
+        let renderer = TextRenderer::new().unwrap();
+        assert!(std::ptr::eq(
+            renderer.font_for('寻', true),
+            &renderer.bold[1]
+        ));
+    }
+}
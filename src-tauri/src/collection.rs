@@ -0,0 +1,308 @@
+use crate::stats::{compare_pull_time, load_account_pulls, GachaKind, PoolCatalog, PullRecord};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use tauri::command;
+
+/// 潜能（武器为精炼）上限，第 1 份为本体，之后每份重复提升 1 级
+const MAX_POTENTIAL: u32 = 5;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionItem {
+    gacha_type: GachaKind,
+    item_id: String,
+    item_name: String,
+    rarity: u8,
+    count: u32,
+    potential: u32,
+    overflow: u32,
+    first_obtained_ts: i64,
+    first_pool_id: String,
+    first_pool_name: String,
+    last_obtained_ts: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingItem {
+    gacha_type: GachaKind,
+    item_id: String,
+    item_name: String,
+    rarity: u8,
+    pool_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionReport {
+    user_key: String,
+    items: Vec<CollectionItem>,
+    missing: Vec<MissingItem>,
+}
+
+fn item_key(pull: &PullRecord) -> String {
+    if pull.item_id.is_empty() {
+        pull.item_name.clone()
+    } else {
+        pull.item_id.clone()
+    }
+}
+
+fn build_items(pulls: &[PullRecord], catalog: &PoolCatalog) -> Vec<CollectionItem> {
+    let mut items: BTreeMap<(GachaKind, String), CollectionItem> = BTreeMap::new();
+
+    for pull in pulls {
+        let key = item_key(pull);
+        if key.is_empty() {
+            continue;
+        }
+        let item = items
+            .entry((pull.kind, key.clone()))
+            .or_insert_with(|| CollectionItem {
+                gacha_type: pull.kind,
+                item_id: key,
+                item_name: pull.item_name.clone(),
+                rarity: pull.rarity,
+                count: 0,
+                potential: 0,
+                overflow: 0,
+                first_obtained_ts: pull.gacha_ts,
+                first_pool_id: pull.pool_id.clone(),
+                first_pool_name: catalog
+                    .get(&pull.pool_id)
+                    .map(|info| info.pool_name.clone())
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| pull.pool_name.clone()),
+                last_obtained_ts: pull.gacha_ts,
+            });
+        item.count += 1;
+        item.last_obtained_ts = item.last_obtained_ts.max(pull.gacha_ts);
+        if item.item_name.is_empty() {
+            item.item_name = pull.item_name.clone();
+        }
+    }
+
+    let mut result = items
+        .into_values()
+        .map(|mut item| {
+            let copies = item.count.saturating_sub(1);
+            item.potential = copies.min(MAX_POTENTIAL);
+            item.overflow = copies.saturating_sub(MAX_POTENTIAL);
+            item
+        })
+        .collect::<Vec<_>>();
+    result.sort_by(|a, b| {
+        a.gacha_type
+            .cmp(&b.gacha_type)
+            .then_with(|| b.rarity.cmp(&a.rarity))
+            .then_with(|| a.first_obtained_ts.cmp(&b.first_obtained_ts))
+    });
+    result
+}
+
+fn build_missing(items: &[CollectionItem], catalog: &PoolCatalog) -> Vec<MissingItem> {
+    let owned: HashSet<(GachaKind, &str)> = items
+        .iter()
+        .map(|item| (item.gacha_type, item.item_id.as_str()))
+        .collect();
+    let mut missing: BTreeMap<(GachaKind, String), MissingItem> = BTreeMap::new();
+
+    for entry in catalog.entries() {
+        let Some(kind) = entry.gacha_kind() else {
+            continue;
+        };
+        for pool_item in entry.all.iter() {
+            let id = pool_item.id.trim();
+            if id.is_empty() || owned.contains(&(kind, id)) {
+                continue;
+            }
+            let item = missing
+                .entry((kind, id.to_string()))
+                .or_insert_with(|| MissingItem {
+                    gacha_type: kind,
+                    item_id: id.to_string(),
+                    item_name: pool_item.name.clone(),
+                    rarity: pool_item.rarity,
+                    pool_ids: Vec::new(),
+                });
+            if !item.pool_ids.contains(&entry.pool_id) {
+                item.pool_ids.push(entry.pool_id.clone());
+            }
+        }
+    }
+
+    let mut result = missing.into_values().collect::<Vec<_>>();
+    result.sort_by(|a, b| {
+        a.gacha_type
+            .cmp(&b.gacha_type)
+            .then_with(|| b.rarity.cmp(&a.rarity))
+            .then_with(|| a.item_id.cmp(&b.item_id))
+    });
+    result
+}
+
+#[command]
+pub fn stats_collection(user_key: String) -> Result<CollectionReport, String> {
+    let account = load_account_pulls(&user_key)?;
+    let catalog = PoolCatalog::load()?;
+
+    let mut pulls = account
+        .character
+        .values()
+        .chain(account.weapon.values())
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>();
+    pulls.sort_by(compare_pull_time);
+
+    let items = build_items(&pulls, &catalog);
+    let missing = build_missing(&items, &catalog);
+    Ok(CollectionReport {
+        user_key: user_key.trim().to_string(),
+        items,
+        missing,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pull(kind: GachaKind, item_id: &str, rarity: u8, index: i64) -> PullRecord {
+        PullRecord {
+            kind,
+            pool_key: "E_CharacterGachaPoolType_Special".into(),
+            pool_id: "special_1_0_1".into(),
+            pool_name: "旧卡池名".into(),
+            item_id: item_id.into(),
+            item_name: format!("{}_name", item_id),
+            rarity,
+            is_free: false,
+            is_new: false,
+            gacha_ts: 1_768_000_000_000 + index,
+            seq_id: index.to_string(),
+        }
+    }
+
+    fn catalog() -> PoolCatalog {
+        PoolCatalog::from_value(&json!([
+            {
+                "pool_id": "special_1_0_1",
+                "pool_gacha_type": "char",
+                "pool_name": "限定寻访",
+                "all": [
+                    { "id": "chr_a", "name": "A", "rarity": 6 },
+                    { "id": "chr_b", "name": "B", "rarity": 6 },
+                    { "id": "chr_c", "name": "C", "rarity": 5 }
+                ]
+            },
+            {
+                "pool_id": "special_1_1_1",
+                "pool_gacha_type": "char",
+                "all": [
+                    { "id": "chr_b", "name": "B", "rarity": 6 },
+                    { "id": " ", "name": "空", "rarity": 4 }
+                ]
+            },
+            {
+                "pool_id": "weponbox_1_0_1",
+                "pool_gacha_type": "weapon",
+                "all": [{ "id": "chr_a", "name": "同名武器", "rarity": 6 }]
+            },
+            {
+                "pool_id": "unknown_pool",
+                "pool_gacha_type": "other",
+                "all": [{ "id": "chr_z", "name": "Z", "rarity": 6 }]
+            }
+        ]))
+    }
+
+    #[test]
+    fn duplicates_raise_potential_up_to_the_cap() {
+        let mut pulls = (0..8)
+            .map(|index| pull(GachaKind::Character, "chr_a", 6, index))
+            .collect::<Vec<_>>();
+        pulls.push(pull(GachaKind::Character, "chr_c", 5, 8));
+        pulls.push(pull(GachaKind::Character, "chr_c", 5, 9));
+        pulls.push(pull(GachaKind::Weapon, "wpn_x", 4, 10));
+
+        let items = build_items(&pulls, &catalog());
+        let summary = items
+            .iter()
+            .map(|item| {
+                (
+                    item.item_id.as_str(),
+                    item.count,
+                    item.potential,
+                    item.overflow,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [("chr_a", 8, 5, 2), ("chr_c", 2, 1, 0), ("wpn_x", 1, 0, 0)]
+        );
+
+        let first = &items[0];
+        assert_eq!(first.first_obtained_ts, 1_768_000_000_000);
+        assert_eq!(first.last_obtained_ts, 1_768_000_000_007);
+        assert_eq!(first.first_pool_name, "限定寻访");
+    }
+
+    #[test]
+    fn items_without_id_fall_back_to_name() {
+        let mut nameless = pull(GachaKind::Character, "", 6, 0);
+        nameless.item_name = "无 ID".into();
+        let mut empty = pull(GachaKind::Character, "", 6, 1);
+        empty.item_name = String::new();
+
+        let items = build_items(
+            &[nameless.clone(), nameless, empty],
+            &PoolCatalog::default(),
+        );
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].item_id, "无 ID");
+        assert_eq!(items[0].potential, 1);
+        assert_eq!(items[0].first_pool_name, "旧卡池名");
+    }
+
+    #[test]
+    fn missing_items_are_grouped_by_kind_and_pool() {
+        let catalog = catalog();
+        let items = build_items(&[pull(GachaKind::Character, "chr_a", 6, 0)], &catalog);
+
+        let missing = build_missing(&items, &catalog);
+        let summary = missing
+            .iter()
+            .map(|item| {
+                (
+                    item.gacha_type,
+                    item.item_id.as_str(),
+                    item.pool_ids.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (
+                    GachaKind::Character,
+                    "chr_b",
+                    vec!["special_1_0_1".to_string(), "special_1_1_1".to_string()]
+                ),
+                (
+                    GachaKind::Character,
+                    "chr_c",
+                    vec!["special_1_0_1".to_string()]
+                ),
+                // 同 ID 的武器不因拥有角色而算作已拥有
+                (
+                    GachaKind::Weapon,
+                    "chr_a",
+                    vec!["weponbox_1_0_1".to_string()]
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,511 @@
+//! WebDAV 同步冲突：以结构化对象保存到 webdavConflicts/<key>.conflicts.json，
+//! 用户逐条选择保留本地 / 远端或手动编辑后，下次 webdav_sync_account 自动应用。
+
+use crate::value_to_seqid;
+use crate::webdav::{
+    conflict_dir, merge_record_maps_reporting, now_iso_string, AccountBundle, RecordConflict,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use tauri::command;
+
+const CONFLICT_FILE_SUFFIX: &str = ".conflicts.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictKind {
+    /// 账号身份字段不一致（key / uid / provider / roleId）
+    Identity,
+    /// 同一 seqId 的抽卡记录字段不一致
+    Record,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "choice", rename_all = "camelCase")]
+pub enum ConflictResolution {
+    KeepLocal,
+    KeepRemote,
+    /// 手动编辑后的值：记录冲突为完整记录对象，身份冲突为字符串
+    Edited {
+        value: Value,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    id: String,
+    kind: ConflictKind,
+    /// 身份冲突为字段名；记录冲突为 character / weapon
+    #[serde(default)]
+    field: String,
+    #[serde(default)]
+    pool_key: String,
+    #[serde(default)]
+    seq_id: String,
+    local: Value,
+    remote: Value,
+    #[serde(default)]
+    resolution: Option<ConflictResolution>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictSet {
+    account_key: String,
+    detected_at: String,
+    conflicts: Vec<SyncConflict>,
+}
+
+impl ConflictSet {
+    pub(crate) fn pending_count(&self) -> usize {
+        self.conflicts
+            .iter()
+            .filter(|item| item.resolution.is_none())
+            .count()
+    }
+}
+
+fn conflict_set_path(user_key: &str) -> Result<PathBuf, String> {
+    Ok(conflict_dir()?.join(format!("{}{}", user_key, CONFLICT_FILE_SUFFIX)))
+}
+
+fn load_conflict_set(user_key: &str) -> Result<Option<ConflictSet>, String> {
+    let path = conflict_set_path(user_key)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&text)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+fn save_conflict_set(set: &ConflictSet) -> Result<(), String> {
+    let path = conflict_set_path(&set.account_key)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(set).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| e.to_string())
+}
+
+pub(crate) fn clear_conflict_set(user_key: &str) -> Result<(), String> {
+    let path = conflict_set_path(user_key)?;
+    if path.exists() {
+        fs::remove_file(path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn identity_fields(bundle: &AccountBundle) -> [(&'static str, &str); 4] {
+    [
+        ("key", bundle.account.key.as_str()),
+        ("uid", bundle.account.uid.as_str()),
+        ("provider", bundle.account.provider.as_str()),
+        ("roleId", bundle.account.role_id.role_id.as_str()),
+    ]
+}
+
+fn record_conflict(gacha_type: &str, conflict: RecordConflict) -> SyncConflict {
+    SyncConflict {
+        id: format!(
+            "record:{}:{}:{}",
+            gacha_type, conflict.pool_key, conflict.seq_id
+        ),
+        kind: ConflictKind::Record,
+        field: gacha_type.to_string(),
+        pool_key: conflict.pool_key,
+        seq_id: conflict.seq_id,
+        local: conflict.local,
+        remote: conflict.remote,
+        resolution: None,
+    }
+}
+
+/// 找出本地与远端之间的全部冲突（不含已一致的部分）
+pub(crate) fn detect_conflicts(local: &AccountBundle, remote: &AccountBundle) -> Vec<SyncConflict> {
+    let mut conflicts = identity_fields(local)
+        .iter()
+        .zip(identity_fields(remote).iter())
+        .filter(|(local_field, remote_field)| local_field.1 != remote_field.1)
+        .map(|((field, local_value), (_, remote_value))| SyncConflict {
+            id: format!("identity:{}", field),
+            kind: ConflictKind::Identity,
+            field: field.to_string(),
+            pool_key: String::new(),
+            seq_id: String::new(),
+            local: Value::String(local_value.to_string()),
+            remote: Value::String(remote_value.to_string()),
+            resolution: None,
+        })
+        .collect::<Vec<_>>();
+
+    let (_, character) = merge_record_maps_reporting(&local.character, &remote.character);
+    let (_, weapon) = merge_record_maps_reporting(&local.weapon, &remote.weapon);
+    conflicts.extend(
+        character
+            .into_iter()
+            .map(|item| record_conflict("character", item)),
+    );
+    conflicts.extend(
+        weapon
+            .into_iter()
+            .map(|item| record_conflict("weapon", item)),
+    );
+    conflicts
+}
+
+/// 沿用上次保存的处理结果：只有两侧内容未变化时才视为仍然有效
+fn carry_over_resolutions(
+    mut conflicts: Vec<SyncConflict>,
+    previous: Option<ConflictSet>,
+) -> Vec<SyncConflict> {
+    let Some(previous) = previous else {
+        return conflicts;
+    };
+    for conflict in conflicts.iter_mut() {
+        if let Some(old) = previous.conflicts.iter().find(|old| {
+            old.id == conflict.id && old.local == conflict.local && old.remote == conflict.remote
+        }) {
+            conflict.resolution = old.resolution.clone();
+        }
+    }
+    conflicts
+}
+
+fn replace_record(records: &mut Value, pool_key: &str, seq_id: &str, value: &Value) {
+    let Some(items) = records
+        .get_mut(pool_key)
+        .and_then(|list| list.as_array_mut())
+    else {
+        return;
+    };
+    for item in items.iter_mut() {
+        if item.get("seqId").and_then(value_to_seqid).as_deref() == Some(seq_id) {
+            *item = value.clone();
+        }
+    }
+}
+
+fn set_identity_field(bundle: &mut AccountBundle, field: &str, value: &str) {
+    match field {
+        "key" => bundle.account.key = value.to_string(),
+        "uid" => bundle.account.uid = value.to_string(),
+        "provider" => bundle.account.provider = value.to_string(),
+        "roleId" => bundle.account.role_id.role_id = value.to_string(),
+        _ => {}
+    }
+}
+
+fn resolved_value(conflict: &SyncConflict) -> Result<Value, String> {
+    match conflict.resolution.as_ref() {
+        Some(ConflictResolution::KeepLocal) => Ok(conflict.local.clone()),
+        Some(ConflictResolution::KeepRemote) => Ok(conflict.remote.clone()),
+        Some(ConflictResolution::Edited { value }) => Ok(value.clone()),
+        None => Err(format!("冲突 {} 尚未处理", conflict.id)),
+    }
+}
+
+/// 将处理结果同时写入两侧，使后续合并不再产生冲突
+pub(crate) fn apply_resolutions(
+    local: &mut AccountBundle,
+    remote: &mut AccountBundle,
+    conflicts: &[SyncConflict],
+) -> Result<(), String> {
+    for conflict in conflicts {
+        let value = resolved_value(conflict)?;
+        match conflict.kind {
+            ConflictKind::Identity => {
+                let text = value
+                    .as_str()
+                    .map(str::trim)
+                    .filter(|text| !text.is_empty())
+                    .ok_or_else(|| format!("冲突 {} 的处理结果必须为非空字符串", conflict.id))?;
+                set_identity_field(local, &conflict.field, text);
+                set_identity_field(remote, &conflict.field, text);
+            }
+            ConflictKind::Record => {
+                let target = match conflict.field.as_str() {
+                    "weapon" => (&mut local.weapon, &mut remote.weapon),
+                    _ => (&mut local.character, &mut remote.character),
+                };
+                replace_record(target.0, &conflict.pool_key, &conflict.seq_id, &value);
+                replace_record(target.1, &conflict.pool_key, &conflict.seq_id, &value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 同步时检测到冲突：已全部处理则返回处理列表，否则保存待处理冲突并返回错误说明
+pub(crate) fn prepare_resolutions(
+    user_key: &str,
+    local: &AccountBundle,
+    remote: &AccountBundle,
+) -> Result<Option<Vec<SyncConflict>>, String> {
+    let conflicts = detect_conflicts(local, remote);
+    if conflicts.is_empty() {
+        return Ok(None);
+    }
+
+    let conflicts = carry_over_resolutions(conflicts, load_conflict_set(user_key)?);
+    let set = ConflictSet {
+        account_key: user_key.to_string(),
+        detected_at: now_iso_string(),
+        conflicts,
+    };
+    let pending = set.pending_count();
+    if pending == 0 {
+        return Ok(Some(set.conflicts));
+    }
+
+    save_conflict_set(&set)?;
+    let first = set
+        .conflicts
+        .iter()
+        .find(|item| item.resolution.is_none())
+        .map(|item| match item.kind {
+            ConflictKind::Identity => format!("{} 不一致", item.field),
+            ConflictKind::Record => format!("抽卡记录 seqId ({}) 存在字段差异", item.seq_id),
+        })
+        .unwrap_or_default();
+    Err(format!("{}（共 {} 处冲突待处理）", first, pending))
+}
+
+#[command]
+pub fn webdav_list_conflicts() -> Result<Vec<ConflictSet>, String> {
+    let dir = conflict_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut sets = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(user_key) = name.strip_suffix(CONFLICT_FILE_SUFFIX) else {
+            continue;
+        };
+        if let Some(set) = load_conflict_set(user_key)? {
+            sets.push(set);
+        }
+    }
+    sets.sort_by(|a, b| a.account_key.cmp(&b.account_key));
+    Ok(sets)
+}
+
+/// resolution 为 null 时撤销之前的处理
+#[command]
+pub fn webdav_resolve_conflict(
+    user_key: String,
+    conflict_id: String,
+    resolution: Option<ConflictResolution>,
+) -> Result<ConflictSet, String> {
+    let user_key = user_key.trim();
+    let mut set = load_conflict_set(user_key)?
+        .ok_or_else(|| format!("账号没有待处理的冲突：{}", user_key))?;
+    let conflict = set
+        .conflicts
+        .iter_mut()
+        .find(|item| item.id == conflict_id.trim())
+        .ok_or_else(|| format!("冲突不存在：{}", conflict_id.trim()))?;
+
+    if let (Some(ConflictResolution::Edited { value }), ConflictKind::Record) =
+        (resolution.as_ref(), conflict.kind)
+    {
+        let seq_id = value.get("seqId").and_then(value_to_seqid);
+        if !value.is_object() || seq_id.as_deref() != Some(conflict.seq_id.as_str()) {
+            return Err("编辑后的记录必须是对象且 seqId 保持不变".into());
+        }
+    }
+    conflict.resolution = resolution;
+    save_conflict_set(&set)?;
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{self, DavState};
+    use crate::webdav::webdav_sync_account;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    const KEY: &str = "1001_r1";
+    const ACCOUNT_PATH: &str = "/ef/accounts/1001_r1.json";
+    const POOL: &str = "weaponbox_1_0_1";
+    const CONFLICT_ID: &str = "record:weapon:weaponbox_1_0_1:101";
+
+    fn weapon(seq_id: &str, rarity: u32) -> Value {
+        json!({
+            "weaponId": "wpn_sword_0006",
+            "weaponName": "熔铸火焰",
+            "weaponType": "sword",
+            "poolId": POOL,
+            "poolName": "熔铸申领",
+            "rarity": rarity,
+            "isNew": false,
+            "gachaTs": format!("17680{}000000", seq_id),
+            "seqId": seq_id,
+        })
+    }
+
+    fn write_local(records: &[Value]) {
+        testutil::write_record(
+            KEY,
+            &json!({ "character": {}, "weapon": { POOL: records } }),
+        );
+    }
+
+    fn rarities(records: &Value) -> Vec<(String, u64)> {
+        let mut items = records[POOL]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| {
+                (
+                    item["seqId"].as_str().unwrap().to_string(),
+                    item["rarity"].as_u64().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        items.sort();
+        items
+    }
+
+    fn remote_weapons(state: &Arc<Mutex<DavState>>) -> Value {
+        state.lock().unwrap().json(ACCOUNT_PATH).unwrap()["weapon"].clone()
+    }
+
+    /// 上传 101（4★）后，本机追加 102，另一台设备把 101 改成 5★ 并追加 103
+    async fn setup_conflict() -> (testutil::HttpStub, Arc<Mutex<DavState>>) {
+        let (stub, state) = testutil::dav_server();
+        testutil::write_config(&json!({
+            "users": [testutil::hypergryph_user("1001", "r1")],
+            "currentUser": KEY,
+            "webdav": {
+                "baseUrl": stub.base_url,
+                "username": "user",
+                "password": "pass",
+                "basePath": "/ef"
+            }
+        }));
+        write_local(&[weapon("101", 4)]);
+        webdav_sync_account(None, None).await.unwrap();
+
+        write_local(&[weapon("102", 4), weapon("101", 4)]);
+        let mut remote = state.lock().unwrap().json(ACCOUNT_PATH).unwrap();
+        remote["weapon"][POOL] = json!([weapon("103", 4), weapon("101", 5)]);
+        state
+            .lock()
+            .unwrap()
+            .write(ACCOUNT_PATH, remote.to_string());
+
+        let error = webdav_sync_account(None, None).await.unwrap_err();
+        assert!(error.contains("冲突"), "{}", error);
+        let sets = webdav_list_conflicts().unwrap();
+        assert_eq!(sets.len(), 1);
+        assert_eq!(sets[0].pending_count(), 1);
+        assert_eq!(sets[0].conflicts[0].id, CONFLICT_ID);
+        (stub, state)
+    }
+
+    /// 处理冲突后再次同步，返回同步状态
+    async fn resolve_and_sync(resolution: ConflictResolution) -> String {
+        webdav_resolve_conflict(KEY.into(), CONFLICT_ID.into(), Some(resolution)).unwrap();
+        let result = webdav_sync_account(None, None).await.unwrap();
+        assert!(webdav_list_conflicts().unwrap().is_empty());
+        serde_json::to_value(result).unwrap()["status"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    fn expected(rarity: u64) -> Vec<(String, u64)> {
+        vec![("101".into(), rarity), ("102".into(), 4), ("103".into(), 4)]
+    }
+
+    #[tokio::test]
+    async fn keep_local_merges_both_sides_with_local_record() {
+        let _guard = testutil::userdata();
+        let (_stub, state) = setup_conflict().await;
+
+        assert_eq!(
+            resolve_and_sync(ConflictResolution::KeepLocal).await,
+            "merged"
+        );
+        assert_eq!(rarities(&remote_weapons(&state)), expected(4));
+        assert_eq!(rarities(&testutil::read_record(KEY)["weapon"]), expected(4));
+    }
+
+    #[tokio::test]
+    async fn keep_remote_merges_both_sides_with_remote_record() {
+        let _guard = testutil::userdata();
+        let (_stub, state) = setup_conflict().await;
+
+        assert_eq!(
+            resolve_and_sync(ConflictResolution::KeepRemote).await,
+            "merged"
+        );
+        assert_eq!(rarities(&remote_weapons(&state)), expected(5));
+        assert_eq!(rarities(&testutil::read_record(KEY)["weapon"]), expected(5));
+    }
+
+    #[tokio::test]
+    async fn edited_record_survives_a_concurrent_remote_write() {
+        let _guard = testutil::userdata();
+        let (stub, state) = setup_conflict().await;
+
+        // 本机读取远端后、写入前，另一台设备又追加了 104
+        let mut other = state.lock().unwrap().json(ACCOUNT_PATH).unwrap();
+        other["weapon"][POOL] = json!([weapon("104", 4), weapon("103", 4), weapon("101", 5)]);
+        state
+            .lock()
+            .unwrap()
+            .replace_after_get
+            .insert(ACCOUNT_PATH.into(), other.to_string());
+
+        let edited = ConflictResolution::Edited {
+            value: weapon("101", 6),
+        };
+        assert_eq!(resolve_and_sync(edited).await, "merged");
+        let puts = stub
+            .requests()
+            .into_iter()
+            .filter(|request| request.method == "PUT" && request.path() == ACCOUNT_PATH)
+            .count();
+        assert_eq!(puts, 3);
+        let mut all = expected(6);
+        all.push(("104".into(), 4));
+        assert_eq!(rarities(&remote_weapons(&state)), all);
+        assert_eq!(rarities(&testutil::read_record(KEY)["weapon"]), all);
+    }
+
+    #[tokio::test]
+    async fn resolution_is_dropped_when_the_record_changes_again() {
+        let _guard = testutil::userdata();
+        let (_stub, state) = setup_conflict().await;
+        webdav_resolve_conflict(
+            KEY.into(),
+            CONFLICT_ID.into(),
+            Some(ConflictResolution::KeepLocal),
+        )
+        .unwrap();
+
+        let mut remote = state.lock().unwrap().json(ACCOUNT_PATH).unwrap();
+        remote["weapon"][POOL] = json!([weapon("103", 4), weapon("101", 6)]);
+        state
+            .lock()
+            .unwrap()
+            .write(ACCOUNT_PATH, remote.to_string());
+
+        assert!(webdav_sync_account(None, None).await.is_err());
+        let sets = webdav_list_conflicts().unwrap();
+        assert_eq!(sets[0].pending_count(), 1);
+        assert_eq!(sets[0].conflicts[0].remote["rarity"], 6);
+        assert_eq!(rarities(&remote_weapons(&state))[0], ("101".into(), 6));
+    }
+}
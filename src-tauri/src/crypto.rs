@@ -0,0 +1,308 @@
+//! WebDAV 端到端加密：口令经 Argon2id 派生密钥，XChaCha20-Poly1305 加密整个 JSON 文件
+//!
+//! 远端 encryption.json 以明文保存 KDF 参数（含盐）与口令校验值，其余文件为加密信封。
+//! 本地配置只保存按远端盐派生出的密钥，不保存口令本身。
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const ENVELOPE_FORMAT: &str = "endfield-gacha-encrypted";
+const META_FORMAT: &str = "endfield-gacha-encryption";
+const ENVELOPE_VERSION: u32 = 1;
+const CIPHER_NAME: &str = "xchacha20poly1305";
+const KDF_NAME: &str = "argon2id";
+const SALT_LEN: usize = 16;
+const KEY_CHECK_PLAINTEXT: &str = "endfield-gacha";
+const NAME_LABEL: &str = "endfield-gacha-name:";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KdfParams {
+    name: String,
+    salt: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+impl KdfParams {
+    fn generate() -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self {
+            name: KDF_NAME.into(),
+            salt: BASE64.encode(salt),
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+
+    fn derive_key(&self, passphrase: &str) -> Result<[u8; 32], String> {
+        if self.name != KDF_NAME {
+            return Err(format!("不支持的密钥派生算法：{}", self.name));
+        }
+        let salt = BASE64.decode(&self.salt).map_err(|e| e.to_string())?;
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| e.to_string())?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| e.to_string())?;
+        Ok(key)
+    }
+}
+
+/// 加密文件信封
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Envelope {
+    format: String,
+    version: u32,
+    cipher: String,
+    kdf: KdfParams,
+    key_id: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// 远端 encryption.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EncryptionMeta {
+    format: String,
+    version: u32,
+    pub(crate) key_id: String,
+    kdf: KdfParams,
+    /// 固定明文的加密结果，用于校验口令
+    check: Envelope,
+}
+
+fn key_id(key: &[u8; 32]) -> String {
+    Sha256::digest(key)
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn seal(key: &[u8; 32], kdf: &KdfParams, plaintext: &str) -> Result<Envelope, String> {
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| "加密失败".to_string())?;
+    Ok(Envelope {
+        format: ENVELOPE_FORMAT.into(),
+        version: ENVELOPE_VERSION,
+        cipher: CIPHER_NAME.into(),
+        kdf: kdf.clone(),
+        key_id: key_id(key),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn open(key: &[u8; 32], envelope: &Envelope) -> Option<String> {
+    let nonce = BASE64.decode(&envelope.nonce).ok()?;
+    if nonce.len() != 24 {
+        return None;
+    }
+    let ciphertext = BASE64.decode(&envelope.ciphertext).ok()?;
+    let plaintext = XChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+        .ok()?;
+    String::from_utf8(plaintext).ok()
+}
+
+/// 文本为加密信封时返回解析结果
+pub(crate) fn parse_envelope(text: &str) -> Option<Envelope> {
+    let value: Value = serde_json::from_str(text).ok()?;
+    if value.get("format").and_then(|item| item.as_str()) != Some(ENVELOPE_FORMAT) {
+        return None;
+    }
+    serde_json::from_value(value).ok()
+}
+
+pub(crate) fn parse_meta(text: &str) -> Result<EncryptionMeta, String> {
+    let meta: EncryptionMeta =
+        serde_json::from_str(text).map_err(|e| format!("encryption.json 损坏：{}", e))?;
+    if meta.format != META_FORMAT {
+        return Err("encryption.json 格式不受支持".into());
+    }
+    Ok(meta)
+}
+
+/// 本地配置中保存的派生密钥，只对派生时使用的 KDF 参数（盐）有效
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StoredKey {
+    kdf: KdfParams,
+    key: String,
+}
+
+impl StoredKey {
+    fn key_for(&self, kdf: &KdfParams) -> Result<Option<[u8; 32]>, String> {
+        if self.kdf != *kdf {
+            return Ok(None);
+        }
+        let bytes = BASE64.decode(&self.key).map_err(|e| e.to_string())?;
+        let key: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| "本地保存的加密密钥损坏".to_string())?;
+        Ok(Some(key))
+    }
+}
+
+/// 持有口令与派生密钥缓存；可同时持有多个口令用于轮换期间解密旧文件
+pub(crate) struct BundleCipher {
+    /// 本地保存的派生密钥，优先于口令用于解锁与写入
+    stored: Option<StoredKey>,
+    passphrases: Vec<String>,
+    keys: Mutex<HashMap<(usize, String), [u8; 32]>>,
+    /// 加密写入时使用的 KDF 参数（取自远端 encryption.json）
+    write_kdf: Mutex<Option<KdfParams>>,
+}
+
+impl BundleCipher {
+    pub(crate) fn new(passphrases: Vec<String>) -> Option<Self> {
+        Self::with_stored_key(None, passphrases)
+    }
+
+    pub(crate) fn with_stored_key(
+        stored: Option<StoredKey>,
+        passphrases: Vec<String>,
+    ) -> Option<Self> {
+        let passphrases = passphrases
+            .into_iter()
+            .filter(|item| !item.is_empty())
+            .collect::<Vec<_>>();
+        if stored.is_none() && passphrases.is_empty() {
+            return None;
+        }
+        Some(Self {
+            stored,
+            passphrases,
+            keys: Mutex::new(HashMap::new()),
+            write_kdf: Mutex::new(None),
+        })
+    }
+
+    fn secret_count(&self) -> usize {
+        usize::from(self.stored.is_some()) + self.passphrases.len()
+    }
+
+    /// 第 index 个密钥来源在给定 KDF 参数下的密钥；保存的密钥与参数不符时为 None
+    fn key_for(&self, index: usize, kdf: &KdfParams) -> Result<Option<[u8; 32]>, String> {
+        let index = match self.stored.as_ref() {
+            Some(stored) if index == 0 => return stored.key_for(kdf),
+            Some(_) => index - 1,
+            None => index,
+        };
+        let cache_key = (index, kdf.salt.clone());
+        if let Some(key) = self.keys.lock().map_err(|e| e.to_string())?.get(&cache_key) {
+            return Ok(Some(*key));
+        }
+        let key = kdf.derive_key(&self.passphrases[index])?;
+        self.keys
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(cache_key, key);
+        Ok(Some(key))
+    }
+
+    /// 首个密钥来源的密钥，用于解锁与写入
+    fn primary_key(&self, kdf: &KdfParams) -> Result<[u8; 32], String> {
+        self.key_for(0, kdf)?
+            .ok_or_else(|| "本地保存的加密密钥与远端不一致，请重新输入加密口令".to_string())
+    }
+
+    /// 生成新的 encryption.json（新盐），并设为后续写入的参数
+    pub(crate) fn create_meta(&self) -> Result<EncryptionMeta, String> {
+        let kdf = KdfParams::generate();
+        let key = self.primary_key(&kdf)?;
+        let meta = EncryptionMeta {
+            format: META_FORMAT.into(),
+            version: ENVELOPE_VERSION,
+            key_id: key_id(&key),
+            check: seal(&key, &kdf, KEY_CHECK_PLAINTEXT)?,
+            kdf,
+        };
+        *self.write_kdf.lock().map_err(|e| e.to_string())? = Some(meta.kdf.clone());
+        Ok(meta)
+    }
+
+    /// 用首个口令校验远端 encryption.json，通过后采用其 KDF 参数写入
+    pub(crate) fn unlock(&self, meta: &EncryptionMeta) -> Result<(), String> {
+        let key = self.primary_key(&meta.kdf)?;
+        if open(&key, &meta.check).as_deref() != Some(KEY_CHECK_PLAINTEXT) {
+            return Err("WebDAV 加密口令错误".into());
+        }
+        *self.write_kdf.lock().map_err(|e| e.to_string())? = Some(meta.kdf.clone());
+        Ok(())
+    }
+
+    fn write_key(&self) -> Result<([u8; 32], KdfParams), String> {
+        let kdf = self
+            .write_kdf
+            .lock()
+            .map_err(|e| e.to_string())?
+            .clone()
+            .ok_or("WebDAV 加密尚未初始化")?;
+        Ok((self.primary_key(&kdf)?, kdf))
+    }
+
+    /// 当前写入密钥，解锁或生成 encryption.json 后保存到本地配置以代替口令
+    pub(crate) fn stored_key(&self) -> Result<StoredKey, String> {
+        let (key, kdf) = self.write_key()?;
+        Ok(StoredKey {
+            kdf,
+            key: BASE64.encode(key),
+        })
+    }
+
+    pub(crate) fn encrypt(&self, plaintext: &str) -> Result<String, String> {
+        let (key, kdf) = self.write_key()?;
+        serde_json::to_string_pretty(&seal(&key, &kdf, plaintext)?).map_err(|e| e.to_string())
+    }
+
+    /// 远端文件名：账号 key 的 HMAC，避免在路径中暴露 uid 与角色 ID
+    pub(crate) fn opaque_name(&self, user_key: &str) -> Result<String, String> {
+        let (key, _) = self.write_key()?;
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&key).expect("HMAC 接受任意长度的密钥");
+        mac.update(NAME_LABEL.as_bytes());
+        mac.update(user_key.as_bytes());
+        Ok(mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .take(16)
+            .map(|byte| format!("{:02x}", byte))
+            .collect())
+    }
+
+    pub(crate) fn decrypt(&self, envelope: &Envelope) -> Result<String, String> {
+        if envelope.cipher != CIPHER_NAME {
+            return Err(format!("不支持的加密算法：{}", envelope.cipher));
+        }
+        for index in 0..self.secret_count() {
+            let Some(key) = self.key_for(index, &envelope.kdf)? else {
+                continue;
+            };
+            if let Some(plaintext) = open(&key, envelope) {
+                return Ok(plaintext);
+            }
+        }
+        Err("WebDAV 加密口令错误，无法解密远端数据".into())
+    }
+}
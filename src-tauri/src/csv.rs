@@ -0,0 +1,348 @@
+use crate::compare_seqid;
+use crate::stats::{
+    annotate_account, is_off_banner, load_account_pulls, local_datetime, parse_gacha_kind,
+    FreePullRule, GachaKind, PityPull, PityRules, PoolCatalog,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::command;
+
+const UTF8_BOM: &str = "\u{feff}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CsvDelimiter {
+    #[default]
+    Comma,
+    Tab,
+}
+
+impl CsvDelimiter {
+    fn as_char(self) -> char {
+        match self {
+            CsvDelimiter::Comma => ',',
+            CsvDelimiter::Tab => '\t',
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            CsvDelimiter::Comma => "csv",
+            CsvDelimiter::Tab => "tsv",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CsvColumn {
+    Time,
+    Timestamp,
+    GachaType,
+    PoolKey,
+    PoolId,
+    PoolName,
+    BannerName,
+    ItemId,
+    ItemName,
+    Rarity,
+    Pity,
+    BigPity,
+    IsNew,
+    IsUp,
+    IsOffBanner,
+    IsFree,
+    SeqId,
+}
+
+impl CsvColumn {
+    fn header(self) -> &'static str {
+        match self {
+            CsvColumn::Time => "time",
+            CsvColumn::Timestamp => "gachaTs",
+            CsvColumn::GachaType => "gachaType",
+            CsvColumn::PoolKey => "poolKey",
+            CsvColumn::PoolId => "poolId",
+            CsvColumn::PoolName => "poolName",
+            CsvColumn::BannerName => "bannerName",
+            CsvColumn::ItemId => "itemId",
+            CsvColumn::ItemName => "itemName",
+            CsvColumn::Rarity => "rarity",
+            CsvColumn::Pity => "pity",
+            CsvColumn::BigPity => "bigPity",
+            CsvColumn::IsNew => "isNew",
+            CsvColumn::IsUp => "isUp",
+            CsvColumn::IsOffBanner => "isOffBanner",
+            CsvColumn::IsFree => "isFree",
+            CsvColumn::SeqId => "seqId",
+        }
+    }
+
+    fn value(self, item: &PityPull, catalog: &PoolCatalog) -> String {
+        let pull = &item.pull;
+        match self {
+            CsvColumn::Time => local_datetime(pull.gacha_ts)
+                .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default(),
+            CsvColumn::Timestamp => pull.gacha_ts.to_string(),
+            CsvColumn::GachaType => match pull.kind {
+                GachaKind::Character => "character".into(),
+                GachaKind::Weapon => "weapon".into(),
+            },
+            CsvColumn::PoolKey => pull.pool_key.clone(),
+            CsvColumn::PoolId => pull.pool_id.clone(),
+            CsvColumn::PoolName => pull.pool_name.clone(),
+            CsvColumn::BannerName => catalog
+                .get(&pull.pool_id)
+                .map(|info| info.pool_name.clone())
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| pull.pool_name.clone()),
+            CsvColumn::ItemId => pull.item_id.clone(),
+            CsvColumn::ItemName => pull.item_name.clone(),
+            CsvColumn::Rarity => pull.rarity.to_string(),
+            CsvColumn::Pity => item.pity.to_string(),
+            CsvColumn::BigPity => item
+                .big_pity
+                .map(|value| value.to_string())
+                .unwrap_or_default(),
+            CsvColumn::IsNew => pull.is_new.to_string(),
+            CsvColumn::IsUp => item.is_up.to_string(),
+            CsvColumn::IsOffBanner => is_off_banner(item, catalog).to_string(),
+            CsvColumn::IsFree => pull.is_free.to_string(),
+            CsvColumn::SeqId => pull.seq_id.clone(),
+        }
+    }
+}
+
+fn default_columns() -> Vec<CsvColumn> {
+    vec![
+        CsvColumn::Time,
+        CsvColumn::GachaType,
+        CsvColumn::BannerName,
+        CsvColumn::PoolId,
+        CsvColumn::ItemName,
+        CsvColumn::Rarity,
+        CsvColumn::Pity,
+        CsvColumn::IsNew,
+        CsvColumn::IsUp,
+        CsvColumn::IsFree,
+        CsvColumn::SeqId,
+    ]
+}
+
+fn default_bom() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvExportOptions {
+    #[serde(default)]
+    delimiter: CsvDelimiter,
+    #[serde(default = "default_columns")]
+    columns: Vec<CsvColumn>,
+    #[serde(default)]
+    gacha_type: Option<String>,
+    /// 按卡池 ID 或卡池类型筛选，为空时导出全部
+    #[serde(default)]
+    pools: Vec<String>,
+    #[serde(default)]
+    rarities: Vec<u8>,
+    /// 起止时间（毫秒时间戳，闭区间）
+    #[serde(default)]
+    start_ts: Option<i64>,
+    #[serde(default)]
+    end_ts: Option<i64>,
+    #[serde(default = "default_bom")]
+    bom: bool,
+    /// 合并为单个文件并按 seqId 排序；否则 path 为目录，每个卡池一个文件
+    #[serde(default)]
+    merge_pools: bool,
+    #[serde(default)]
+    free_pull_rules: HashMap<String, FreePullRule>,
+}
+
+impl Default for CsvExportOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: CsvDelimiter::default(),
+            columns: default_columns(),
+            gacha_type: None,
+            pools: Vec::new(),
+            rarities: Vec::new(),
+            start_ts: None,
+            end_ts: None,
+            bom: default_bom(),
+            merge_pools: false,
+            free_pull_rules: HashMap::new(),
+        }
+    }
+}
+
+impl CsvExportOptions {
+    fn matches(&self, item: &PityPull) -> bool {
+        let pull = &item.pull;
+        if !self.pools.is_empty()
+            && !self
+                .pools
+                .iter()
+                .any(|pool| pool == &pull.pool_id || pool == &pull.pool_key)
+        {
+            return false;
+        }
+        if !self.rarities.is_empty() && !self.rarities.contains(&pull.rarity) {
+            return false;
+        }
+        if self.start_ts.is_some_and(|start| pull.gacha_ts < start) {
+            return false;
+        }
+        if self.end_ts.is_some_and(|end| pull.gacha_ts > end) {
+            return false;
+        }
+        true
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvExportResult {
+    files: Vec<String>,
+    row_count: usize,
+}
+
+fn escape_field(value: &str, delimiter: char) -> String {
+    if value.contains(delimiter)
+        || value.contains('"')
+        || value.contains('\n')
+        || value.contains('\r')
+    {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn render_table(items: &[&PityPull], catalog: &PoolCatalog, options: &CsvExportOptions) -> String {
+    let delimiter = options.delimiter.as_char();
+    let separator = delimiter.to_string();
+    let mut output = String::new();
+    if options.bom {
+        output.push_str(UTF8_BOM);
+    }
+
+    let header = options
+        .columns
+        .iter()
+        .map(|column| escape_field(column.header(), delimiter))
+        .collect::<Vec<_>>();
+    output.push_str(&header.join(&separator));
+    output.push_str("\r\n");
+
+    for item in items {
+        let row = options
+            .columns
+            .iter()
+            .map(|column| escape_field(&column.value(item, catalog), delimiter))
+            .collect::<Vec<_>>();
+        output.push_str(&row.join(&separator));
+        output.push_str("\r\n");
+    }
+    output
+}
+
+fn sort_by_seqid(items: &mut [&PityPull]) {
+    items.sort_by(|a, b| compare_seqid(&a.pull.seq_id, &b.pull.seq_id));
+}
+
+fn sanitize_file_part(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn write_file(path: &Path, content: &str) -> Result<String, String> {
+    fs::write(path, content).map_err(|e| e.to_string())?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[command]
+pub fn export_csv(
+    user_key: String,
+    path: String,
+    options: Option<CsvExportOptions>,
+) -> Result<CsvExportResult, String> {
+    let path = path.trim().to_string();
+    if path.is_empty() {
+        return Err("Export path cannot be empty".into());
+    }
+    let options = options.unwrap_or_default();
+    if options.columns.is_empty() {
+        return Err("At least one column is required".into());
+    }
+
+    let kind = parse_gacha_kind(options.gacha_type.as_deref())?;
+    let account = load_account_pulls(user_key.trim())?;
+    let catalog = PoolCatalog::load()?;
+    let rules = PityRules::new(options.free_pull_rules.clone());
+
+    // 垫抽按完整历史计算后再筛选，避免筛选打断保底计数
+    let items = annotate_account(&account, kind, &catalog, &rules);
+    let mut selected = items
+        .iter()
+        .filter(|item| options.matches(item))
+        .collect::<Vec<_>>();
+    let row_count = selected.len();
+
+    if options.merge_pools {
+        sort_by_seqid(&mut selected);
+        let file = write_file(
+            Path::new(&path),
+            &render_table(&selected, &catalog, &options),
+        )?;
+        return Ok(CsvExportResult {
+            files: vec![file],
+            row_count,
+        });
+    }
+
+    let dir = PathBuf::from(&path);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let mut groups: BTreeMap<(GachaKind, String), Vec<&PityPull>> = BTreeMap::new();
+    for item in selected {
+        groups
+            .entry((item.pull.kind, item.pull.pool_id.clone()))
+            .or_default()
+            .push(item);
+    }
+
+    let mut files = Vec::new();
+    for ((group_kind, pool_id), mut group) in groups {
+        sort_by_seqid(&mut group);
+        let prefix = match group_kind {
+            GachaKind::Character => "character",
+            GachaKind::Weapon => "weapon",
+        };
+        let file_name = format!(
+            "{}_{}.{}",
+            prefix,
+            sanitize_file_part(&pool_id),
+            options.delimiter.extension()
+        );
+        files.push(write_file(
+            &dir.join(file_name),
+            &render_table(&group, &catalog, &options),
+        )?);
+    }
+
+    Ok(CsvExportResult { files, row_count })
+}
@@ -0,0 +1,635 @@
+//! WebDAV 认证：Basic / Digest / Bearer / 仅自定义请求头
+//!
+//! Digest 按 RFC 7616 实现：首次请求收到 401 后缓存服务器质询，之后每次请求递增 nc 计算响应；
+//! 质询过期（stale）或被更换时重试一次。
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use md5::Md5;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, WWW_AUTHENTICATE};
+use reqwest::{Method, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum WebDavAuthKind {
+    #[default]
+    Basic,
+    Digest,
+    Bearer,
+    /// 不发送 Authorization，仅依赖自定义请求头
+    Headers,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WebDavHeader {
+    #[serde(default)]
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DigestAlgorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess,
+}
+
+impl DigestAlgorithm {
+    fn parse(value: Option<&str>) -> Option<Self> {
+        match value.map(|item| item.to_ascii_uppercase()).as_deref() {
+            None | Some("MD5") => Some(Self::Md5),
+            Some("MD5-SESS") => Some(Self::Md5Sess),
+            Some("SHA-256") => Some(Self::Sha256),
+            Some("SHA-256-SESS") => Some(Self::Sha256Sess),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "MD5",
+            Self::Md5Sess => "MD5-sess",
+            Self::Sha256 => "SHA-256",
+            Self::Sha256Sess => "SHA-256-sess",
+        }
+    }
+
+    fn is_session(self) -> bool {
+        matches!(self, Self::Md5Sess | Self::Sha256Sess)
+    }
+
+    fn hash(self, data: &str) -> String {
+        match self {
+            Self::Md5 | Self::Md5Sess => hex_digest::<Md5>(data),
+            Self::Sha256 | Self::Sha256Sess => hex_digest::<Sha256>(data),
+        }
+    }
+}
+
+fn hex_digest<D: Digest>(data: &str) -> String {
+    D::digest(data.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+struct DigestChallenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: DigestAlgorithm,
+    /// 服务器声明了 qop=auth；未声明时按 RFC 2069 兼容方式计算
+    qop_auth: bool,
+    stale: bool,
+}
+
+struct DigestSession {
+    challenge: DigestChallenge,
+    nonce_count: u32,
+}
+
+/// 解析 `key=value, key="quoted"` 形式的认证参数
+fn parse_auth_params(input: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars
+            .peek()
+            .is_some_and(|ch| *ch == ',' || ch.is_whitespace())
+        {
+            chars.next();
+        }
+        let mut key = String::new();
+        while let Some(ch) = chars.peek().copied() {
+            if ch == '=' || ch == ',' {
+                break;
+            }
+            key.push(ch);
+            chars.next();
+        }
+        if key.trim().is_empty() {
+            break;
+        }
+        let mut value = String::new();
+        if chars.peek() == Some(&'=') {
+            chars.next();
+            while chars.peek().is_some_and(|ch| ch.is_whitespace()) {
+                chars.next();
+            }
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                while let Some(ch) = chars.next() {
+                    match ch {
+                        '\\' => {
+                            if let Some(escaped) = chars.next() {
+                                value.push(escaped);
+                            }
+                        }
+                        '"' => break,
+                        _ => value.push(ch),
+                    }
+                }
+            } else {
+                while let Some(ch) = chars.peek().copied() {
+                    if ch == ',' {
+                        break;
+                    }
+                    value.push(ch);
+                    chars.next();
+                }
+            }
+        }
+        params.push((key.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+    params
+}
+
+fn parse_digest_challenge(header: &str) -> Option<DigestChallenge> {
+    let header = header.trim();
+    let (scheme, rest) = header.split_once(char::is_whitespace)?;
+    if !scheme.eq_ignore_ascii_case("digest") {
+        return None;
+    }
+    let params = parse_auth_params(rest);
+    let find = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let qop_auth = match find("qop") {
+        Some(qop) => {
+            // 只提供 auth-int 时无法在不缓冲请求体的前提下计算，视为不支持
+            if !qop
+                .split(',')
+                .any(|item| item.trim().eq_ignore_ascii_case("auth"))
+            {
+                return None;
+            }
+            true
+        }
+        None => false,
+    };
+
+    Some(DigestChallenge {
+        realm: find("realm").unwrap_or_default().to_string(),
+        nonce: find("nonce")?.to_string(),
+        opaque: find("opaque").map(str::to_string),
+        algorithm: DigestAlgorithm::parse(find("algorithm"))?,
+        qop_auth,
+        stale: find("stale").is_some_and(|value| value.eq_ignore_ascii_case("true")),
+    })
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn generate_cnonce() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// 请求行中的 URI：路径 + 查询串
+fn request_uri(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(parsed) => match parsed.query() {
+            Some(query) => format!("{}?{}", parsed.path(), query),
+            None => parsed.path().to_string(),
+        },
+        Err(_) => url.to_string(),
+    }
+}
+
+fn digest_response(
+    challenge: &DigestChallenge,
+    username: &str,
+    password: &str,
+    method: &Method,
+    uri: &str,
+    nonce_count: u32,
+    cnonce: &str,
+) -> String {
+    let algorithm = challenge.algorithm;
+    let mut ha1 = algorithm.hash(&format!("{}:{}:{}", username, challenge.realm, password));
+    if algorithm.is_session() {
+        ha1 = algorithm.hash(&format!("{}:{}:{}", ha1, challenge.nonce, cnonce));
+    }
+    let ha2 = algorithm.hash(&format!("{}:{}", method.as_str(), uri));
+    let nc = format!("{:08x}", nonce_count);
+    let response = if challenge.qop_auth {
+        algorithm.hash(&format!(
+            "{}:{}:{}:{}:auth:{}",
+            ha1, challenge.nonce, nc, cnonce, ha2
+        ))
+    } else {
+        algorithm.hash(&format!("{}:{}:{}", ha1, challenge.nonce, ha2))
+    };
+
+    let mut parts = vec![
+        format!("username={}", quote(username)),
+        format!("realm={}", quote(&challenge.realm)),
+        format!("nonce={}", quote(&challenge.nonce)),
+        format!("uri={}", quote(uri)),
+        format!("algorithm={}", algorithm.name()),
+        format!("response={}", quote(&response)),
+    ];
+    if challenge.qop_auth {
+        parts.push("qop=auth".into());
+        parts.push(format!("nc={}", nc));
+        parts.push(format!("cnonce={}", quote(cnonce)));
+    }
+    if let Some(opaque) = challenge.opaque.as_deref() {
+        parts.push(format!("opaque={}", quote(opaque)));
+    }
+    format!("Digest {}", parts.join(", "))
+}
+
+/// 构造自定义请求头；名称为空的行忽略
+pub(crate) fn build_extra_headers(headers: &[WebDavHeader]) -> Result<HeaderMap, String> {
+    let mut map = HeaderMap::new();
+    for header in headers {
+        let name = header.name.trim();
+        if name.is_empty() {
+            continue;
+        }
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("自定义请求头名称无效：{}", name))?;
+        let header_value = HeaderValue::from_str(header.value.trim())
+            .map_err(|_| format!("自定义请求头 {} 的值无效", name))?;
+        map.append(header_name, header_value);
+    }
+    Ok(map)
+}
+
+/// 按认证方式检查必填项
+pub(crate) fn validate_credentials(
+    kind: WebDavAuthKind,
+    username: &str,
+    password: &str,
+    bearer_token: &str,
+    extra_headers: &[WebDavHeader],
+) -> Result<(), String> {
+    let complete = match kind {
+        WebDavAuthKind::Basic | WebDavAuthKind::Digest => {
+            !username.is_empty() && !password.is_empty()
+        }
+        WebDavAuthKind::Bearer => !bearer_token.trim().is_empty(),
+        WebDavAuthKind::Headers => extra_headers
+            .iter()
+            .any(|header| !header.name.trim().is_empty()),
+    };
+    if complete {
+        Ok(())
+    } else {
+        Err("请先填写完整的 WebDAV 配置".into())
+    }
+}
+
+/// 每个 WebDavClient 持有一份，Digest 质询与 nc 计数在其生命周期内复用
+pub(crate) struct DavAuthenticator {
+    kind: WebDavAuthKind,
+    username: String,
+    password: String,
+    bearer_token: String,
+    extra_headers: HeaderMap,
+    digest: Mutex<Option<DigestSession>>,
+}
+
+impl DavAuthenticator {
+    pub(crate) fn new(
+        kind: WebDavAuthKind,
+        username: String,
+        password: String,
+        bearer_token: String,
+        extra_headers: &[WebDavHeader],
+    ) -> Result<Self, String> {
+        Ok(Self {
+            kind,
+            username,
+            password,
+            bearer_token: bearer_token.trim().to_string(),
+            extra_headers: build_extra_headers(extra_headers)?,
+            digest: Mutex::new(None),
+        })
+    }
+
+    /// 生成一次请求要附加的全部认证相关请求头
+    pub(crate) fn headers_for(&self, method: &Method, url: &str) -> Result<HeaderMap, String> {
+        let mut headers = self.extra_headers.clone();
+        let authorization = match self.kind {
+            WebDavAuthKind::Basic => Some(format!(
+                "Basic {}",
+                BASE64.encode(format!("{}:{}", self.username, self.password))
+            )),
+            WebDavAuthKind::Bearer => Some(format!("Bearer {}", self.bearer_token)),
+            WebDavAuthKind::Digest => {
+                let mut guard = self.digest.lock().map_err(|e| e.to_string())?;
+                guard.as_mut().map(|session| {
+                    session.nonce_count = session.nonce_count.wrapping_add(1);
+                    digest_response(
+                        &session.challenge,
+                        &self.username,
+                        &self.password,
+                        method,
+                        &request_uri(url),
+                        session.nonce_count,
+                        &generate_cnonce(),
+                    )
+                })
+            }
+            WebDavAuthKind::Headers => None,
+        };
+        if let Some(value) = authorization {
+            let value = HeaderValue::from_str(&value).map_err(|_| "WebDAV 认证信息包含非法字符")?;
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+        }
+        Ok(headers)
+    }
+
+    /// 收到 401 后读取 Digest 质询；返回 true 表示应以新质询重试
+    pub(crate) fn accept_challenge(&self, headers: &HeaderMap) -> Result<bool, String> {
+        if self.kind != WebDavAuthKind::Digest {
+            return Ok(false);
+        }
+        let Some(challenge) = headers
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(parse_digest_challenge)
+        else {
+            return Ok(false);
+        };
+
+        let mut guard = self.digest.lock().map_err(|e| e.to_string())?;
+        // 同一 nonce 且未标记 stale，说明凭证本身错误，重试无意义
+        let retry = match guard.as_ref() {
+            Some(session) => challenge.stale || session.challenge.nonce != challenge.nonce,
+            None => true,
+        };
+        *guard = Some(DigestSession {
+            challenge,
+            nonce_count: 0,
+        });
+        Ok(retry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{self, DavState, HttpStub, StubRequest, StubResponse};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    const USER: &str = "alice";
+    const PASSWORD: &str = "wonderland";
+    const REALM: &str = "dav";
+
+    fn write_config(stub: &HttpStub, auth: Value) {
+        let mut webdav = json!({
+            "baseUrl": stub.base_url,
+            "basePath": "/ef",
+            "username": USER,
+            "password": PASSWORD
+        });
+        for (key, value) in auth.as_object().unwrap() {
+            webdav[key] = value.clone();
+        }
+        testutil::write_config(&json!({ "users": [], "webdav": webdav }));
+    }
+
+    /// 服务端 Digest 校验（RFC 7616, MD5 + qop=auth），nonce 在通过 rotate_after 次请求后轮换
+    struct DigestServer {
+        dav: DavState,
+        nonce: String,
+        generation: u32,
+        rotate_after: Option<usize>,
+        authorized: usize,
+        password: String,
+        /// nonce -> 已使用的最大 nc
+        last_nc: HashMap<String, u32>,
+    }
+
+    impl DigestServer {
+        fn new(rotate_after: Option<usize>, password: &str) -> Self {
+            Self {
+                dav: DavState::default(),
+                nonce: "nonce-1".into(),
+                generation: 1,
+                rotate_after,
+                authorized: 0,
+                password: password.into(),
+                last_nc: HashMap::new(),
+            }
+        }
+
+        fn challenge(&self, stale: bool) -> StubResponse {
+            let mut value = format!(
+                "Digest realm=\"{}\", qop=\"auth\", nonce=\"{}\", opaque=\"op-42\", algorithm=MD5",
+                REALM, self.nonce
+            );
+            if stale {
+                value.push_str(", stale=true");
+            }
+            StubResponse::new(401).header("WWW-Authenticate", &value)
+        }
+
+        fn handle(&mut self, request: &StubRequest) -> StubResponse {
+            let Some(params) = request
+                .header("authorization")
+                .and_then(|value| value.strip_prefix("Digest "))
+                .map(parse_auth_params)
+            else {
+                return self.challenge(false);
+            };
+            let find = |name: &str| {
+                params
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.clone())
+                    .unwrap_or_default()
+            };
+            if find("nonce") != self.nonce {
+                return self.challenge(true);
+            }
+            let nc = u32::from_str_radix(&find("nc"), 16).unwrap_or(0);
+            let last = self.last_nc.entry(find("nonce")).or_insert(0);
+            if nc <= *last {
+                return self.challenge(false);
+            }
+            *last = nc;
+
+            let ha1 = hex_digest::<Md5>(&format!("{}:{}:{}", USER, REALM, self.password));
+            let ha2 = hex_digest::<Md5>(&format!("{}:{}", request.method, find("uri")));
+            let expected = hex_digest::<Md5>(&format!(
+                "{}:{}:{}:{}:auth:{}",
+                ha1,
+                self.nonce,
+                find("nc"),
+                find("cnonce"),
+                ha2
+            ));
+            if find("uri") != request.target
+                || find("opaque") != "op-42"
+                || find("response") != expected
+            {
+                return self.challenge(false);
+            }
+
+            self.authorized += 1;
+            if self.rotate_after == Some(self.authorized) {
+                self.generation += 1;
+                self.nonce = format!("nonce-{}", self.generation);
+            }
+            self.dav.handle(request)
+        }
+    }
+
+    fn digest_server(
+        rotate_after: Option<usize>,
+        password: &str,
+    ) -> (HttpStub, Arc<Mutex<DigestServer>>) {
+        let server = Arc::new(Mutex::new(DigestServer::new(rotate_after, password)));
+        let shared = server.clone();
+        let stub = HttpStub::start(move |request| shared.lock().unwrap().handle(request));
+        (stub, server)
+    }
+
+    fn digest_param(request: &StubRequest, name: &str) -> Option<String> {
+        let header = request.header("authorization")?.strip_prefix("Digest ")?;
+        parse_auth_params(header)
+            .into_iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+
+    #[tokio::test]
+    async fn digest_retries_after_challenge_and_increments_nc() {
+        let _guard = testutil::userdata();
+        let (stub, server) = digest_server(None, PASSWORD);
+        write_config(&stub, json!({ "authKind": "digest" }));
+
+        crate::webdav::webdav_test_connection().await.unwrap();
+
+        let requests = stub.requests();
+        assert!(requests[0].header("authorization").is_none());
+        // 只有首个请求收到质询，之后直接携带 Digest 认证
+        assert_eq!(
+            requests
+                .iter()
+                .filter(|request| request.header("authorization").is_none())
+                .count(),
+            1
+        );
+        assert_eq!(requests[1].method, requests[0].method);
+        assert_eq!(requests[1].target, requests[0].target);
+        let counts = requests[1..]
+            .iter()
+            .map(|request| digest_param(request, "nc").expect("nc"))
+            .collect::<Vec<_>>();
+        let expected = (1..=counts.len())
+            .map(|nc| format!("{:08x}", nc))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, expected);
+        assert!(server
+            .lock()
+            .unwrap()
+            .dav
+            .files
+            .contains_key("/ef/manifest.json"));
+    }
+
+    #[tokio::test]
+    async fn digest_stale_nonce_is_retried_with_new_nonce() {
+        let _guard = testutil::userdata();
+        let (stub, server) = digest_server(Some(2), PASSWORD);
+        write_config(&stub, json!({ "authKind": "digest" }));
+
+        crate::webdav::webdav_test_connection().await.unwrap();
+
+        let requests = stub.requests();
+        let nonces = requests
+            .iter()
+            .filter_map(|request| digest_param(request, "nonce"))
+            .collect::<Vec<_>>();
+        assert_eq!(nonces.iter().filter(|nonce| *nonce == "nonce-1").count(), 3);
+        assert!(nonces.iter().any(|nonce| nonce == "nonce-2"));
+        // 新 nonce 的 nc 从 1 重新计数
+        let first_new = requests
+            .iter()
+            .find(|request| digest_param(request, "nonce").as_deref() == Some("nonce-2"))
+            .unwrap();
+        assert_eq!(digest_param(first_new, "nc").as_deref(), Some("00000001"));
+        assert_eq!(server.lock().unwrap().generation, 2);
+    }
+
+    #[tokio::test]
+    async fn digest_wrong_password_is_not_retried_forever() {
+        let _guard = testutil::userdata();
+        let (stub, _server) = digest_server(None, "other");
+        write_config(&stub, json!({ "authKind": "digest" }));
+
+        assert!(crate::webdav::webdav_test_connection().await.is_err());
+        // 无认证请求 + 一次携带认证的重试
+        assert_eq!(stub.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn bearer_and_custom_headers_are_sent_on_every_request() {
+        let _guard = testutil::userdata();
+        let (stub, _state) = testutil::dav_server();
+        write_config(
+            &stub,
+            json!({
+                "authKind": "bearer",
+                "bearerToken": "tok-123",
+                "extraHeaders": [
+                    { "name": "X-Api-Key", "value": "k-1" },
+                    { "name": "", "value": "ignored" }
+                ]
+            }),
+        );
+
+        crate::webdav::webdav_test_connection().await.unwrap();
+
+        let requests = stub.requests();
+        assert!(requests.len() > 3);
+        for request in requests.iter() {
+            assert_eq!(request.header("authorization"), Some("Bearer tok-123"));
+            assert_eq!(request.header("x-api-key"), Some("k-1"));
+        }
+    }
+
+    #[tokio::test]
+    async fn header_only_auth_sends_no_authorization() {
+        let _guard = testutil::userdata();
+        let (stub, _state) = testutil::dav_server();
+        write_config(
+            &stub,
+            json!({
+                "authKind": "headers",
+                "extraHeaders": [{ "name": "Cf-Access-Client-Id", "value": "id-1" }]
+            }),
+        );
+
+        crate::webdav::webdav_test_connection().await.unwrap();
+
+        for request in stub.requests().iter() {
+            assert_eq!(request.header("authorization"), None);
+            assert_eq!(request.header("cf-access-client-id"), Some("id-1"));
+        }
+    }
+}
@@ -0,0 +1,392 @@
+//! 比较两份抽卡记录（记录文件或账号文件），按卡池列出单侧记录与同 seqId 的字段差异
+//!
+//! 缺少 seqId 的记录按内容配对；同一文件内重复的 seqId 单独列出。
+
+use crate::compare_seqid;
+use crate::value_to_seqid;
+use crate::webdav::{conflict_snapshot_paths, normalize_record_for_conflict_compare};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use tauri::command;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldDiff {
+    field: String,
+    /// 字段缺失时为 null
+    a: Value,
+    b: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangedRecord {
+    seq_id: String,
+    fields: Vec<FieldDiff>,
+    a: Value,
+    b: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolDiff {
+    /// character / weapon
+    gacha_type: String,
+    pool_key: String,
+    only_in_a: Vec<Value>,
+    only_in_b: Vec<Value>,
+    changed: Vec<ChangedRecord>,
+    same_count: usize,
+    /// 同一文件内 seqId 重复的记录，只有首条参与比较
+    duplicates_in_a: Vec<Value>,
+    duplicates_in_b: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordDiffResult {
+    a_path: String,
+    b_path: String,
+    identical: bool,
+    only_in_a_count: usize,
+    only_in_b_count: usize,
+    changed_count: usize,
+    same_count: usize,
+    duplicate_count: usize,
+    pools: Vec<PoolDiff>,
+}
+
+#[derive(Default)]
+struct IndexedRecords<'a> {
+    by_seq_id: BTreeMap<String, &'a Value>,
+    /// 同一文件内 seqId 重复出现的记录（首条之外）
+    duplicates: Vec<&'a Value>,
+    without_seq_id: Vec<&'a Value>,
+}
+
+fn index_records(items: &[Value]) -> IndexedRecords<'_> {
+    let mut indexed = IndexedRecords::default();
+    for item in items {
+        match item.get("seqId").and_then(value_to_seqid) {
+            Some(seq_id) => match indexed.by_seq_id.entry(seq_id) {
+                Entry::Vacant(entry) => {
+                    entry.insert(item);
+                }
+                Entry::Occupied(_) => indexed.duplicates.push(item),
+            },
+            None => indexed.without_seq_id.push(item),
+        }
+    }
+    indexed
+}
+
+/// 缺少 seqId 的记录无法按序号配对，只有规范化后内容完全相同才视为同一条
+fn match_without_seq_id(diff: &mut PoolDiff, a: &[&Value], b: &[&Value]) {
+    let mut unmatched_b = b
+        .iter()
+        .map(|item| Some(normalize_record_for_conflict_compare(item)))
+        .collect::<Vec<_>>();
+    for item in a {
+        let normalized = normalize_record_for_conflict_compare(item);
+        match unmatched_b
+            .iter_mut()
+            .find(|candidate| candidate.as_ref() == Some(&normalized))
+        {
+            Some(candidate) => {
+                *candidate = None;
+                diff.same_count += 1;
+            }
+            None => diff.only_in_a.push((*item).clone()),
+        }
+    }
+    diff.only_in_b.extend(
+        b.iter()
+            .zip(unmatched_b)
+            .filter(|(_, candidate)| candidate.is_some())
+            .map(|(item, _)| (*item).clone()),
+    );
+}
+
+/// 字段级差异，忽略 charName / weaponName / poolName（与冲突判定一致）
+fn diff_fields(a: &Value, b: &Value) -> Vec<FieldDiff> {
+    let a = normalize_record_for_conflict_compare(a);
+    let b = normalize_record_for_conflict_compare(b);
+    let (Some(a_obj), Some(b_obj)) = (a.as_object(), b.as_object()) else {
+        return if a == b {
+            Vec::new()
+        } else {
+            vec![FieldDiff {
+                field: String::new(),
+                a,
+                b,
+            }]
+        };
+    };
+
+    let fields = a_obj.keys().chain(b_obj.keys()).collect::<BTreeSet<_>>();
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let a_value = a_obj.get(field).cloned().unwrap_or(Value::Null);
+            let b_value = b_obj.get(field).cloned().unwrap_or(Value::Null);
+            (a_value != b_value).then(|| FieldDiff {
+                field: field.clone(),
+                a: a_value,
+                b: b_value,
+            })
+        })
+        .collect()
+}
+
+fn diff_pool(gacha_type: &str, pool_key: &str, a: &[Value], b: &[Value]) -> PoolDiff {
+    let a_index = index_records(a);
+    let b_index = index_records(b);
+    let mut diff = PoolDiff {
+        gacha_type: gacha_type.to_string(),
+        pool_key: pool_key.to_string(),
+        only_in_a: Vec::new(),
+        only_in_b: Vec::new(),
+        changed: Vec::new(),
+        same_count: 0,
+        duplicates_in_a: a_index
+            .duplicates
+            .iter()
+            .map(|item| (*item).clone())
+            .collect(),
+        duplicates_in_b: b_index
+            .duplicates
+            .iter()
+            .map(|item| (*item).clone())
+            .collect(),
+    };
+
+    let mut keys = a_index
+        .by_seq_id
+        .keys()
+        .chain(b_index.by_seq_id.keys())
+        .collect::<Vec<_>>();
+    keys.sort_by(|x, y| compare_seqid(y, x));
+    keys.dedup();
+
+    for key in keys {
+        match (a_index.by_seq_id.get(key), b_index.by_seq_id.get(key)) {
+            (Some(a_item), None) => diff.only_in_a.push((*a_item).clone()),
+            (None, Some(b_item)) => diff.only_in_b.push((*b_item).clone()),
+            (Some(a_item), Some(b_item)) => {
+                let fields = diff_fields(a_item, b_item);
+                if fields.is_empty() {
+                    diff.same_count += 1;
+                } else {
+                    diff.changed.push(ChangedRecord {
+                        seq_id: key.clone(),
+                        fields,
+                        a: (*a_item).clone(),
+                        b: (*b_item).clone(),
+                    });
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    match_without_seq_id(&mut diff, &a_index.without_seq_id, &b_index.without_seq_id);
+    diff
+}
+
+/// 比较两个 { poolKey: [record] } 结构
+pub(crate) fn diff_record_maps(gacha_type: &str, a: &Value, b: &Value) -> Vec<PoolDiff> {
+    let mut pool_keys = BTreeSet::new();
+    for map in [a, b] {
+        if let Some(obj) = map.as_object() {
+            pool_keys.extend(obj.keys().cloned());
+        }
+    }
+
+    let items = |map: &Value, key: &str| {
+        map.get(key)
+            .and_then(|value| value.as_array())
+            .cloned()
+            .unwrap_or_default()
+    };
+    pool_keys
+        .into_iter()
+        .map(|key| diff_pool(gacha_type, &key, &items(a, &key), &items(b, &key)))
+        .collect()
+}
+
+pub(crate) fn diff_record_values(a: &Value, b: &Value) -> Vec<PoolDiff> {
+    let empty = Value::Object(Default::default());
+    let mut pools = Vec::new();
+    for gacha_type in ["character", "weapon"] {
+        pools.extend(diff_record_maps(
+            gacha_type,
+            a.get(gacha_type).unwrap_or(&empty),
+            b.get(gacha_type).unwrap_or(&empty),
+        ));
+    }
+    pools
+}
+
+fn read_record_value(path: &Path) -> Result<Value, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let value: Value = serde_json::from_str(text.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    if !value.is_object() {
+        return Err(format!("{}: not a record file or bundle", path.display()));
+    }
+    Ok(value)
+}
+
+fn build_result(a_path: &Path, b_path: &Path) -> Result<RecordDiffResult, String> {
+    let pools = diff_record_values(&read_record_value(a_path)?, &read_record_value(b_path)?);
+    let only_in_a_count = pools.iter().map(|pool| pool.only_in_a.len()).sum::<usize>();
+    let only_in_b_count = pools.iter().map(|pool| pool.only_in_b.len()).sum::<usize>();
+    let changed_count = pools.iter().map(|pool| pool.changed.len()).sum::<usize>();
+    let same_count = pools.iter().map(|pool| pool.same_count).sum();
+    let duplicate_count = pools
+        .iter()
+        .map(|pool| pool.duplicates_in_a.len() + pool.duplicates_in_b.len())
+        .sum::<usize>();
+
+    Ok(RecordDiffResult {
+        a_path: a_path.to_string_lossy().to_string(),
+        b_path: b_path.to_string_lossy().to_string(),
+        identical: only_in_a_count == 0
+            && only_in_b_count == 0
+            && changed_count == 0
+            && duplicate_count == 0,
+        only_in_a_count,
+        only_in_b_count,
+        changed_count,
+        same_count,
+        duplicate_count,
+        // 只返回有差异的卡池
+        pools: pools
+            .into_iter()
+            .filter(|pool| {
+                !pool.only_in_a.is_empty()
+                    || !pool.only_in_b.is_empty()
+                    || !pool.changed.is_empty()
+                    || !pool.duplicates_in_a.is_empty()
+                    || !pool.duplicates_in_b.is_empty()
+            })
+            .collect(),
+    })
+}
+
+#[command]
+pub fn diff_record_files(a_path: String, b_path: String) -> Result<RecordDiffResult, String> {
+    if a_path.trim().is_empty() || b_path.trim().is_empty() {
+        return Err("File path cannot be empty".into());
+    }
+    build_result(Path::new(a_path.trim()), Path::new(b_path.trim()))
+}
+
+/// 比较 WebDAV 冲突时保存的 <key>.local.json（A）与 <key>.remote.json（B）
+#[command]
+pub fn diff_conflict_snapshots(user_key: String) -> Result<RecordDiffResult, String> {
+    let (local_path, remote_path) = conflict_snapshot_paths(user_key.trim())?;
+    if !local_path.exists() || !remote_path.exists() {
+        return Err(format!(
+            "No conflict snapshot for account: {}",
+            user_key.trim()
+        ));
+    }
+    build_result(&local_path, &remote_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(seq_id: Option<&str>, name: &str, rarity: u8) -> Value {
+        let mut item = json!({
+            "charId": format!("chr_{}", name),
+            "charName": name,
+            "gachaTs": "1768001000000",
+            "poolId": "special_1_0_1",
+            "rarity": rarity,
+        });
+        if let Some(seq_id) = seq_id {
+            item["seqId"] = json!(seq_id);
+        }
+        item
+    }
+
+    #[test]
+    fn matches_by_seq_id_and_reports_field_changes() {
+        let a = [record(Some("2"), "a", 4), record(Some("1"), "b", 5)];
+        let b = [record(Some("3"), "c", 4), record(Some("2"), "a", 6)];
+        let diff = diff_pool("character", "pool", &a, &b);
+        assert_eq!(diff.only_in_a, vec![a[1].clone()]);
+        assert_eq!(diff.only_in_b, vec![b[0].clone()]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].seq_id, "2");
+        assert_eq!(diff.changed[0].fields[0].field, "rarity");
+        assert_eq!(diff.same_count, 0);
+    }
+
+    #[test]
+    fn records_without_seq_id_are_not_paired_by_position() {
+        let a = [record(None, "a", 4), record(None, "same", 5)];
+        let b = [record(None, "same", 5), record(None, "c", 6)];
+        let diff = diff_pool("character", "pool", &a, &b);
+        assert_eq!(diff.same_count, 1);
+        assert!(diff.changed.is_empty());
+        assert_eq!(diff.only_in_a, vec![a[0].clone()]);
+        assert_eq!(diff.only_in_b, vec![b[1].clone()]);
+    }
+
+    #[test]
+    fn identical_records_without_seq_id_match_one_to_one() {
+        let a = [record(None, "x", 4), record(None, "x", 4)];
+        let b = [record(None, "x", 4)];
+        let diff = diff_pool("character", "pool", &a, &b);
+        assert_eq!(diff.same_count, 1);
+        assert_eq!(diff.only_in_a.len(), 1);
+        assert!(diff.only_in_b.is_empty());
+    }
+
+    #[test]
+    fn duplicate_seq_ids_are_reported() {
+        let a = [
+            record(Some("7"), "first", 4),
+            record(Some("7"), "second", 6),
+        ];
+        let b = [record(Some("7"), "first", 4)];
+        let diff = diff_pool("character", "pool", &a, &b);
+        assert_eq!(diff.same_count, 1);
+        assert_eq!(diff.duplicates_in_a, vec![a[1].clone()]);
+        assert!(diff.duplicates_in_b.is_empty());
+    }
+
+    #[test]
+    fn result_is_not_identical_when_a_file_has_duplicates() {
+        let dir = std::env::temp_dir().join(format!("endfield-diff-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let a_path = dir.join("a.json");
+        let b_path = dir.join("b.json");
+        let item = record(Some("7"), "first", 4);
+        fs::write(
+            &a_path,
+            json!({ "character": { "pool": [item, item] } }).to_string(),
+        )
+        .unwrap();
+        fs::write(
+            &b_path,
+            json!({ "character": { "pool": [item] } }).to_string(),
+        )
+        .unwrap();
+
+        let result = build_result(&a_path, &b_path).unwrap();
+        assert!(!result.identical);
+        assert_eq!(result.duplicate_count, 1);
+        assert_eq!(result.same_count, 1);
+        assert_eq!(result.pools.len(), 1);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,555 @@
+//! 通用抽卡记录交换格式（参考社区 UIGF / SRGF 标准）
+//!
+//! ```json
+//! {
+//!   "info": {
+//!     "format": "EFGF",
+//!     "version": "v1.0",
+//!     "export_app": "endfield-gacha",
+//!     "export_app_version": "0.6.3",
+//!     "export_timestamp": 1767225600,
+//!     "export_time": "2026-01-01T00:00:00.000Z"
+//!   },
+//!   "accounts": [
+//!     {
+//!       "uid": "...",
+//!       "role_id": "...",
+//!       "provider": "hypergryph",
+//!       "server_id": "1",
+//!       "server_name": "...",
+//!       "nick_name": "...",
+//!       "timezone": "+08:00",
+//!       "character": [{ "gacha_type": "E_CharacterGachaPoolType_Special", "seqId": "...", ... }],
+//!       "weapon": [{ "gacha_type": "weponbox_1_0_1", "seqId": "...", ... }]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! 记录字段保持与本地 `gachaData/<key>.json` 一致，额外的 `gacha_type` 为本地记录中的分组键：
+//! 角色记录为卡池类型，武器记录为卡池 ID。
+
+use crate::webdav::{
+    get_user_key, load_config_data, load_local_record_value, merge_record_maps_reporting,
+    normalize_provider, now_iso_string, save_config_data, upsert_user_from_bundle,
+    write_bundle_to_local_record, AccountBundle, BundleAccount, RecordConflict, UserRoleMeta,
+};
+use crate::{calc_max_seqid_from_records, validate_user_key};
+use chrono::{Local, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use tauri::command;
+
+pub(crate) const INTERCHANGE_FORMAT: &str = "EFGF";
+pub(crate) const INTERCHANGE_VERSION: &str = "v1.0";
+const GROUP_FIELD: &str = "gacha_type";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct InterchangeInfo {
+    #[serde(default)]
+    pub(crate) format: String,
+    #[serde(default)]
+    pub(crate) version: String,
+    #[serde(default)]
+    pub(crate) export_app: String,
+    #[serde(default)]
+    pub(crate) export_app_version: String,
+    #[serde(default)]
+    pub(crate) export_timestamp: i64,
+    #[serde(default)]
+    pub(crate) export_time: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub(crate) struct InterchangeAccount {
+    #[serde(default)]
+    pub(crate) uid: String,
+    #[serde(default)]
+    pub(crate) role_id: String,
+    #[serde(default)]
+    pub(crate) provider: String,
+    #[serde(default)]
+    pub(crate) server_id: String,
+    #[serde(default)]
+    pub(crate) server_name: String,
+    #[serde(default)]
+    pub(crate) nick_name: String,
+    #[serde(default)]
+    pub(crate) timezone: String,
+    #[serde(default)]
+    pub(crate) character: Vec<Value>,
+    #[serde(default)]
+    pub(crate) weapon: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct InterchangeFile {
+    pub(crate) info: InterchangeInfo,
+    #[serde(default)]
+    pub(crate) accounts: Vec<InterchangeAccount>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterchangeExportResult {
+    path: String,
+    character_count: usize,
+    weapon_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterchangeImportAccount {
+    user_key: String,
+    character_added: usize,
+    weapon_added: usize,
+    duplicates: usize,
+    conflicts: Vec<RecordConflict>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterchangeImportResult {
+    dry_run: bool,
+    accounts: Vec<InterchangeImportAccount>,
+}
+
+fn local_timezone() -> String {
+    Local::now().format("%:z").to_string()
+}
+
+pub(crate) fn build_info() -> InterchangeInfo {
+    let now = Utc::now();
+    InterchangeInfo {
+        format: INTERCHANGE_FORMAT.into(),
+        version: INTERCHANGE_VERSION.into(),
+        export_app: "endfield-gacha".into(),
+        export_app_version: env!("CARGO_PKG_VERSION").into(),
+        export_timestamp: now.timestamp(),
+        export_time: now_iso_string(),
+    }
+}
+
+/// 将 { 分组键: [记录] } 展开为带 gacha_type 的扁平列表
+pub(crate) fn flatten_record_map(records: &Value) -> Vec<Value> {
+    let mut result = Vec::new();
+    let Some(obj) = records.as_object() else {
+        return result;
+    };
+    for (group, list) in obj.iter() {
+        let Some(items) = list.as_array() else {
+            continue;
+        };
+        for item in items {
+            let Some(fields) = item.as_object() else {
+                continue;
+            };
+            let mut record = Map::new();
+            record.insert(GROUP_FIELD.into(), Value::String(group.clone()));
+            for (key, value) in fields.iter() {
+                record.insert(key.clone(), value.clone());
+            }
+            result.push(Value::Object(record));
+        }
+    }
+    result
+}
+
+/// 按 gacha_type 还原为本地记录结构，缺失时按角色卡池类型 / 武器卡池 ID 回退
+pub(crate) fn group_record_list(items: &[Value], is_weapon: bool) -> Value {
+    let mut result = Map::new();
+    for item in items {
+        let Some(fields) = item.as_object() else {
+            continue;
+        };
+        let mut record = fields.clone();
+        let group = record
+            .remove(GROUP_FIELD)
+            .and_then(|value| value.as_str().map(|text| text.trim().to_string()))
+            .filter(|value| !value.is_empty())
+            .or_else(|| {
+                let fallback = if is_weapon { "poolId" } else { "poolType" };
+                record
+                    .get(fallback)
+                    .and_then(|value| value.as_str())
+                    .map(|text| text.trim().to_string())
+                    .filter(|value| !value.is_empty())
+            });
+        let Some(group) = group else {
+            continue;
+        };
+        let list = result
+            .entry(group)
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Some(list) = list.as_array_mut() {
+            list.push(Value::Object(record));
+        }
+    }
+    Value::Object(result)
+}
+
+fn count_records(records: &Value) -> usize {
+    records
+        .as_object()
+        .map(|obj| {
+            obj.values()
+                .map(|list| list.as_array().map(|items| items.len()).unwrap_or(0))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+pub(crate) fn build_interchange_account(user_key: &str) -> Result<InterchangeAccount, String> {
+    let config = load_config_data()?;
+    let user = config
+        .users
+        .iter()
+        .find(|item| get_user_key(item) == user_key)
+        .cloned()
+        .ok_or_else(|| format!("Account not found: {}", user_key))?;
+    let role = user.role_id.clone().unwrap_or_default();
+    let full_data = load_local_record_value(user_key)?;
+
+    Ok(InterchangeAccount {
+        uid: user.uid,
+        role_id: role.role_id,
+        provider: user.provider,
+        server_id: role.server_id,
+        server_name: role.server_name,
+        nick_name: role.nick_name,
+        timezone: local_timezone(),
+        character: flatten_record_map(full_data.get("character").unwrap_or(&Value::Null)),
+        weapon: flatten_record_map(full_data.get("weapon").unwrap_or(&Value::Null)),
+    })
+}
+
+pub(crate) fn parse_interchange_text(text: &str) -> Result<InterchangeFile, String> {
+    let file: InterchangeFile = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if file.info.format != INTERCHANGE_FORMAT {
+        return Err(format!(
+            "Unsupported interchange format: {}",
+            file.info.format
+        ));
+    }
+    let major = file.info.version.trim_start_matches('v');
+    if !major.starts_with("1.") && major != "1" {
+        return Err(format!(
+            "Unsupported interchange version: {}",
+            file.info.version
+        ));
+    }
+    Ok(file)
+}
+
+fn account_to_bundle(account: &InterchangeAccount) -> Result<AccountBundle, String> {
+    let uid = account.uid.trim().to_string();
+    let role_id = account.role_id.trim().to_string();
+    if uid.is_empty() || role_id.is_empty() {
+        return Err("Interchange account is missing uid or role_id".into());
+    }
+    let key = format!("{}_{}", uid, role_id);
+    validate_user_key(&key)?;
+    let character = group_record_list(&account.character, false);
+    let weapon = group_record_list(&account.weapon, true);
+
+    Ok(AccountBundle {
+        schema_version: 1,
+        account: BundleAccount {
+            key,
+            provider: normalize_provider(&account.provider)?,
+            uid,
+            role_id: UserRoleMeta {
+                server_id: account.server_id.trim().to_string(),
+                server_name: account.server_name.trim().to_string(),
+                nick_name: account.nick_name.trim().to_string(),
+                role_id,
+            },
+        },
+        updated_at: now_iso_string(),
+        character_max_seqid: calc_max_seqid_from_records(&character),
+        weapon_max_seqid: calc_max_seqid_from_records(&weapon),
+        character,
+        weapon,
+    })
+}
+
+/// 将外部记录合并进本地账号：seqId 去重，字段冲突保留本地一侧并记录在报告中
+pub(crate) fn merge_bundle_into_local(
+    incoming: &AccountBundle,
+    dry_run: bool,
+) -> Result<(usize, usize, usize, Vec<RecordConflict>), String> {
+    let user_key = incoming.account.key.clone();
+    let local = load_local_record_value(&user_key)?;
+    let local_character = local.get("character").cloned().unwrap_or(Value::Null);
+    let local_weapon = local.get("weapon").cloned().unwrap_or(Value::Null);
+
+    let (character, mut conflicts) =
+        merge_record_maps_reporting(&local_character, &incoming.character);
+    let (weapon, weapon_conflicts) = merge_record_maps_reporting(&local_weapon, &incoming.weapon);
+    conflicts.extend(weapon_conflicts);
+
+    let character_added = count_records(&character).saturating_sub(count_records(&local_character));
+    let weapon_added = count_records(&weapon).saturating_sub(count_records(&local_weapon));
+    let incoming_total = count_records(&incoming.character) + count_records(&incoming.weapon);
+    let duplicates =
+        incoming_total.saturating_sub(character_added + weapon_added + conflicts.len());
+
+    if !dry_run {
+        let mut config = load_config_data()?;
+        let merged = AccountBundle {
+            character_max_seqid: calc_max_seqid_from_records(&character),
+            weapon_max_seqid: calc_max_seqid_from_records(&weapon),
+            character,
+            weapon,
+            ..incoming.clone()
+        };
+        write_bundle_to_local_record(&user_key, &merged)?;
+        upsert_user_from_bundle(&mut config, &merged, true)?;
+        let has_current_user = config
+            .users
+            .iter()
+            .any(|user| get_user_key(user) == config.current_user);
+        if !has_current_user {
+            config.current_user = user_key.clone();
+        }
+        save_config_data(&config)?;
+    }
+
+    Ok((character_added, weapon_added, duplicates, conflicts))
+}
+
+#[command]
+pub fn export_interchange(
+    user_keys: Vec<String>,
+    path: String,
+) -> Result<InterchangeExportResult, String> {
+    let path = path.trim().to_string();
+    if path.is_empty() {
+        return Err("Export path cannot be empty".into());
+    }
+
+    let mut accounts = Vec::new();
+    for key in user_keys
+        .iter()
+        .map(|key| key.trim())
+        .filter(|key| !key.is_empty())
+    {
+        accounts.push(build_interchange_account(key)?);
+    }
+    if accounts.is_empty() {
+        return Err("At least one account is required".into());
+    }
+
+    let character_count = accounts.iter().map(|item| item.character.len()).sum();
+    let weapon_count = accounts.iter().map(|item| item.weapon.len()).sum();
+    let file = InterchangeFile {
+        info: build_info(),
+        accounts,
+    };
+    let json = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| e.to_string())?;
+
+    Ok(InterchangeExportResult {
+        path,
+        character_count,
+        weapon_count,
+    })
+}
+
+#[command]
+pub fn import_interchange(path: String, dry_run: bool) -> Result<InterchangeImportResult, String> {
+    let content = fs::read_to_string(path.trim()).map_err(|e| e.to_string())?;
+    let file = parse_interchange_text(&content)?;
+
+    let mut accounts = Vec::new();
+    for account in file.accounts.iter() {
+        let bundle = account_to_bundle(account)?;
+        let (character_added, weapon_added, duplicates, conflicts) =
+            merge_bundle_into_local(&bundle, dry_run)?;
+        accounts.push(InterchangeImportAccount {
+            user_key: bundle.account.key.clone(),
+            character_added,
+            weapon_added,
+            duplicates,
+            conflicts,
+        });
+    }
+
+    Ok(InterchangeImportResult { dry_run, accounts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil;
+    use serde_json::json;
+
+    const USER_KEY: &str = "1001_r1";
+
+    fn character(seq_id: &str, name: &str) -> Value {
+        json!({
+            "charId": "chr_1",
+            "charName": name,
+            "rarity": 6,
+            "poolId": "special_1_0_1",
+            "poolName": "限定寻访",
+            "gachaTs": "1768000000000",
+            "seqId": seq_id
+        })
+    }
+
+    fn local_records() -> Value {
+        json!({
+            "character": {
+                "E_CharacterGachaPoolType_Special": [character("2", "甲"), character("1", "乙")]
+            },
+            "weapon": {
+                "weponbox_1_0_1": [{
+                    "weaponId": "wpn_1",
+                    "weaponName": "武器",
+                    "rarity": 5,
+                    "poolId": "weponbox_1_0_1",
+                    "gachaTs": "1768000000000",
+                    "seqId": "7"
+                }]
+            }
+        })
+    }
+
+    fn interchange_with(account: InterchangeAccount) -> String {
+        serde_json::to_string(&InterchangeFile {
+            info: build_info(),
+            accounts: vec![account],
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn export_and_import_round_trip() {
+        let _guard = testutil::userdata();
+        testutil::write_config(&json!({ "users": [testutil::hypergryph_user("1001", "r1")] }));
+        testutil::write_record(USER_KEY, &local_records());
+        let path = testutil::temp_path("efgf.json")
+            .to_string_lossy()
+            .to_string();
+
+        let exported = export_interchange(vec![USER_KEY.into()], path.clone()).unwrap();
+        assert_eq!((exported.character_count, exported.weapon_count), (2, 1));
+        let file = parse_interchange_text(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(file.info.format, INTERCHANGE_FORMAT);
+        assert_eq!(
+            file.accounts[0].character[0][GROUP_FIELD],
+            "E_CharacterGachaPoolType_Special"
+        );
+
+        // 在空白 userData 中导入：dry run 不落盘，正式导入后记录与原始一致
+        fs::remove_file(crate::get_record_path(USER_KEY).unwrap()).unwrap();
+        testutil::write_config(&json!({ "users": [] }));
+        let preview = import_interchange(path.clone(), true).unwrap();
+        assert_eq!(preview.accounts[0].character_added, 2);
+        assert_eq!(preview.accounts[0].weapon_added, 1);
+        assert!(!crate::get_record_path(USER_KEY).unwrap().exists());
+
+        let imported = import_interchange(path.clone(), false).unwrap();
+        assert_eq!(imported.accounts[0].user_key, USER_KEY);
+        let record = testutil::read_record(USER_KEY);
+        assert_eq!(record["character"], local_records()["character"]);
+        assert_eq!(record["weapon"], local_records()["weapon"]);
+        let config = load_config_data().unwrap();
+        assert_eq!(config.current_user, USER_KEY);
+        assert_eq!(get_user_key(&config.users[0]), USER_KEY);
+
+        let again = import_interchange(path, false).unwrap();
+        let account = &again.accounts[0];
+        assert_eq!((account.character_added, account.weapon_added), (0, 0));
+        assert_eq!(account.duplicates, 3);
+        assert!(account.conflicts.is_empty());
+    }
+
+    #[test]
+    fn conflicting_fields_keep_local_record() {
+        let _guard = testutil::userdata();
+        testutil::write_config(&json!({ "users": [testutil::hypergryph_user("1001", "r1")] }));
+        testutil::write_record(USER_KEY, &local_records());
+        let path = testutil::temp_path("efgf.json");
+        let mut account = build_interchange_account(USER_KEY).unwrap();
+        account.character[0]["charId"] = json!("chr_2");
+        fs::write(&path, interchange_with(account)).unwrap();
+
+        let result = import_interchange(path.to_string_lossy().to_string(), false).unwrap();
+        let conflicts = &result.accounts[0].conflicts;
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].seq_id, "2");
+        assert_eq!(
+            testutil::read_record(USER_KEY)["character"]["E_CharacterGachaPoolType_Special"][0]
+                ["charId"],
+            "chr_1"
+        );
+    }
+
+    #[test]
+    fn hostile_uid_is_rejected_before_writing() {
+        let _guard = testutil::userdata();
+        testutil::write_config(&json!({ "users": [] }));
+        let path = testutil::temp_path("evil.json");
+        for (uid, role_id) in [("../../x", "r1"), ("1001", "a/b"), ("1001", "..\\x")] {
+            fs::write(
+                &path,
+                interchange_with(InterchangeAccount {
+                    uid: uid.into(),
+                    role_id: role_id.into(),
+                    provider: "hypergryph".into(),
+                    character: vec![character("1", "甲")],
+                    ..InterchangeAccount::default()
+                }),
+            )
+            .unwrap();
+
+            for dry_run in [true, false] {
+                let err =
+                    import_interchange(path.to_string_lossy().to_string(), dry_run).unwrap_err();
+                assert!(err.contains("Invalid account key"), "{}", err);
+            }
+        }
+        let userdata = crate::get_userdata_dir().unwrap();
+        assert!(!userdata.join("x_r1.json").exists());
+        assert!(!userdata.parent().unwrap().join("x_r1.json").exists());
+        assert!(load_config_data().unwrap().users.is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_format_and_major_version() {
+        let mut file = InterchangeFile {
+            info: build_info(),
+            accounts: Vec::new(),
+        };
+        assert!(parse_interchange_text(&serde_json::to_string(&file).unwrap()).is_ok());
+        file.info.version = "v2.0".into();
+        let err = parse_interchange_text(&serde_json::to_string(&file).unwrap()).unwrap_err();
+        assert!(err.contains("version"), "{}", err);
+        file.info.format = "UIGF".into();
+        let err = parse_interchange_text(&serde_json::to_string(&file).unwrap()).unwrap_err();
+        assert!(err.contains("format"), "{}", err);
+    }
+
+    #[test]
+    fn group_field_falls_back_to_pool_type_or_pool_id() {
+        let grouped = group_record_list(
+            &[
+                json!({ "poolType": "E_CharacterGachaPoolType_Joint", "seqId": "1" }),
+                json!({ "gacha_type": " ", "seqId": "2" }),
+            ],
+            false,
+        );
+        assert_eq!(
+            grouped,
+            json!({ "E_CharacterGachaPoolType_Joint": [{ "poolType": "E_CharacterGachaPoolType_Joint", "seqId": "1" }] })
+        );
+        let weapons = group_record_list(&[json!({ "poolId": "weponbox_1_0_1" })], true);
+        assert_eq!(
+            weapons,
+            json!({ "weponbox_1_0_1": [{ "poolId": "weponbox_1_0_1" }] })
+        );
+    }
+}
@@ -6,7 +6,29 @@ use std::{thread, time::Duration};
 use tauri::command;
 use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder, WindowEvent};
 
+mod archive;
+mod card;
+mod collection;
+mod conflict;
+mod crypto;
+mod csv;
+mod davauth;
+mod diff;
+mod interchange;
+mod logscan;
+mod network;
+mod propfind;
+mod remote;
+mod s3;
+mod schema;
+mod share;
+mod spend;
+mod stats;
+mod syncfolder;
+#[cfg(test)]
+mod testutil;
 mod webdav;
+mod xlsx;
 
 #[cfg(target_os = "linux")]
 const APP_IDENTIFIER: &str = "com.bhao.endfieldgacha";
@@ -79,7 +101,16 @@ pub(crate) fn get_config_path() -> Result<PathBuf, String> {
     Ok(root.join("config.json"))
 }
 
+/// 账号 key 会用作 gachaData 下的文件名，拒绝可能越出该目录的 key
+pub(crate) fn validate_user_key(key: &str) -> Result<(), String> {
+    if key.is_empty() || key.contains('/') || key.contains('\\') || key.contains("..") {
+        return Err(format!("Invalid account key: {}", key));
+    }
+    Ok(())
+}
+
 pub(crate) fn get_record_path(uid: &str) -> Result<PathBuf, String> {
+    validate_user_key(uid)?;
     let root = get_userdata_dir()?;
     let gacha_dir = root.join("gachaData");
 
@@ -90,7 +121,7 @@ pub(crate) fn get_record_path(uid: &str) -> Result<PathBuf, String> {
     Ok(gacha_dir.join(format!("{}.json", uid)))
 }
 
-fn get_pool_info_path() -> Result<PathBuf, String> {
+pub(crate) fn get_pool_info_path() -> Result<PathBuf, String> {
     let root = get_userdata_dir()?;
     let gacha_dir = root.join("gachaData");
 
@@ -266,7 +297,27 @@ fn default_pool_info() -> serde_json::Value {
     ])
 }
 
-fn load_full_record(uid: &str) -> Result<serde_json::Value, String> {
+pub(crate) fn load_pool_info() -> Result<serde_json::Value, String> {
+    let file_path = get_pool_info_path()?;
+
+    if !file_path.exists() {
+        let default_data = default_pool_info();
+        let json_string = serde_json::to_string_pretty(&default_data).map_err(|e| e.to_string())?;
+        fs::write(&file_path, json_string).map_err(|e| e.to_string())?;
+        return Ok(default_data);
+    }
+
+    let content = fs::read_to_string(&file_path).map_err(|e| e.to_string())?;
+    let data: serde_json::Value =
+        serde_json::from_str(&content).unwrap_or_else(|_| serde_json::json!([]));
+    if data.is_array() {
+        Ok(data)
+    } else {
+        Ok(serde_json::json!([]))
+    }
+}
+
+pub(crate) fn load_full_record(uid: &str) -> Result<serde_json::Value, String> {
     let file_path = get_record_path(uid)?;
 
     if !file_path.exists() {
@@ -287,7 +338,7 @@ fn is_digits_only(value: &str) -> bool {
     !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit())
 }
 
-fn compare_seqid(a: &str, b: &str) -> std::cmp::Ordering {
+pub(crate) fn compare_seqid(a: &str, b: &str) -> std::cmp::Ordering {
     if a == b {
         return std::cmp::Ordering::Equal;
     }
@@ -313,7 +364,7 @@ fn compare_seqid(a: &str, b: &str) -> std::cmp::Ordering {
     a.cmp(b)
 }
 
-fn value_to_seqid(value: &serde_json::Value) -> Option<String> {
+pub(crate) fn value_to_seqid(value: &serde_json::Value) -> Option<String> {
     if let Some(s) = value.as_str() {
         let s = s.trim();
         if s.is_empty() {
@@ -330,7 +381,7 @@ fn value_to_seqid(value: &serde_json::Value) -> Option<String> {
     }
 }
 
-fn calc_max_seqid_from_records(records: &serde_json::Value) -> String {
+pub(crate) fn calc_max_seqid_from_records(records: &serde_json::Value) -> String {
     let obj = match records.as_object() {
         Some(o) => o,
         None => return "".into(),
@@ -762,23 +813,7 @@ fn ensure_pool_info_defaults() -> Result<String, String> {
 
 #[command]
 fn read_pool_info() -> Result<serde_json::Value, String> {
-    let file_path = get_pool_info_path()?;
-
-    if !file_path.exists() {
-        let default_data = default_pool_info();
-        let json_string = serde_json::to_string_pretty(&default_data).map_err(|e| e.to_string())?;
-        fs::write(&file_path, json_string).map_err(|e| e.to_string())?;
-        return Ok(default_data);
-    }
-
-    let content = fs::read_to_string(&file_path).map_err(|e| e.to_string())?;
-    let data: serde_json::Value =
-        serde_json::from_str(&content).unwrap_or_else(|_| serde_json::json!([]));
-    if data.is_array() {
-        Ok(data)
-    } else {
-        Ok(serde_json::json!([]))
-    }
+    load_pool_info()
 }
 
 #[command]
@@ -819,10 +854,42 @@ pub fn run() {
             save_pool_info,
             get_os,
             open_login_window,
+            stats::stats_timeline,
+            stats::stats_aggregate,
+            stats::stats_free_pulls,
+            spend::estimate_spend,
+            collection::stats_collection,
+            archive::export_archive,
+            archive::import_archive,
+            card::export_summary_card,
+            csv::export_csv,
+            diff::diff_record_files,
+            diff::diff_conflict_snapshots,
+            interchange::export_interchange,
+            interchange::import_interchange,
+            logscan::scan_game_log,
+            logscan::import_log_account,
+            schema::get_json_schema,
+            schema::export_json_schemas,
+            schema::validate_file,
+            share::export_share,
+            xlsx::export_xlsx,
+            xlsx::import_xlsx,
             webdav::webdav_test_connection,
             webdav::webdav_sync_account,
+            webdav::webdav_sync_all,
             webdav::webdav_list_restore_accounts,
-            webdav::webdav_restore_accounts
+            webdav::webdav_restore_accounts,
+            conflict::webdav_list_conflicts,
+            conflict::webdav_resolve_conflict,
+            webdav::webdav_encryption_status,
+            webdav::webdav_unlock_encryption,
+            webdav::webdav_set_encryption,
+            webdav::webdav_list_versions,
+            webdav::webdav_restore_version,
+            webdav::webdav_delete_account,
+            webdav::webdav_prune_remote,
+            webdav::webdav_list_remote_files
         ])
         .setup(|app| {
             #[cfg(any(target_os = "linux", target_os = "macos"))]
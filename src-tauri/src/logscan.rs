@@ -0,0 +1,452 @@
+//! 从游戏本地日志中提取抽卡记录链接（u8_token），创建或更新 source 为 log 的账号
+
+use crate::get_record_path;
+use crate::network::{build_client, describe_request_error};
+use crate::webdav::{
+    get_user_key, load_config_data, load_local_record_value, normalize_provider, save_config_data,
+    AppUser, UserRoleMeta,
+};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use tauri::command;
+
+const PROVIDERS: [&str; 2] = ["hypergryph", "gryphline"];
+const DEFAULT_USER_AGENT: &str = "endfield-gacha/log";
+const SERVER_ID_PARAM_KEYS: [&str; 4] = ["server_id", "serverId", "serverid", "server"];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogScanOptions {
+    /// 日志或缓存文件路径；为空时按 provider 查找默认的 sdklogs/HGWebview.log
+    #[serde(default)]
+    path: Option<String>,
+    /// hypergryph / gryphline；为空时先国服后国际服
+    #[serde(default)]
+    provider: Option<String>,
+    #[serde(default)]
+    user_agent: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogScanResult {
+    path: String,
+    provider: String,
+    url: String,
+    u8_token: String,
+    pool_id: String,
+    server_id: String,
+    channel_label: String,
+    params: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogImportResult {
+    user_key: String,
+    uid: String,
+    role_id: String,
+    nick_name: String,
+    server_id: String,
+    server_name: String,
+    provider: String,
+    channel_label: String,
+    created: bool,
+    log_path: String,
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("USERPROFILE")
+        .or_else(|| env::var_os("HOME"))
+        .map(PathBuf::from)
+}
+
+fn default_log_path(provider: &str) -> Option<PathBuf> {
+    let vendor = if provider == "gryphline" {
+        "Gryphline"
+    } else {
+        "Hypergryph"
+    };
+    home_dir().map(|home| {
+        home.join("AppData")
+            .join("LocalLow")
+            .join(vendor)
+            .join("Endfield")
+            .join("sdklogs")
+            .join("HGWebview.log")
+    })
+}
+
+fn gacha_url_prefix(provider: &str) -> String {
+    format!("https://ef-webview.{}.com/page/gacha_", provider)
+}
+
+/// 与前端 parseGachaParams 一致：必须同时包含 u8_token 与 pool_id
+pub(crate) fn parse_gacha_params(uri: &str) -> Option<BTreeMap<String, String>> {
+    let url = Url::parse(uri).ok()?;
+    let params = url
+        .query_pairs()
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect::<BTreeMap<_, _>>();
+    let has = |key: &str| params.get(key).is_some_and(|value| !value.is_empty());
+    if !has("u8_token") || !has("pool_id") {
+        return None;
+    }
+    Some(params)
+}
+
+/// 缓存文件中的链接可能被 JSON / HTML 转义，统一还原
+fn unescape_url(raw: &str) -> String {
+    raw.replace("\\u0026", "&")
+        .replace("\\/", "/")
+        .replace("&amp;", "&")
+}
+
+fn is_url_terminator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '"' | '\'' | '<' | '>' | '`' | ',')
+}
+
+/// 从日志内容中倒序查找最新一条可用的抽卡链接
+pub(crate) fn find_gacha_url(content: &str, provider: &str) -> Option<String> {
+    let prefix = gacha_url_prefix(provider);
+    content.lines().rev().find_map(|line| {
+        let line = unescape_url(line);
+        let starts = line.match_indices(&prefix).map(|(start, _)| start);
+        starts
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .find_map(|start| {
+                let rest = &line[start..];
+                let end = rest.find(is_url_terminator).unwrap_or(rest.len());
+                let url = rest[..end].trim_end_matches([')', ']', '}', ';']);
+                parse_gacha_params(url).map(|_| url.to_string())
+            })
+    })
+}
+
+fn pick_server_id(provider: &str, params: &BTreeMap<String, String>) -> Result<String, String> {
+    if provider != "gryphline" {
+        return Ok("1".into());
+    }
+    SERVER_ID_PARAM_KEYS
+        .iter()
+        .filter_map(|key| params.get(*key))
+        .map(|value| value.trim())
+        .find(|value| !value.is_empty())
+        .map(String::from)
+        .ok_or_else(|| "Gacha URL is missing serverId (required for global server)".to_string())
+}
+
+fn channel_label(provider: &str, params: &BTreeMap<String, String>) -> String {
+    if provider == "gryphline" {
+        return "国际服".into();
+    }
+    let channel = params.get("channel").map(String::as_str).unwrap_or("");
+    let sub_channel = params.get("subChannel").map(String::as_str).unwrap_or("");
+    match (channel, sub_channel) {
+        ("1", "1") => "官服".into(),
+        ("2", "2") => "B服".into(),
+        _ => "未知渠道".into(),
+    }
+}
+
+fn read_log_text(path: &PathBuf) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub(crate) fn scan_log(options: &LogScanOptions) -> Result<LogScanResult, String> {
+    let providers = match options.provider.as_deref().map(str::trim) {
+        Some(value) if !value.is_empty() => vec![normalize_provider(value)?],
+        _ => PROVIDERS.iter().map(|item| item.to_string()).collect(),
+    };
+    let custom_path = options
+        .path
+        .as_deref()
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from);
+
+    let mut searched = Vec::new();
+    for provider in providers.iter() {
+        let Some(path) = custom_path.clone().or_else(|| default_log_path(provider)) else {
+            continue;
+        };
+        if !path.exists() {
+            searched.push(path.display().to_string());
+            continue;
+        }
+        let content = read_log_text(&path)?;
+        let Some(url) = find_gacha_url(&content, provider) else {
+            searched.push(path.display().to_string());
+            continue;
+        };
+        let params = parse_gacha_params(&url).unwrap_or_default();
+        return Ok(LogScanResult {
+            path: path.to_string_lossy().to_string(),
+            provider: provider.clone(),
+            u8_token: params.get("u8_token").cloned().unwrap_or_default(),
+            pool_id: params.get("pool_id").cloned().unwrap_or_default(),
+            server_id: pick_server_id(provider, &params)?,
+            channel_label: channel_label(provider, &params),
+            url,
+            params,
+        });
+    }
+
+    searched.dedup();
+    Err(format!(
+        "No gacha URL found in game log, open the gacha history page in game first (searched: {})",
+        searched.join(", ")
+    ))
+}
+
+struct RoleInfo {
+    uid: String,
+    role_id: String,
+    nick_name: String,
+    server_name: String,
+}
+
+async fn query_role(scan: &LogScanResult, user_agent: &str) -> Result<RoleInfo, String> {
+    let client = build_client(load_config_data()?.network(), user_agent)?;
+    let response = client
+        .post(format!(
+            "https://u8.{}.com/game/role/v1/query_role_list",
+            scan.provider
+        ))
+        .json(&serde_json::json!({ "token": scan.u8_token, "serverId": scan.server_id }))
+        .send()
+        .await
+        .map_err(|e| describe_request_error(&e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "query_role_list request failed: {}",
+            response.status()
+        ));
+    }
+
+    let json: Value = response.json().await.map_err(|e| e.to_string())?;
+    if json.get("status").and_then(Value::as_i64) != Some(0) {
+        return Err(format!(
+            "query_role_list returned error: {}",
+            json.get("msg").and_then(Value::as_str).unwrap_or("unknown")
+        ));
+    }
+    let data = json.get("data").cloned().unwrap_or(Value::Null);
+    let text = |value: Option<&Value>| match value {
+        Some(Value::String(text)) => text.trim().to_string(),
+        Some(Value::Number(number)) => number.to_string(),
+        _ => String::new(),
+    };
+    let roles = data
+        .get("roles")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let role = roles
+        .iter()
+        .find(|role| text(role.get("serverId")) == scan.server_id)
+        .or_else(|| roles.first())
+        .cloned()
+        .unwrap_or(Value::Null);
+
+    let info = RoleInfo {
+        uid: text(data.get("uid")),
+        role_id: text(role.get("roleId")),
+        nick_name: {
+            let name = text(role.get("nickname"));
+            if name.is_empty() {
+                text(role.get("nickName"))
+            } else {
+                name
+            }
+        },
+        server_name: text(role.get("serverName")),
+    };
+    if info.uid.is_empty() {
+        return Err("query_role_list response is missing uid".into());
+    }
+    if info.role_id.is_empty() {
+        return Err("query_role_list response is missing roleId".into());
+    }
+    Ok(info)
+}
+
+/// 写入账号配置：已有账号只更新角色信息，保留登录 Token
+fn upsert_log_user(scan: &LogScanResult, role: &RoleInfo) -> Result<(String, bool), String> {
+    let key = format!("{}_{}", role.uid, role.role_id);
+    let meta = UserRoleMeta {
+        server_id: scan.server_id.clone(),
+        server_name: if role.server_name.is_empty() {
+            if scan.provider == "gryphline" {
+                "Global".into()
+            } else {
+                "China".into()
+            }
+        } else {
+            role.server_name.clone()
+        },
+        nick_name: if role.nick_name.is_empty() {
+            role.role_id.clone()
+        } else {
+            role.nick_name.clone()
+        },
+        role_id: role.role_id.clone(),
+    };
+
+    let mut config = load_config_data()?;
+    let created = match config
+        .users
+        .iter_mut()
+        .find(|item| get_user_key(item) == key)
+    {
+        Some(user) => {
+            user.key = key.clone();
+            user.uid = role.uid.clone();
+            user.provider = scan.provider.clone();
+            user.role_id = Some(meta);
+            if user.token.trim().is_empty() {
+                user.source = "log".into();
+            }
+            false
+        }
+        None => {
+            config.users.push(AppUser {
+                key: key.clone(),
+                uid: role.uid.clone(),
+                token: String::new(),
+                provider: scan.provider.clone(),
+                role_id: Some(meta),
+                source: "log".into(),
+            });
+            true
+        }
+    };
+    if config.current_user.trim().is_empty() {
+        config.current_user = key.clone();
+    }
+    save_config_data(&config)?;
+
+    let record_path = get_record_path(&key)?;
+    if !record_path.exists() {
+        let record = load_local_record_value(&key)?;
+        let json = serde_json::to_string_pretty(&record).map_err(|e| e.to_string())?;
+        fs::write(record_path, json).map_err(|e| e.to_string())?;
+    }
+    Ok((key, created))
+}
+
+#[command]
+pub fn scan_game_log(options: Option<LogScanOptions>) -> Result<LogScanResult, String> {
+    scan_log(&options.unwrap_or_default())
+}
+
+#[command]
+pub async fn import_log_account(
+    options: Option<LogScanOptions>,
+) -> Result<LogImportResult, String> {
+    let options = options.unwrap_or_default();
+    let scan = scan_log(&options)?;
+    let user_agent = options
+        .user_agent
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .unwrap_or(DEFAULT_USER_AGENT);
+    let role = query_role(&scan, user_agent).await?;
+    let (user_key, created) = upsert_log_user(&scan, &role)?;
+
+    Ok(LogImportResult {
+        user_key,
+        uid: role.uid,
+        role_id: role.role_id,
+        nick_name: role.nick_name,
+        server_id: scan.server_id,
+        server_name: role.server_name,
+        provider: scan.provider,
+        channel_label: scan.channel_label,
+        created,
+        log_path: scan.path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CN_LOG: &str = include_str!("../tests/fixtures/logscan/cn_hgwebview.log");
+    const ESCAPED_LOG: &str = include_str!("../tests/fixtures/logscan/escaped_cache.log");
+
+    fn fixture(name: &str) -> Option<String> {
+        Some(format!(
+            "{}/tests/fixtures/logscan/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        ))
+    }
+
+    fn scan(name: &str, provider: Option<&str>) -> Result<LogScanResult, String> {
+        scan_log(&LogScanOptions {
+            path: fixture(name),
+            provider: provider.map(String::from),
+            user_agent: None,
+        })
+    }
+
+    #[test]
+    fn cn_log_picks_latest_complete_url() {
+        let result = scan("cn_hgwebview.log", None).unwrap();
+        assert_eq!(result.provider, "hypergryph");
+        assert_eq!(result.u8_token, "NEW+TOKEN=");
+        assert_eq!(result.pool_id, "special_1_1_1");
+        assert_eq!(result.server_id, "1");
+        assert_eq!(result.channel_label, "官服");
+        assert!(result.url.ends_with("lang=zh-cn"));
+        assert_eq!(find_gacha_url(CN_LOG, "gryphline"), None);
+    }
+
+    #[test]
+    fn global_log_falls_through_to_gryphline() {
+        let result = scan("global_hgwebview.log", None).unwrap();
+        assert_eq!(result.provider, "gryphline");
+        assert_eq!(result.u8_token, "GLOBALTOKEN");
+        assert_eq!(result.pool_id, "weponbox_1_0_1");
+        assert_eq!(result.server_id, "3");
+        assert_eq!(result.channel_label, "国际服");
+    }
+
+    #[test]
+    fn escaped_urls_are_unescaped() {
+        let mut lines = ESCAPED_LOG.lines();
+        let json_url = find_gacha_url(lines.next().unwrap(), "hypergryph").unwrap();
+        assert_eq!(
+            json_url,
+            "https://ef-webview.hypergryph.com/page/gacha_char?pool_id=special_1_0_1&u8_token=JSONTOKEN&channel=2&subChannel=2"
+        );
+        let html_url = find_gacha_url(lines.next().unwrap(), "hypergryph").unwrap();
+        assert!(html_url.ends_with("u8_token=HTMLTOKEN&channel=2&subChannel=2"));
+
+        let result = scan("escaped_cache.log", Some("hypergryph")).unwrap();
+        assert_eq!(result.u8_token, "HTMLTOKEN");
+        assert_eq!(result.channel_label, "B服");
+    }
+
+    #[test]
+    fn global_url_without_server_id_is_rejected() {
+        let err = scan("global_missing_server.log", Some("gryphline")).unwrap_err();
+        assert!(err.contains("serverId"), "{}", err);
+    }
+
+    #[test]
+    fn missing_url_lists_searched_paths() {
+        let err = scan("global_hgwebview.log", Some("hypergryph")).unwrap_err();
+        assert!(err.contains("global_hgwebview.log"), "{}", err);
+    }
+}
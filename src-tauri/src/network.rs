@@ -0,0 +1,406 @@
+//! 出站 HTTP 客户端的共享网络设置：自定义 CA、证书指纹固定、代理与超时
+//!
+//! 游戏接口请求通过 `build_client` 构造客户端，只共享 CA、代理与超时；
+//! 证书指纹只用于同步后端，由 `build_sync_client` 附加。
+
+use reqwest::{Certificate, Client, Proxy, Url};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+const PROXY_SCHEMES: [&str; 5] = ["http", "https", "socks4", "socks5", "socks5h"];
+const MAX_TIMEOUT_SECS: u64 = 600;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NetworkConfigData {
+    /// PEM 格式的 CA 证书文件路径，或直接粘贴的证书内容；与系统内置根证书同时生效
+    #[serde(default)]
+    ca_bundle: String,
+    /// 同步服务器证书的 SHA-256 指纹；设置后同步后端只接受该证书，不再校验证书链
+    #[serde(default)]
+    cert_pin: String,
+    /// http(s):// 或 socks5(h):// 代理地址，可带用户名密码
+    #[serde(default)]
+    proxy_url: String,
+    #[serde(default = "default_timeout_secs")]
+    timeout_secs: u64,
+    #[serde(default = "default_connect_timeout_secs")]
+    connect_timeout_secs: u64,
+}
+
+impl Default for NetworkConfigData {
+    fn default() -> Self {
+        Self {
+            ca_bundle: String::new(),
+            cert_pin: String::new(),
+            proxy_url: String::new(),
+            timeout_secs: default_timeout_secs(),
+            connect_timeout_secs: default_connect_timeout_secs(),
+        }
+    }
+}
+
+impl NetworkConfigData {
+    pub(crate) fn normalize(&mut self) {
+        self.ca_bundle = self.ca_bundle.trim().to_string();
+        self.cert_pin = self.cert_pin.trim().to_string();
+        self.proxy_url = self.proxy_url.trim().to_string();
+        self.timeout_secs = clamp_timeout(self.timeout_secs, default_timeout_secs());
+        self.connect_timeout_secs =
+            clamp_timeout(self.connect_timeout_secs, default_connect_timeout_secs());
+    }
+
+    /// 导出配置时去掉代理地址中的密码
+    pub(crate) fn clear_proxy_password(&mut self) {
+        if let Ok(mut url) = Url::parse(&self.proxy_url) {
+            if url.password().is_some() && url.set_password(None).is_ok() {
+                self.proxy_url = url.to_string();
+            }
+        }
+    }
+}
+
+fn default_timeout_secs() -> u64 {
+    20
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    10
+}
+
+/// 0 表示使用默认值
+fn clamp_timeout(value: u64, fallback: u64) -> u64 {
+    if value == 0 {
+        fallback
+    } else {
+        value.min(MAX_TIMEOUT_SECS)
+    }
+}
+
+fn load_ca_bundle(value: &str) -> Result<Vec<Certificate>, String> {
+    if value.is_empty() {
+        return Ok(Vec::new());
+    }
+    let pem = if value.starts_with("-----BEGIN") {
+        value.as_bytes().to_vec()
+    } else {
+        fs::read(value).map_err(|e| format!("读取 CA 证书文件失败 {}: {}", value, e))?
+    };
+    let certificates =
+        Certificate::from_pem_bundle(&pem).map_err(|e| format!("CA 证书解析失败：{}", e))?;
+    if certificates.is_empty() {
+        return Err("CA 证书文件中没有找到 PEM 证书".into());
+    }
+    Ok(certificates)
+}
+
+/// 接受 `AB:CD:...`、纯十六进制以及 `sha256:` 前缀
+fn parse_cert_pin(value: &str) -> Result<Option<[u8; 32]>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let lower = value.to_ascii_lowercase();
+    let hex = lower
+        .strip_prefix("sha256:")
+        .unwrap_or(&lower)
+        .chars()
+        .filter(|ch| *ch != ':' && !ch.is_whitespace())
+        .collect::<String>();
+    if hex.len() != 64 || !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        return Err("证书指纹格式无效，应为 64 位十六进制的 SHA-256 指纹".into());
+    }
+    let mut pin = [0u8; 32];
+    for (index, byte) in pin.iter_mut().enumerate() {
+        *byte =
+            u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).map_err(|e| e.to_string())?;
+    }
+    Ok(Some(pin))
+}
+
+fn build_proxy(value: &str) -> Result<Option<Proxy>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let url = Url::parse(value).map_err(|_| format!("代理地址无效：{}", value))?;
+    if !PROXY_SCHEMES.contains(&url.scheme()) {
+        return Err(format!(
+            "不支持的代理协议：{}（可用 {}）",
+            url.scheme(),
+            PROXY_SCHEMES.join(" / ")
+        ));
+    }
+    Proxy::all(url.as_str())
+        .map(Some)
+        .map_err(|e| format!("代理地址无效：{}", e))
+}
+
+/// 只比对叶子证书指纹，握手签名仍按 ring 支持的算法校验
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    pin: [u8; 32],
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if Sha256::digest(end_entity.as_ref()).as_slice() == self.pin {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "服务器证书与固定的指纹不一致".into(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+fn pinned_tls_config(pin: [u8; 32]) -> Result<ClientConfig, String> {
+    let provider = Arc::new(ring::default_provider());
+    let mut config = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(PinnedCertVerifier { pin, provider }))
+        .with_no_client_auth();
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+/// reqwest 的顶层错误只有“error sending request”，附上底层原因便于排查证书与代理问题
+pub(crate) fn describe_request_error(error: &reqwest::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        let text = cause.to_string();
+        if !message.contains(&text) {
+            message.push_str(": ");
+            message.push_str(&text);
+        }
+        source = cause.source();
+    }
+    message
+}
+
+/// 按共享网络设置构造客户端（不含证书指纹）
+pub(crate) fn build_client(
+    network: &NetworkConfigData,
+    user_agent: &str,
+) -> Result<Client, String> {
+    build(network, user_agent, false)
+}
+
+/// 同步后端（WebDAV / S3）使用的客户端，配置了证书指纹时只信任该证书
+pub(crate) fn build_sync_client(
+    network: &NetworkConfigData,
+    user_agent: &str,
+) -> Result<Client, String> {
+    build(network, user_agent, true)
+}
+
+fn build(network: &NetworkConfigData, user_agent: &str, pinned: bool) -> Result<Client, String> {
+    let mut network = network.clone();
+    network.normalize();
+
+    let mut builder = Client::builder()
+        .timeout(Duration::from_secs(network.timeout_secs))
+        .connect_timeout(Duration::from_secs(network.connect_timeout_secs))
+        .user_agent(user_agent);
+    if let Some(proxy) = build_proxy(&network.proxy_url)? {
+        builder = builder.proxy(proxy);
+    }
+    let pin = if pinned {
+        parse_cert_pin(&network.cert_pin)?
+    } else {
+        None
+    };
+    match pin {
+        Some(pin) => builder = builder.use_preconfigured_tls(pinned_tls_config(pin)?),
+        None => {
+            for certificate in load_ca_bundle(&network.ca_bundle)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::PrivateKeyDer;
+    use rustls::{ServerConfig, ServerConnection, StreamOwned};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/network");
+
+    fn fixture(name: &str) -> String {
+        format!("{}/{}", FIXTURES, name)
+    }
+
+    fn server_cert() -> CertificateDer<'static> {
+        CertificateDer::from_pem_file(fixture("server.pem")).expect("server cert")
+    }
+
+    fn fingerprint(cert: &CertificateDer<'_>) -> String {
+        Sha256::digest(cert.as_ref())
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    /// 使用测试 CA 签发的 127.0.0.1 证书、对每个请求返回 200 的 HTTPS 服务
+    fn start_tls_server() -> String {
+        let key = PrivateKeyDer::from_pem_file(fixture("server.key")).expect("server key");
+        let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![server_cert()], key)
+            .expect("server config");
+        let config = Arc::new(config);
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let url = format!(
+            "https://127.0.0.1:{}/",
+            listener.local_addr().unwrap().port()
+        );
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let connection = ServerConnection::new(config.clone()).unwrap();
+                let mut tls = StreamOwned::new(connection, stream);
+                let mut head = Vec::new();
+                let mut byte = [0u8; 1];
+                while !head.ends_with(b"\r\n\r\n") {
+                    match tls.read(&mut byte) {
+                        Ok(1) => head.push(byte[0]),
+                        _ => break,
+                    }
+                }
+                if head.ends_with(b"\r\n\r\n") {
+                    let _ = tls.write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                    );
+                    tls.conn.send_close_notify();
+                    let _ = tls.flush();
+                }
+            }
+        });
+        url
+    }
+
+    fn network(ca_bundle: &str, cert_pin: &str) -> NetworkConfigData {
+        NetworkConfigData {
+            ca_bundle: ca_bundle.into(),
+            cert_pin: cert_pin.into(),
+            ..Default::default()
+        }
+    }
+
+    async fn fetch(client: Client, url: &str) -> Result<String, String> {
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| describe_request_error(&e))?;
+        response.text().await.map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn parses_cert_pin_formats() {
+        let hex = "81".repeat(32);
+        let expected = Some([0x81u8; 32]);
+        assert_eq!(parse_cert_pin(&hex).unwrap(), expected);
+        assert_eq!(
+            parse_cert_pin(&format!("sha256:{}", hex)).unwrap(),
+            expected
+        );
+        assert_eq!(parse_cert_pin(&vec!["81"; 32].join(":")).unwrap(), expected);
+        assert_eq!(parse_cert_pin("").unwrap(), None);
+        assert!(parse_cert_pin("AB:CD").is_err());
+    }
+
+    #[tokio::test]
+    async fn sync_client_trusts_pinned_certificate_without_ca() {
+        let url = start_tls_server();
+        let pinned = network("", &fingerprint(&server_cert()));
+        let body = fetch(build_sync_client(&pinned, "test").unwrap(), &url).await;
+        assert_eq!(body.unwrap(), "ok");
+        // 游戏接口客户端不使用指纹，自签 CA 的证书照常被拒绝
+        assert!(fetch(build_client(&pinned, "test").unwrap(), &url)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn cert_pin_does_not_apply_to_general_client() {
+        let url = start_tls_server();
+        let other_pin = "00".repeat(32);
+        let config = network(&fixture("ca.pem"), &other_pin);
+
+        // 同步后端只认指纹，即使 CA 可信也拒绝
+        let error = fetch(build_sync_client(&config, "test").unwrap(), &url)
+            .await
+            .unwrap_err();
+        assert!(error.contains("指纹"), "{}", error);
+        // 通用客户端共享 CA 证书，忽略指纹
+        let body = fetch(build_client(&config, "test").unwrap(), &url).await;
+        assert_eq!(body.unwrap(), "ok");
+    }
+
+    #[test]
+    fn invalid_pin_only_fails_sync_client() {
+        let config = network("", "not-a-pin");
+        assert!(build_sync_client(&config, "test").is_err());
+        assert!(build_client(&config, "test").is_ok());
+    }
+}
@@ -0,0 +1,312 @@
+//! WebDAV PROPFIND 207 Multi-Status 响应解析
+//!
+//! 按 DAV: 命名空间识别元素（兼容未声明命名空间的网关），只采用 2xx propstat 中的属性。
+
+use percent_encoding::percent_decode_str;
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::Event;
+use quick_xml::name::{Namespace, ResolveResult};
+use quick_xml::NsReader;
+use reqwest::Url;
+use serde::Serialize;
+
+const DAV_NAMESPACE: &[u8] = b"DAV:";
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DavResource {
+    /// 原始 href（已还原 XML 实体）
+    pub(crate) href: String,
+    /// 百分号解码后的路径，不含协议与主机，去掉末尾的 /
+    pub(crate) path: String,
+    pub(crate) etag: Option<String>,
+    pub(crate) content_length: Option<u64>,
+    /// 服务器返回的 getlastmodified（RFC 1123）
+    pub(crate) last_modified: Option<String>,
+    pub(crate) is_collection: bool,
+}
+
+#[derive(Default)]
+struct PropValues {
+    etag: Option<String>,
+    content_length: Option<u64>,
+    last_modified: Option<String>,
+    is_collection: bool,
+}
+
+/// href 可能是绝对 URL 或路径，统一转换为解码后的路径
+pub(crate) fn href_to_path(href: &str) -> String {
+    let href = href.trim();
+    let raw_path = if href.starts_with("http://") || href.starts_with("https://") {
+        Url::parse(href)
+            .map(|url| url.path().to_string())
+            .unwrap_or_else(|_| href.to_string())
+    } else {
+        href.split(['?', '#']).next().unwrap_or("").to_string()
+    };
+    let decoded = percent_decode_str(&raw_path).decode_utf8_lossy();
+    let trimmed = decoded.trim_end_matches('/');
+    if trimmed.is_empty() {
+        "/".into()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// 2xx 或缺失状态行时视为成功
+fn status_is_success(status: &str) -> bool {
+    let code = status
+        .split_whitespace()
+        .nth(1)
+        .and_then(|item| item.parse::<u16>().ok());
+    code.map_or(true, |code| (200..300).contains(&code))
+}
+
+fn is_dav_element(resolve: &ResolveResult) -> bool {
+    match resolve {
+        ResolveResult::Bound(Namespace(ns)) => *ns == DAV_NAMESPACE,
+        // 部分网盘网关省略命名空间声明
+        ResolveResult::Unbound => true,
+        ResolveResult::Unknown(_) => true,
+    }
+}
+
+pub(crate) fn parse_multistatus(xml: &str) -> Result<Vec<DavResource>, String> {
+    let mut reader = NsReader::from_str(xml.trim_start_matches('\u{feff}'));
+    reader.config_mut().trim_text(false);
+
+    let mut resources = Vec::new();
+    // 当前所在的 DAV 元素路径（本地名）
+    let mut stack: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut current: Option<DavResource> = None;
+    let mut props = PropValues::default();
+    let mut propstat_status = String::new();
+    let mut response_ok = true;
+
+    loop {
+        let (resolve, event) = reader
+            .read_resolved_event()
+            .map_err(|e| format!("PROPFIND 响应解析失败: {}", e))?;
+        match event {
+            Event::Start(element) => {
+                let name = String::from_utf8_lossy(element.local_name().as_ref()).to_lowercase();
+                let name = if is_dav_element(&resolve) {
+                    name
+                } else {
+                    format!("?{}", name)
+                };
+                match name.as_str() {
+                    "response" => {
+                        current = Some(DavResource::default());
+                        response_ok = true;
+                    }
+                    "propstat" => {
+                        props = PropValues::default();
+                        propstat_status.clear();
+                    }
+                    _ => {}
+                }
+                stack.push(name);
+                text.clear();
+            }
+            Event::Empty(element) => {
+                let name = String::from_utf8_lossy(element.local_name().as_ref()).to_lowercase();
+                if is_dav_element(&resolve)
+                    && name == "collection"
+                    && stack.last().map(String::as_str) == Some("resourcetype")
+                {
+                    props.is_collection = true;
+                }
+            }
+            Event::Text(content) => {
+                text.push_str(&content.decode().map_err(|e| e.to_string())?);
+            }
+            Event::CData(content) => {
+                text.push_str(&content.decode().map_err(|e| e.to_string())?);
+            }
+            Event::GeneralRef(reference) => {
+                if let Some(ch) = reference.resolve_char_ref().map_err(|e| e.to_string())? {
+                    text.push(ch);
+                } else {
+                    let entity = reference.decode().map_err(|e| e.to_string())?;
+                    match resolve_predefined_entity(&entity) {
+                        Some(value) => text.push_str(value),
+                        None => {
+                            text.push('&');
+                            text.push_str(&entity);
+                            text.push(';');
+                        }
+                    }
+                }
+            }
+            Event::End(_) => {
+                let name = stack.pop().unwrap_or_default();
+                let parent = stack.last().map(String::as_str).unwrap_or("");
+                let value = text.trim().to_string();
+                match (name.as_str(), parent) {
+                    ("href", "response") => {
+                        if let Some(resource) = current.as_mut() {
+                            resource.path = href_to_path(&value);
+                            resource.href = value;
+                        }
+                    }
+                    ("status", "propstat") => propstat_status = value,
+                    // 没有 propstat、只有状态行的 response（如 404 成员）
+                    ("status", "response") => response_ok = status_is_success(&value),
+                    ("getetag", "prop") if !value.is_empty() => props.etag = Some(value),
+                    ("getcontentlength", "prop") => props.content_length = value.parse().ok(),
+                    ("getlastmodified", "prop") if !value.is_empty() => {
+                        props.last_modified = Some(value)
+                    }
+                    ("collection", "resourcetype") => props.is_collection = true,
+                    ("propstat", _) => {
+                        if let (Some(resource), true) =
+                            (current.as_mut(), status_is_success(&propstat_status))
+                        {
+                            let props = std::mem::take(&mut props);
+                            resource.etag = props.etag.or(resource.etag.take());
+                            resource.content_length =
+                                props.content_length.or(resource.content_length);
+                            resource.last_modified =
+                                props.last_modified.or(resource.last_modified.take());
+                            resource.is_collection |= props.is_collection;
+                        }
+                    }
+                    ("response", _) => {
+                        if let Some(resource) = current.take() {
+                            if response_ok && !resource.href.is_empty() {
+                                resources.push(resource);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                text.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(resources)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEXTCLOUD: &str = include_str!("../tests/fixtures/propfind/nextcloud.xml");
+    const APACHE: &str = include_str!("../tests/fixtures/propfind/apache.xml");
+    const NGINX: &str = include_str!("../tests/fixtures/propfind/nginx.xml");
+    const NETDISK: &str = include_str!("../tests/fixtures/propfind/netdisk.xml");
+
+    fn find<'a>(resources: &'a [DavResource], path: &str) -> &'a DavResource {
+        resources
+            .iter()
+            .find(|item| item.path == path)
+            .unwrap_or_else(|| panic!("missing {}", path))
+    }
+
+    #[test]
+    fn parses_nextcloud_multistatus() {
+        let resources = parse_multistatus(NEXTCLOUD).unwrap();
+        assert_eq!(resources.len(), 3);
+
+        let dir = find(
+            &resources,
+            "/remote.php/dav/files/alice/endfield-gacha/accounts",
+        );
+        assert!(dir.is_collection);
+        // 404 propstat 中的属性不采用
+        assert_eq!(dir.content_length, None);
+
+        let file = find(
+            &resources,
+            "/remote.php/dav/files/alice/endfield-gacha/accounts/1001_r1.json",
+        );
+        assert!(!file.is_collection);
+        assert_eq!(
+            file.etag.as_deref(),
+            Some("\"b3f0c1e4d2a7f9e8c6b5a4d3e2f1a0b9\"")
+        );
+        assert_eq!(file.content_length, Some(20481));
+        assert_eq!(
+            file.last_modified.as_deref(),
+            Some("Sun, 18 Oct 2026 09:12:44 GMT")
+        );
+
+        let encoded = find(
+            &resources,
+            "/remote.php/dav/files/alice/endfield-gacha/accounts/测试 账号.json",
+        );
+        assert!(encoded.href.contains("%e6%b5%8b"));
+    }
+
+    #[test]
+    fn parses_apache_mod_dav_multistatus() {
+        let resources = parse_multistatus(APACHE).unwrap();
+        assert_eq!(resources.len(), 3);
+
+        // lp1 前缀同样绑定到 DAV: 命名空间
+        let dir = find(&resources, "/dav/endfield-gacha/accounts");
+        assert!(dir.is_collection);
+        assert_eq!(dir.etag.as_deref(), Some("\"1000-5f1a2b3c4d5e6\""));
+
+        let file = find(&resources, "/dav/endfield-gacha/accounts/1002_r7.json");
+        assert!(!file.is_collection);
+        assert_eq!(file.etag.as_deref(), Some("\"2de-5f19aa0b7c1d2\""));
+        assert_eq!(file.content_length, Some(734));
+        assert_eq!(
+            file.last_modified.as_deref(),
+            Some("Thu, 15 Oct 2026 02:40:10 GMT")
+        );
+    }
+
+    #[test]
+    fn parses_nginx_dav_ext_multistatus_without_etags() {
+        let resources = parse_multistatus(NGINX).unwrap();
+        assert_eq!(resources.len(), 3);
+        assert!(find(&resources, "/webdav/endfield-gacha/accounts").is_collection);
+
+        let file = find(&resources, "/webdav/endfield-gacha/accounts/1001_r1.json");
+        assert!(!file.is_collection);
+        assert_eq!(file.etag, None);
+        assert_eq!(file.content_length, Some(20481));
+
+        let escaped = find(
+            &resources,
+            "/webdav/endfield-gacha/accounts/a&b [copy].json",
+        );
+        assert_eq!(escaped.content_length, Some(2));
+    }
+
+    #[test]
+    fn parses_netdisk_multistatus_with_undeclared_prefix_and_raw_utf8_hrefs() {
+        let resources = parse_multistatus(NETDISK).unwrap();
+        // 只有状态行的 404 成员被丢弃
+        assert_eq!(resources.len(), 3);
+        assert!(resources
+            .iter()
+            .all(|item| !item.path.ends_with("已删除.json")));
+
+        assert!(find(&resources, "/dav/抽卡记录/accounts").is_collection);
+        let file = find(&resources, "/dav/抽卡记录/accounts/1001_r1.json");
+        assert!(!file.is_collection);
+        assert_eq!(file.etag.as_deref(), Some("W/\"a1b2c3d4e5\""));
+        assert_eq!(file.content_length, Some(20481));
+
+        // 百分号编码与查询串
+        let backup = find(&resources, "/dav/抽卡记录/accounts/备份.json");
+        assert_eq!(backup.content_length, Some(88));
+        assert_eq!(backup.etag, None);
+    }
+
+    #[test]
+    fn missing_or_non_2xx_status_lines() {
+        assert!(status_is_success(""));
+        assert!(status_is_success("HTTP/1.1 207 Multi-Status"));
+        assert!(!status_is_success("HTTP/1.1 404 Not Found"));
+        assert!(!status_is_success("HTTP/1.1 403 Forbidden"));
+    }
+}
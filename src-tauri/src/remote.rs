@@ -0,0 +1,148 @@
+//! 同步后端抽象：WebDAV、本地/同步盘目录与 S3 兼容存储共用同一套决策与合并逻辑
+//!
+//! 路径均相对于配置的 basePath，使用 `/` 分隔，例如 `accounts/<key>.json`。
+
+use crate::s3::S3Store;
+use crate::syncfolder::FolderStore;
+use crate::webdav::WebDavClient;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 远端文件内容及其 ETag（后端未提供时为 None）
+pub(crate) struct RemoteFile {
+    pub(crate) path: String,
+    pub(crate) text: String,
+    pub(crate) etag: Option<String>,
+}
+
+/// 列出的文件及其最后修改时间（后端未提供时为 None）
+pub(crate) struct RemoteEntry {
+    pub(crate) path: String,
+    pub(crate) modified: Option<DateTime<Utc>>,
+}
+
+/// 写入的乐观并发条件
+#[derive(Debug, Clone)]
+pub(crate) enum PutPrecondition {
+    None,
+    /// 远端 ETag 未变化时才写入
+    IfMatch(String),
+    /// 远端文件不存在时才写入
+    IfNoneMatch,
+}
+
+impl PutPrecondition {
+    pub(crate) fn for_remote(file: Option<&RemoteFile>) -> Self {
+        match file {
+            None => PutPrecondition::IfNoneMatch,
+            Some(file) => Self::if_match(file.etag.clone()),
+        }
+    }
+
+    /// If-Match 只做强比较，弱 ETag（W/"…"）永远不会匹配，此时退化为无条件写入
+    pub(crate) fn if_match(etag: Option<String>) -> Self {
+        match etag {
+            Some(etag) if !etag.trim_start().starts_with("W/") => PutPrecondition::IfMatch(etag),
+            _ => PutPrecondition::None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum PutOutcome {
+    Written,
+    PreconditionFailed,
+}
+
+pub(crate) trait RemoteStore {
+    /// 确保目录存在；空字符串表示 basePath 本身。没有目录概念的后端可直接返回
+    async fn ensure_dir(&self, dir: &str) -> Result<(), String>;
+
+    /// 文件不存在时返回 None
+    async fn get(&self, path: &str) -> Result<Option<RemoteFile>, String>;
+
+    async fn put(
+        &self,
+        path: &str,
+        body: String,
+        precondition: &PutPrecondition,
+    ) -> Result<PutOutcome, String>;
+
+    /// 列出目录下的文件（不含子目录），按相对路径排序；目录不存在时为空
+    async fn list(&self, dir: &str) -> Result<Vec<RemoteEntry>, String>;
+
+    /// 文件不存在视为成功
+    async fn delete(&self, path: &str) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum RemoteBackendKind {
+    #[default]
+    Webdav,
+    /// 本地目录，可配合 Syncthing、Dropbox 或网络共享使用
+    Folder,
+    S3,
+}
+
+pub(crate) enum RemoteBackend {
+    WebDav(WebDavClient),
+    Folder(FolderStore),
+    S3(S3Store),
+}
+
+impl RemoteBackend {
+    pub(crate) fn as_webdav(&self) -> Option<&WebDavClient> {
+        match self {
+            RemoteBackend::WebDav(client) => Some(client),
+            _ => None,
+        }
+    }
+}
+
+impl RemoteStore for RemoteBackend {
+    async fn ensure_dir(&self, dir: &str) -> Result<(), String> {
+        match self {
+            RemoteBackend::WebDav(store) => store.ensure_dir(dir).await,
+            RemoteBackend::Folder(store) => store.ensure_dir(dir).await,
+            RemoteBackend::S3(store) => store.ensure_dir(dir).await,
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<Option<RemoteFile>, String> {
+        match self {
+            RemoteBackend::WebDav(store) => store.get(path).await,
+            RemoteBackend::Folder(store) => store.get(path).await,
+            RemoteBackend::S3(store) => store.get(path).await,
+        }
+    }
+
+    async fn put(
+        &self,
+        path: &str,
+        body: String,
+        precondition: &PutPrecondition,
+    ) -> Result<PutOutcome, String> {
+        match self {
+            RemoteBackend::WebDav(store) => store.put(path, body, precondition).await,
+            RemoteBackend::Folder(store) => store.put(path, body, precondition).await,
+            RemoteBackend::S3(store) => store.put(path, body, precondition).await,
+        }
+    }
+
+    async fn list(&self, dir: &str) -> Result<Vec<RemoteEntry>, String> {
+        match self {
+            RemoteBackend::WebDav(store) => store.list(dir).await,
+            RemoteBackend::Folder(store) => store.list(dir).await,
+            RemoteBackend::S3(store) => store.list(dir).await,
+        }
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), String> {
+        match self {
+            RemoteBackend::WebDav(store) => store.delete(path).await,
+            RemoteBackend::Folder(store) => store.delete(path).await,
+            RemoteBackend::S3(store) => store.delete(path).await,
+        }
+    }
+}
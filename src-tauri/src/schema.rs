@@ -0,0 +1,365 @@
+//! 持久化格式的 JSON Schema：由 Rust 类型生成，供第三方工具读取与校验
+//!
+//! 覆盖 gachaData/<uid>.json、WebDAV manifest.json 与账号文件（AccountBundle）。
+
+use crate::webdav::{AccountBundle, ManifestFile};
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::command;
+
+const MAX_REPORTED_ISSUES: usize = 100;
+
+/// 读取端同时接受字符串与数字（旧版本与第三方工具写入的 seqId、gachaTs、rarity）
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub(crate) enum StringOrNumber {
+    Text(String),
+    Integer(u64),
+}
+
+/// 读取端按布尔值、0/1 或 "true"/"1" 解析
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub(crate) enum FlagValue {
+    Bool(bool),
+    Integer(i64),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CharacterRecord {
+    #[serde(default)]
+    char_id: String,
+    #[serde(default)]
+    char_name: String,
+    /// 毫秒时间戳，兼容 10 位秒级时间戳；缺失时按 0 处理
+    #[serde(default)]
+    gacha_ts: Option<StringOrNumber>,
+    #[serde(default)]
+    is_free: Option<FlagValue>,
+    #[serde(default)]
+    is_new: Option<FlagValue>,
+    #[serde(default)]
+    pool_id: String,
+    #[serde(default)]
+    pool_name: String,
+    #[serde(default)]
+    rarity: Option<StringOrNumber>,
+    /// 缺失时合并端原样保留该记录，不参与 seqId 去重
+    #[serde(default)]
+    seq_id: Option<StringOrNumber>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WeaponRecord {
+    /// 缺失时读取端以所在分组（卡池 ID）代替
+    #[serde(default)]
+    pool_id: String,
+    #[serde(default)]
+    pool_name: String,
+    #[serde(default)]
+    weapon_id: String,
+    #[serde(default)]
+    weapon_name: String,
+    #[serde(default)]
+    weapon_type: String,
+    #[serde(default)]
+    rarity: Option<StringOrNumber>,
+    #[serde(default)]
+    is_new: Option<FlagValue>,
+    /// 毫秒时间戳，兼容 10 位秒级时间戳；缺失时按 0 处理
+    #[serde(default)]
+    gacha_ts: Option<StringOrNumber>,
+    /// 缺失时合并端原样保留该记录，不参与 seqId 去重
+    #[serde(default)]
+    seq_id: Option<StringOrNumber>,
+}
+
+/// 角色记录按卡池类型（E_CharacterGachaPoolType_*）分组，组内按 seqId 倒序
+pub(crate) type CharacterPools = BTreeMap<String, Vec<CharacterRecord>>;
+/// 武器记录按卡池 ID 分组，组内按 seqId 倒序
+pub(crate) type WeaponPools = BTreeMap<String, Vec<WeaponRecord>>;
+
+/// gachaData/<uid>.json
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub(crate) struct RecordFile {
+    #[serde(default)]
+    character_max_seqid: String,
+    #[serde(default)]
+    weapon_max_seqid: String,
+    #[serde(default)]
+    character: CharacterPools,
+    #[serde(default)]
+    weapon: WeaponPools,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SchemaKind {
+    Record,
+    Manifest,
+    AccountBundle,
+}
+
+impl SchemaKind {
+    const ALL: [SchemaKind; 3] = [
+        SchemaKind::Record,
+        SchemaKind::Manifest,
+        SchemaKind::AccountBundle,
+    ];
+
+    fn file_name(self) -> &'static str {
+        match self {
+            SchemaKind::Record => "gacha-record.schema.json",
+            SchemaKind::Manifest => "webdav-manifest.schema.json",
+            SchemaKind::AccountBundle => "account-bundle.schema.json",
+        }
+    }
+
+    /// 按顶层字段猜测文件类型
+    fn detect(value: &Value) -> Option<Self> {
+        let obj = value.as_object()?;
+        if obj.contains_key("account") {
+            Some(SchemaKind::AccountBundle)
+        } else if obj.contains_key("accounts") {
+            Some(SchemaKind::Manifest)
+        } else if obj.contains_key("character")
+            || obj.contains_key("weapon")
+            || obj.contains_key("character_max_seqid")
+        {
+            Some(SchemaKind::Record)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaIssue {
+    /// JSON Pointer，如 /character/E_CharacterGachaPoolType_Special/0/rarity
+    path: String,
+    message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaValidationResult {
+    path: String,
+    kind: SchemaKind,
+    valid: bool,
+    issues: Vec<SchemaIssue>,
+}
+
+pub(crate) fn schema_value(kind: SchemaKind) -> Value {
+    let schema = match kind {
+        SchemaKind::Record => schema_for!(RecordFile),
+        SchemaKind::Manifest => schema_for!(ManifestFile),
+        SchemaKind::AccountBundle => schema_for!(AccountBundle),
+    };
+    serde_json::to_value(schema).unwrap_or(Value::Null)
+}
+
+pub(crate) fn validate_value(kind: SchemaKind, value: &Value) -> Result<Vec<SchemaIssue>, String> {
+    let validator = jsonschema::validator_for(&schema_value(kind)).map_err(|e| e.to_string())?;
+    Ok(validator
+        .iter_errors(value)
+        .take(MAX_REPORTED_ISSUES)
+        .map(|error| SchemaIssue {
+            path: error.instance_path.to_string(),
+            message: error.to_string(),
+        })
+        .collect())
+}
+
+/// 校验失败时返回首个问题，供写入前检查使用
+pub(crate) fn ensure_valid(kind: SchemaKind, value: &Value) -> Result<(), String> {
+    let issues = validate_value(kind, value)?;
+    match issues.first() {
+        None => Ok(()),
+        Some(issue) => Err(format!(
+            "{} ({} issue(s), first at {})",
+            issue.message,
+            issues.len(),
+            if issue.path.is_empty() {
+                "/"
+            } else {
+                &issue.path
+            }
+        )),
+    }
+}
+
+#[command]
+pub fn get_json_schema(kind: SchemaKind) -> Value {
+    schema_value(kind)
+}
+
+#[command]
+pub fn export_json_schemas(dir: String) -> Result<Vec<String>, String> {
+    let dir = dir.trim();
+    if dir.is_empty() {
+        return Err("Export path cannot be empty".into());
+    }
+    let dir = PathBuf::from(dir);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let mut files = Vec::new();
+    for kind in SchemaKind::ALL {
+        let path = dir.join(kind.file_name());
+        let json = serde_json::to_string_pretty(&schema_value(kind)).map_err(|e| e.to_string())?;
+        fs::write(&path, json).map_err(|e| e.to_string())?;
+        files.push(path.to_string_lossy().to_string());
+    }
+    Ok(files)
+}
+
+#[command]
+pub fn validate_file(
+    path: String,
+    kind: Option<SchemaKind>,
+) -> Result<SchemaValidationResult, String> {
+    let path = path.trim().to_string();
+    if path.is_empty() {
+        return Err("File path cannot be empty".into());
+    }
+    let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let value: Value = serde_json::from_str(text.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("Invalid JSON: {}", e))?;
+    let kind = kind
+        .or_else(|| SchemaKind::detect(&value))
+        .ok_or("Unable to detect file type, please specify kind")?;
+    let issues = validate_value(kind, &value)?;
+
+    Ok(SchemaValidationResult {
+        path,
+        kind,
+        valid: issues.is_empty(),
+        issues,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record_file(character: Value, weapon: Value) -> Value {
+        json!({ "character": { "E_CharacterGachaPoolType_Special": [character] }, "weapon": { "weaponbox_1_0_1": [weapon] } })
+    }
+
+    fn issues(kind: SchemaKind, value: &Value) -> Vec<String> {
+        validate_value(kind, value)
+            .unwrap()
+            .into_iter()
+            .map(|issue| issue.path)
+            .collect()
+    }
+
+    #[test]
+    fn accepts_what_readers_accept() {
+        let character = json!({
+            "charName": "莱万汀",
+            "gachaTs": 1768003000,
+            "isFree": 1,
+            "isNew": "true",
+            "rarity": "6",
+            "seqId": 104
+        });
+        let weapon = json!({
+            "weaponName": "熔铸火焰",
+            "gachaTs": "1768005000000",
+            "rarity": 6,
+            "seqId": "202"
+        });
+        let value = record_file(character.clone(), weapon.clone());
+        assert_eq!(issues(SchemaKind::Record, &value), Vec::<String>::new());
+
+        let bundle = json!({
+            "schema_version": 1,
+            "account": { "key": "1001_r1", "uid": "1001", "provider": "hypergryph" },
+            "updatedAt": "2026-01-22T12:00:00.000Z",
+            "character": value["character"],
+            "weapon": value["weapon"],
+        });
+        assert!(ensure_valid(SchemaKind::AccountBundle, &bundle).is_ok());
+    }
+
+    #[test]
+    fn rejects_records_readers_cannot_use() {
+        let character =
+            json!({ "gachaTs": "1768003000000", "rarity": { "value": 6 }, "seqId": "1" });
+        let weapon = json!({ "gachaTs": "1768005000000", "rarity": 6, "seqId": ["1"] });
+        let value = record_file(character, weapon);
+        let paths = issues(SchemaKind::Record, &value);
+        assert!(paths.contains(&"/character/E_CharacterGachaPoolType_Special/0/rarity".to_string()));
+        assert!(paths.contains(&"/weapon/weaponbox_1_0_1/0/seqId".to_string()));
+    }
+
+    /// 合并端把缺少 seqId 的记录保留下来，上传前的 Schema 校验不能因此拒绝整个账号
+    #[test]
+    fn accepts_records_without_seq_id_time_or_rarity() {
+        let character = json!({ "charName": "莱万汀", "poolId": "special_1_0_1" });
+        let weapon = json!({ "weaponName": "熔铸火焰", "seqId": null });
+        let value = record_file(character, weapon);
+        assert_eq!(issues(SchemaKind::Record, &value), Vec::<String>::new());
+
+        let bundle = json!({
+            "schema_version": 1,
+            "account": { "key": "1001_r1", "uid": "1001", "provider": "hypergryph" },
+            "updatedAt": "2026-01-22T12:00:00.000Z",
+            "character": value["character"],
+            "weapon": value["weapon"],
+        });
+        assert!(ensure_valid(SchemaKind::AccountBundle, &bundle).is_ok());
+    }
+
+    #[test]
+    fn committed_schemas_match_generated() {
+        for (kind, text) in [
+            (
+                SchemaKind::Record,
+                include_str!("../schemas/gacha-record.schema.json"),
+            ),
+            (
+                SchemaKind::Manifest,
+                include_str!("../schemas/webdav-manifest.schema.json"),
+            ),
+            (
+                SchemaKind::AccountBundle,
+                include_str!("../schemas/account-bundle.schema.json"),
+            ),
+        ] {
+            let committed: Value = serde_json::from_str(text).unwrap();
+            assert_eq!(
+                committed,
+                schema_value(kind),
+                "schemas/{} 已过期，请用 export_json_schemas 重新生成",
+                kind.file_name()
+            );
+        }
+    }
+
+    #[test]
+    fn detects_kind_from_top_level_fields() {
+        assert_eq!(
+            SchemaKind::detect(&json!({ "account": {} })),
+            Some(SchemaKind::AccountBundle)
+        );
+        assert_eq!(
+            SchemaKind::detect(&json!({ "accounts": {} })),
+            Some(SchemaKind::Manifest)
+        );
+        assert_eq!(
+            SchemaKind::detect(&json!({ "weapon": {} })),
+            Some(SchemaKind::Record)
+        );
+        assert_eq!(SchemaKind::detect(&json!([])), None);
+    }
+}
@@ -0,0 +1,216 @@
+//! 匿名分享导出：uid / roleId / 昵称替换为加盐的稳定假名，时间戳降低精度，
+//! 保留 seqId 顺序与卡池 ID 以便社区统计。
+
+use crate::get_userdata_dir;
+use crate::stats::parse_gacha_ts;
+use crate::webdav::{
+    build_local_bundle, get_user_key, load_config_data, AccountBundle, BundleAccount, UserRoleMeta,
+};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use tauri::command;
+
+const SHARE_SALT_FILE: &str = "shareSalt";
+const SHARE_SALT_LEN: usize = 32;
+const PSEUDONYM_LEN: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimestampPrecision {
+    Second,
+    Minute,
+    #[default]
+    Hour,
+    Day,
+}
+
+impl TimestampPrecision {
+    fn step_ms(self) -> i64 {
+        match self {
+            TimestampPrecision::Second => 1_000,
+            TimestampPrecision::Minute => 60_000,
+            TimestampPrecision::Hour => 3_600_000,
+            TimestampPrecision::Day => 86_400_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareExportOptions {
+    /// 自定义盐值；为空时使用本机持久化的盐，保证多次导出的假名一致
+    #[serde(default)]
+    salt: Option<String>,
+    #[serde(default)]
+    precision: TimestampPrecision,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareExportResult {
+    path: String,
+    key: String,
+    character_count: usize,
+    weapon_count: usize,
+}
+
+fn sha256_hex(value: &str) -> String {
+    Sha256::digest(value.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// 本机盐值：首次使用时由系统随机数生成，之后持久化复用
+pub(crate) fn load_or_create_salt() -> Result<String, String> {
+    let path = get_userdata_dir()?.join(SHARE_SALT_FILE);
+    if path.exists() {
+        let salt = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        if !salt.trim().is_empty() {
+            return Ok(salt.trim().to_string());
+        }
+    }
+    let mut bytes = [0u8; SHARE_SALT_LEN];
+    OsRng.fill_bytes(&mut bytes);
+    let salt = bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    fs::write(&path, &salt).map_err(|e| e.to_string())?;
+    Ok(salt)
+}
+
+pub(crate) fn pseudonym(salt: &str, field: &str, value: &str) -> String {
+    if value.trim().is_empty() {
+        return String::new();
+    }
+    let hash = sha256_hex(&format!("{}:{}:{}", salt, field, value.trim()));
+    format!("anon-{}", &hash[..PSEUDONYM_LEN])
+}
+
+fn anonymize_account(account: &BundleAccount, salt: &str) -> BundleAccount {
+    let uid = pseudonym(salt, "uid", &account.uid);
+    let role_id = pseudonym(salt, "roleId", &account.role_id.role_id);
+    BundleAccount {
+        key: format!("{}_{}", uid, role_id),
+        provider: account.provider.clone(),
+        uid,
+        role_id: UserRoleMeta {
+            server_id: account.role_id.server_id.clone(),
+            server_name: account.role_id.server_name.clone(),
+            nick_name: pseudonym(salt, "nickName", &account.role_id.nick_name),
+            role_id,
+        },
+    }
+}
+
+/// 按精度向下取整 gachaTs，返回记录条数
+fn coarsen_records(records: &mut Value, precision: TimestampPrecision) -> usize {
+    let step = precision.step_ms();
+    let mut count = 0;
+    let Some(obj) = records.as_object_mut() else {
+        return count;
+    };
+    for list in obj.values_mut() {
+        let Some(items) = list.as_array_mut() else {
+            continue;
+        };
+        for item in items.iter_mut() {
+            count += 1;
+            let Some(fields) = item.as_object_mut() else {
+                continue;
+            };
+            let ts = fields.get("gachaTs").and_then(|value| match value {
+                Value::String(text) => parse_gacha_ts(text),
+                Value::Number(number) => number.as_i64(),
+                _ => None,
+            });
+            if let Some(ts) = ts {
+                fields.insert(
+                    "gachaTs".into(),
+                    Value::String((ts - ts.rem_euclid(step)).to_string()),
+                );
+            }
+        }
+    }
+    count
+}
+
+#[command]
+pub fn export_share(
+    user_key: String,
+    path: String,
+    options: Option<ShareExportOptions>,
+) -> Result<ShareExportResult, String> {
+    let path = path.trim().to_string();
+    if path.is_empty() {
+        return Err("Export path cannot be empty".into());
+    }
+    let options = options.unwrap_or_default();
+    let salt = match options.salt.as_deref().map(str::trim) {
+        Some(salt) if !salt.is_empty() => salt.to_string(),
+        _ => load_or_create_salt()?,
+    };
+
+    let config = load_config_data()?;
+    let user = config
+        .users
+        .iter()
+        .find(|item| get_user_key(item) == user_key.trim())
+        .ok_or_else(|| format!("Account not found: {}", user_key.trim()))?;
+
+    // 记录文件本身不含 Token，账号信息只保留假名与区服
+    let local = build_local_bundle(user)?;
+    let mut character = local.character.clone();
+    let mut weapon = local.weapon.clone();
+    let character_count = coarsen_records(&mut character, options.precision);
+    let weapon_count = coarsen_records(&mut weapon, options.precision);
+    let shared = AccountBundle {
+        account: anonymize_account(&local.account, &salt),
+        character,
+        weapon,
+        ..local
+    };
+
+    let json = serde_json::to_string_pretty(&shared).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| e.to_string())?;
+
+    Ok(ShareExportResult {
+        path,
+        key: shared.account.key,
+        character_count,
+        weapon_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil;
+
+    #[test]
+    fn salt_is_random_and_persisted() {
+        let _guard = testutil::userdata();
+        let salt = load_or_create_salt().unwrap();
+        assert_eq!(salt.len(), SHARE_SALT_LEN * 2);
+        assert!(salt.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(load_or_create_salt().unwrap(), salt);
+
+        fs::remove_file(get_userdata_dir().unwrap().join(SHARE_SALT_FILE)).unwrap();
+        assert_ne!(load_or_create_salt().unwrap(), salt);
+    }
+
+    #[test]
+    fn pseudonyms_depend_on_salt_and_field() {
+        let a = pseudonym("salt-a", "uid", "1001");
+        assert_eq!(a, pseudonym("salt-a", "uid", " 1001 "));
+        assert_eq!(a.len(), "anon-".len() + PSEUDONYM_LEN);
+        assert_ne!(a, pseudonym("salt-b", "uid", "1001"));
+        assert_ne!(a, pseudonym("salt-a", "roleId", "1001"));
+        assert_eq!(pseudonym("salt-a", "uid", "  "), "");
+    }
+}
@@ -0,0 +1,407 @@
+use crate::stats::{load_account_pulls, GachaKind, PoolCatalog, PullRecord};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tauri::command;
+
+const DEFAULT_CHARACTER_PULL_PRICE: u64 = 500;
+const DEFAULT_WEAPON_PULL_PRICE: u64 = 198;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SpendCurrency {
+    Oroberyl,
+    ArsenalTicket,
+}
+
+impl SpendCurrency {
+    fn for_kind(kind: GachaKind) -> Self {
+        match kind {
+            GachaKind::Character => SpendCurrency::Oroberyl,
+            GachaKind::Weapon => SpendCurrency::ArsenalTicket,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopUpTier {
+    #[serde(default)]
+    price: f64,
+    #[serde(default)]
+    amount: u64,
+    currency: SpendCurrency,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpendOptions {
+    #[serde(default = "default_character_pull_price")]
+    character_pull_price: u64,
+    #[serde(default = "default_weapon_pull_price")]
+    weapon_pull_price: u64,
+    /// 按卡池类型（角色池）或卡池 ID 覆盖单抽价格
+    #[serde(default)]
+    pool_pull_prices: HashMap<String, u64>,
+    #[serde(default)]
+    top_up_tiers: Vec<TopUpTier>,
+}
+
+impl Default for SpendOptions {
+    fn default() -> Self {
+        Self {
+            character_pull_price: default_character_pull_price(),
+            weapon_pull_price: default_weapon_pull_price(),
+            pool_pull_prices: HashMap::new(),
+            top_up_tiers: Vec::new(),
+        }
+    }
+}
+
+fn default_character_pull_price() -> u64 {
+    DEFAULT_CHARACTER_PULL_PRICE
+}
+
+fn default_weapon_pull_price() -> u64 {
+    DEFAULT_WEAPON_PULL_PRICE
+}
+
+impl SpendOptions {
+    fn pull_price(&self, pull: &PullRecord) -> u64 {
+        if let Some(price) = self
+            .pool_pull_prices
+            .get(&pull.pool_id)
+            .or_else(|| self.pool_pull_prices.get(&pull.pool_key))
+        {
+            return *price;
+        }
+        match pull.kind {
+            GachaKind::Character => self.character_pull_price,
+            GachaKind::Weapon => self.weapon_pull_price,
+        }
+    }
+
+    /// 取同一货币下性价比最高的充值档位，返回每单位货币对应的金额
+    fn best_unit_price(&self, currency: SpendCurrency) -> Option<f64> {
+        self.top_up_tiers
+            .iter()
+            .filter(|tier| tier.currency == currency && tier.amount > 0 && tier.price > 0.0)
+            .map(|tier| tier.price / tier.amount as f64)
+            .min_by(|a, b| a.total_cmp(b))
+    }
+
+    fn to_money(&self, currency: SpendCurrency, amount: u64) -> Option<f64> {
+        self.best_unit_price(currency)
+            .map(|unit_price| unit_price * amount as f64)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpendSummary {
+    gacha_type: GachaKind,
+    currency: SpendCurrency,
+    total_pulls: u32,
+    paid_pulls: u32,
+    free_pulls: u32,
+    amount: u64,
+    money: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BannerSpend {
+    gacha_type: GachaKind,
+    pool_key: String,
+    pool_id: String,
+    pool_name: String,
+    currency: SpendCurrency,
+    total_pulls: u32,
+    paid_pulls: u32,
+    free_pulls: u32,
+    amount: u64,
+    money: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpendReport {
+    user_key: String,
+    summaries: Vec<SpendSummary>,
+    banners: Vec<BannerSpend>,
+    total_money: Option<f64>,
+}
+
+fn sum_money(values: impl Iterator<Item = Option<f64>>) -> Option<f64> {
+    let mut total = None;
+    for value in values.flatten() {
+        total = Some(total.unwrap_or(0.0) + value);
+    }
+    total
+}
+
+#[command]
+pub fn estimate_spend(
+    user_key: String,
+    options: Option<SpendOptions>,
+) -> Result<SpendReport, String> {
+    let options = options.unwrap_or_default();
+    let account = load_account_pulls(&user_key)?;
+    let catalog = PoolCatalog::load()?;
+
+    let mut summaries = Vec::new();
+    let mut banners: BTreeMap<(GachaKind, String, String), BannerSpend> = BTreeMap::new();
+
+    for kind in [GachaKind::Character, GachaKind::Weapon] {
+        let currency = SpendCurrency::for_kind(kind);
+        let mut summary = SpendSummary {
+            gacha_type: kind,
+            currency,
+            total_pulls: 0,
+            paid_pulls: 0,
+            free_pulls: 0,
+            amount: 0,
+            money: None,
+        };
+
+        for pull in account.pools(kind).values().flatten() {
+            let banner = banners
+                .entry((kind, pull.pool_key.clone(), pull.pool_id.clone()))
+                .or_insert_with(|| BannerSpend {
+                    gacha_type: kind,
+                    pool_key: pull.pool_key.clone(),
+                    pool_id: pull.pool_id.clone(),
+                    pool_name: catalog
+                        .get(&pull.pool_id)
+                        .map(|info| info.pool_name.clone())
+                        .filter(|name| !name.is_empty())
+                        .unwrap_or_else(|| pull.pool_name.clone()),
+                    currency,
+                    total_pulls: 0,
+                    paid_pulls: 0,
+                    free_pulls: 0,
+                    amount: 0,
+                    money: None,
+                });
+
+            summary.total_pulls += 1;
+            banner.total_pulls += 1;
+            if pull.is_free {
+                summary.free_pulls += 1;
+                banner.free_pulls += 1;
+                continue;
+            }
+
+            let price = options.pull_price(pull);
+            summary.paid_pulls += 1;
+            summary.amount += price;
+            banner.paid_pulls += 1;
+            banner.amount += price;
+        }
+
+        summary.money = options.to_money(currency, summary.amount);
+        summaries.push(summary);
+    }
+
+    let banners = banners
+        .into_values()
+        .map(|mut banner| {
+            banner.money = options.to_money(banner.currency, banner.amount);
+            banner
+        })
+        .collect::<Vec<_>>();
+
+    Ok(SpendReport {
+        user_key: user_key.trim().to_string(),
+        total_money: sum_money(summaries.iter().map(|summary| summary.money)),
+        summaries,
+        banners,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::SPECIAL_POOL_KEY;
+    use crate::testutil;
+    use serde_json::{json, Value};
+
+    fn pull(kind: GachaKind, pool_key: &str, pool_id: &str) -> PullRecord {
+        PullRecord {
+            kind,
+            pool_key: pool_key.into(),
+            pool_id: pool_id.into(),
+            pool_name: String::new(),
+            item_id: String::new(),
+            item_name: String::new(),
+            rarity: 4,
+            is_free: false,
+            is_new: false,
+            gacha_ts: 0,
+            seq_id: String::new(),
+        }
+    }
+
+    fn options(value: Value) -> SpendOptions {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn record(seq_id: u32, pool_id: &str, is_free: bool) -> Value {
+        json!({
+            "charId": "chr_1",
+            "charName": "测试",
+            "rarity": 4,
+            "poolId": pool_id,
+            "poolName": pool_id,
+            "isFree": is_free,
+            "gachaTs": (1_768_000_000_000u64 + seq_id as u64).to_string(),
+            "seqId": seq_id.to_string()
+        })
+    }
+
+    #[test]
+    fn pull_price_prefers_pool_id_then_pool_key_then_default() {
+        let options = options(json!({
+            "characterPullPrice": 600,
+            "poolPullPrices": { "special_1_0_1": 300, SPECIAL_POOL_KEY: 400 }
+        }));
+        assert_eq!(options.weapon_pull_price, DEFAULT_WEAPON_PULL_PRICE);
+
+        let banner = pull(GachaKind::Character, SPECIAL_POOL_KEY, "special_1_0_1");
+        let other = pull(GachaKind::Character, SPECIAL_POOL_KEY, "special_1_1_1");
+        let standard = pull(
+            GachaKind::Character,
+            "E_CharacterGachaPoolType_Standard",
+            "std",
+        );
+        let weapon = pull(GachaKind::Weapon, "weponbox_1_0_1", "weponbox_1_0_1");
+        assert_eq!(options.pull_price(&banner), 300);
+        assert_eq!(options.pull_price(&other), 400);
+        assert_eq!(options.pull_price(&standard), 600);
+        assert_eq!(options.pull_price(&weapon), DEFAULT_WEAPON_PULL_PRICE);
+    }
+
+    #[test]
+    fn converts_with_cheapest_tier_of_the_same_currency() {
+        let options = options(json!({
+            "topUpTiers": [
+                { "price": 6.0, "amount": 60, "currency": "oroberyl" },
+                { "price": 648.0, "amount": 8080, "currency": "oroberyl" },
+                { "price": 0.0, "amount": 1000, "currency": "oroberyl" },
+                { "price": 30.0, "amount": 0, "currency": "oroberyl" },
+                { "price": 10.0, "amount": 5, "currency": "arsenalTicket" }
+            ]
+        }));
+
+        let unit = options.best_unit_price(SpendCurrency::Oroberyl).unwrap();
+        assert!((unit - 648.0 / 8080.0).abs() < 1e-12);
+        let money = options.to_money(SpendCurrency::Oroberyl, 8080).unwrap();
+        assert!((money - 648.0).abs() < 1e-9);
+        assert_eq!(
+            options.to_money(SpendCurrency::ArsenalTicket, 10),
+            Some(20.0)
+        );
+
+        let without_tiers = SpendOptions::default();
+        assert_eq!(without_tiers.to_money(SpendCurrency::Oroberyl, 500), None);
+    }
+
+    #[test]
+    fn sum_money_is_none_only_when_nothing_converts() {
+        assert_eq!(sum_money([None, None].into_iter()), None);
+        assert_eq!(
+            sum_money([Some(1.5), None, Some(2.0)].into_iter()),
+            Some(3.5)
+        );
+        assert_eq!(sum_money(std::iter::empty()), None);
+    }
+
+    #[test]
+    fn empty_record_maps_report_zero_spend() {
+        let _guard = testutil::userdata();
+        testutil::write_record("1001_r1", &json!({ "character": {}, "weapon": {} }));
+
+        for user_key in ["1001_r1", "missing_r2"] {
+            let report = estimate_spend(user_key.into(), None).unwrap();
+            assert!(report.banners.is_empty());
+            assert_eq!(report.total_money, None);
+            assert_eq!(report.summaries.len(), 2);
+            for summary in &report.summaries {
+                assert_eq!(summary.total_pulls, 0);
+                assert_eq!(summary.amount, 0);
+                assert_eq!(summary.money, None);
+            }
+        }
+        assert!(estimate_spend(" ".into(), None).is_err());
+    }
+
+    #[test]
+    fn free_pulls_are_counted_but_not_priced() {
+        let _guard = testutil::userdata();
+        testutil::write_record(
+            "1001_r1",
+            &json!({
+                "character": {
+                    SPECIAL_POOL_KEY: [
+                        record(4, "special_1_1_1", false),
+                        record(3, "special_1_0_1", true),
+                        record(2, "special_1_0_1", false),
+                        record(1, "special_1_0_1", false)
+                    ]
+                },
+                "weapon": {
+                    "weponbox_1_0_1": [
+                        record(6, "weponbox_1_0_1", false),
+                        record(5, "weponbox_1_0_1", false)
+                    ]
+                }
+            }),
+        );
+
+        let report = estimate_spend(
+            "1001_r1".into(),
+            Some(options(json!({
+                "topUpTiers": [{ "price": 1.0, "amount": 100, "currency": "oroberyl" }]
+            }))),
+        )
+        .unwrap();
+
+        let character = &report.summaries[0];
+        assert_eq!(character.currency, SpendCurrency::Oroberyl);
+        assert_eq!(
+            (
+                character.total_pulls,
+                character.paid_pulls,
+                character.free_pulls
+            ),
+            (4, 3, 1)
+        );
+        assert_eq!(character.amount, 3 * DEFAULT_CHARACTER_PULL_PRICE);
+        assert_eq!(character.money, Some(15.0));
+
+        let weapon = &report.summaries[1];
+        assert_eq!(weapon.currency, SpendCurrency::ArsenalTicket);
+        assert_eq!(weapon.amount, 2 * DEFAULT_WEAPON_PULL_PRICE);
+        assert_eq!(weapon.money, None);
+        assert_eq!(report.total_money, Some(15.0));
+
+        let banners = report
+            .banners
+            .iter()
+            .map(|banner| {
+                (
+                    banner.pool_id.as_str(),
+                    banner.paid_pulls,
+                    banner.free_pulls,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            banners,
+            [
+                ("special_1_0_1", 2, 1),
+                ("special_1_1_1", 1, 0),
+                ("weponbox_1_0_1", 2, 0)
+            ]
+        );
+    }
+}
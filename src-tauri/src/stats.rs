@@ -0,0 +1,1322 @@
+use crate::{compare_seqid, load_full_record, load_pool_info, value_to_seqid};
+use chrono::{DateTime, Datelike, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use tauri::command;
+
+pub(crate) const SPECIAL_POOL_KEY: &str = "E_CharacterGachaPoolType_Special";
+pub(crate) const JOINT_POOL_KEY: &str = "E_CharacterGachaPoolType_Joint";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum GachaKind {
+    Character,
+    Weapon,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PullRecord {
+    pub(crate) kind: GachaKind,
+    pub(crate) pool_key: String,
+    pub(crate) pool_id: String,
+    pub(crate) pool_name: String,
+    pub(crate) item_id: String,
+    pub(crate) item_name: String,
+    pub(crate) rarity: u8,
+    pub(crate) is_free: bool,
+    pub(crate) is_new: bool,
+    pub(crate) gacha_ts: i64,
+    pub(crate) seq_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PityPull {
+    #[serde(flatten)]
+    pub(crate) pull: PullRecord,
+    pub(crate) pity: u32,
+    /// 是否计入小保底；不计入的免费抽出 6★ 不重置垫抽
+    pub(crate) counts_pity: bool,
+    pub(crate) big_pity: Option<u32>,
+    pub(crate) is_up: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct PoolItemInfo {
+    #[serde(default)]
+    pub(crate) id: String,
+    #[serde(default)]
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) rarity: u8,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct PoolInfoEntry {
+    #[serde(default)]
+    pub(crate) pool_id: String,
+    #[serde(default)]
+    pub(crate) pool_gacha_type: String,
+    #[serde(default)]
+    pub(crate) pool_name: String,
+    #[serde(default)]
+    pub(crate) pool_type: String,
+    #[serde(default)]
+    pub(crate) up6_id: String,
+    #[serde(default)]
+    pub(crate) up6_ids: Vec<String>,
+    #[serde(default)]
+    pub(crate) all: Vec<PoolItemInfo>,
+}
+
+impl PoolInfoEntry {
+    /// 与前端 getPoolInfoUp6Ids 一致：优先 up6_ids，回退到 up6_id
+    pub(crate) fn up6_id_list(&self) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+        for id in self.up6_ids.iter() {
+            let id = id.trim();
+            if !id.is_empty() && !result.iter().any(|item| item == id) {
+                result.push(id.to_string());
+            }
+        }
+        if result.is_empty() && !self.up6_id.trim().is_empty() {
+            result.push(self.up6_id.trim().to_string());
+        }
+        result
+    }
+
+    pub(crate) fn gacha_kind(&self) -> Option<GachaKind> {
+        match self.pool_gacha_type.trim() {
+            "char" | "character" => Some(GachaKind::Character),
+            "weapon" => Some(GachaKind::Weapon),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PoolCatalog {
+    entries: Vec<PoolInfoEntry>,
+    index: HashMap<String, usize>,
+}
+
+impl PoolCatalog {
+    pub(crate) fn from_value(value: &Value) -> Self {
+        let mut catalog = PoolCatalog::default();
+        let Some(items) = value.as_array() else {
+            return catalog;
+        };
+        for item in items {
+            let Ok(entry) = serde_json::from_value::<PoolInfoEntry>(item.clone()) else {
+                continue;
+            };
+            if entry.pool_id.trim().is_empty() {
+                continue;
+            }
+            catalog
+                .index
+                .insert(entry.pool_id.clone(), catalog.entries.len());
+            catalog.entries.push(entry);
+        }
+        catalog
+    }
+
+    pub(crate) fn load() -> Result<Self, String> {
+        Ok(Self::from_value(&load_pool_info()?))
+    }
+
+    pub(crate) fn get(&self, pool_id: &str) -> Option<&PoolInfoEntry> {
+        self.index
+            .get(pool_id)
+            .and_then(|index| self.entries.get(*index))
+    }
+
+    pub(crate) fn entries(&self) -> &[PoolInfoEntry] {
+        &self.entries
+    }
+
+    pub(crate) fn up6_ids(&self, pool_id: &str) -> Vec<String> {
+        self.get(pool_id)
+            .map(|entry| entry.up6_id_list())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AccountPulls {
+    pub(crate) character: BTreeMap<String, Vec<PullRecord>>,
+    pub(crate) weapon: BTreeMap<String, Vec<PullRecord>>,
+}
+
+impl AccountPulls {
+    pub(crate) fn pools(&self, kind: GachaKind) -> &BTreeMap<String, Vec<PullRecord>> {
+        match kind {
+            GachaKind::Character => &self.character,
+            GachaKind::Weapon => &self.weapon,
+        }
+    }
+}
+
+fn value_to_string(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.trim().to_string(),
+        Some(Value::Number(number)) => number.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn value_to_bool(value: Option<&Value>) -> bool {
+    match value {
+        Some(Value::Bool(flag)) => *flag,
+        Some(Value::Number(number)) => number.as_i64().unwrap_or(0) != 0,
+        Some(Value::String(text)) => matches!(text.trim(), "true" | "1"),
+        _ => false,
+    }
+}
+
+/// gachaTs 为毫秒字符串，兼容 10 位秒级时间戳
+pub(crate) fn parse_gacha_ts(value: &str) -> Option<i64> {
+    let raw = value.trim();
+    if raw.is_empty() || !raw.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let number: i64 = raw.parse().ok()?;
+    if raw.len() <= 10 {
+        return Some(number * 1000);
+    }
+    if raw.len() >= 13 {
+        return Some(number);
+    }
+    Some(if number < 100_000_000_000 {
+        number * 1000
+    } else {
+        number
+    })
+}
+
+pub(crate) fn parse_pull(kind: GachaKind, pool_key: &str, item: &Value) -> Option<PullRecord> {
+    let obj = item.as_object()?;
+    let (id_field, name_field) = match kind {
+        GachaKind::Character => ("charId", "charName"),
+        GachaKind::Weapon => ("weaponId", "weaponName"),
+    };
+    let rarity = match obj.get("rarity") {
+        Some(Value::Number(number)) => number.as_u64().unwrap_or(0),
+        Some(Value::String(text)) => text.trim().parse().unwrap_or(0),
+        _ => 0,
+    };
+    let pool_id = value_to_string(obj.get("poolId"));
+
+    Some(PullRecord {
+        kind,
+        pool_key: pool_key.to_string(),
+        pool_id: if pool_id.is_empty() && kind == GachaKind::Weapon {
+            pool_key.to_string()
+        } else {
+            pool_id
+        },
+        pool_name: value_to_string(obj.get("poolName")),
+        item_id: value_to_string(obj.get(id_field)),
+        item_name: value_to_string(obj.get(name_field)),
+        rarity: rarity.min(u8::MAX as u64) as u8,
+        is_free: value_to_bool(obj.get("isFree")),
+        is_new: value_to_bool(obj.get("isNew")),
+        gacha_ts: parse_gacha_ts(&value_to_string(obj.get("gachaTs"))).unwrap_or(0),
+        seq_id: obj
+            .get("seqId")
+            .and_then(value_to_seqid)
+            .unwrap_or_default(),
+    })
+}
+
+/// 本地记录按 seqId 倒序存储，这里统一翻转为时间正序；表格导入生成的非数字 seqId
+/// 会被排到最旧一端，因此翻转后再按 gachaTs 稳定排序，同一时间戳（十连）内保持原始顺序
+pub(crate) fn pulls_from_record_map(
+    kind: GachaKind,
+    records: &Value,
+) -> BTreeMap<String, Vec<PullRecord>> {
+    let mut result = BTreeMap::new();
+    let Some(obj) = records.as_object() else {
+        return result;
+    };
+    for (pool_key, list) in obj.iter() {
+        let Some(items) = list.as_array() else {
+            continue;
+        };
+        let mut pulls = items
+            .iter()
+            .rev()
+            .filter_map(|item| parse_pull(kind, pool_key, item))
+            .collect::<Vec<_>>();
+        pulls.sort_by_key(|pull| pull.gacha_ts);
+        result.insert(pool_key.clone(), pulls);
+    }
+    result
+}
+
+pub(crate) fn account_pulls_from_value(full_data: &Value) -> AccountPulls {
+    AccountPulls {
+        character: pulls_from_record_map(
+            GachaKind::Character,
+            full_data.get("character").unwrap_or(&Value::Null),
+        ),
+        weapon: pulls_from_record_map(
+            GachaKind::Weapon,
+            full_data.get("weapon").unwrap_or(&Value::Null),
+        ),
+    }
+}
+
+pub(crate) fn load_account_pulls(user_key: &str) -> Result<AccountPulls, String> {
+    if user_key.trim().is_empty() {
+        return Err("UID cannot be empty".into());
+    }
+    Ok(account_pulls_from_value(&load_full_record(
+        user_key.trim(),
+    )?))
+}
+
+pub(crate) fn compare_pull_time(a: &PullRecord, b: &PullRecord) -> Ordering {
+    a.gacha_ts
+        .cmp(&b.gacha_ts)
+        .then_with(|| compare_seqid(&a.seq_id, &b.seq_id))
+}
+
+/// 免费抽（加急招募等活动赠送）是否计入小保底 / 大保底
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FreePullRule {
+    counts_small_pity: bool,
+    counts_big_pity: bool,
+}
+
+pub(crate) const SPECIAL_BIG_PITY_MAX: u32 = 120;
+
+/// 卡池规则：默认值与前端 gachaCalc 保持一致，可按卡池类型或卡池 ID 覆盖免费抽规则
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PityRules {
+    free_pull_overrides: HashMap<String, FreePullRule>,
+}
+
+impl PityRules {
+    pub(crate) fn new(free_pull_overrides: HashMap<String, FreePullRule>) -> Self {
+        Self {
+            free_pull_overrides,
+        }
+    }
+
+    pub(crate) fn free_pull_rule(
+        &self,
+        kind: GachaKind,
+        pool_key: &str,
+        pool_id: &str,
+    ) -> FreePullRule {
+        if let Some(rule) = self
+            .free_pull_overrides
+            .get(pool_id)
+            .or_else(|| self.free_pull_overrides.get(pool_key))
+        {
+            return *rule;
+        }
+        let is_event_pool = kind == GachaKind::Character
+            && (pool_key == SPECIAL_POOL_KEY || pool_key == JOINT_POOL_KEY);
+        FreePullRule {
+            counts_small_pity: !is_event_pool,
+            counts_big_pity: !is_event_pool,
+        }
+    }
+
+    /// 特殊寻访（联合卡池）小保底按池独立，其余卡池类型内连续继承
+    fn resets_small_pity_per_pool(kind: GachaKind, pool_key: &str) -> bool {
+        kind == GachaKind::Character && pool_key == JOINT_POOL_KEY
+    }
+
+    fn big_pity_max(kind: GachaKind, pool_key: &str) -> Option<u32> {
+        if kind == GachaKind::Character && pool_key == SPECIAL_POOL_KEY {
+            Some(SPECIAL_BIG_PITY_MAX)
+        } else {
+            None
+        }
+    }
+}
+
+/// 逐抽计算出货时的垫抽数：不计入小保底的免费抽不累加也不重置垫抽，
+/// 特许寻访记录当前垫抽，按池独立的特殊寻访记为 0，与前端 gachaCalc 一致
+pub(crate) fn annotate_pity(
+    kind: GachaKind,
+    pool_key: &str,
+    pulls: &[PullRecord],
+    catalog: &PoolCatalog,
+    rules: &PityRules,
+) -> Vec<PityPull> {
+    let mut result = Vec::with_capacity(pulls.len());
+    let mut pity: u32 = 0;
+    let mut big_pity: u32 = 0;
+    let mut current_pool_id: Option<&str> = None;
+    let resets_per_pool = PityRules::resets_small_pity_per_pool(kind, pool_key);
+    let big_pity_max = PityRules::big_pity_max(kind, pool_key);
+
+    for pull in pulls {
+        if current_pool_id != Some(pull.pool_id.as_str()) {
+            if resets_per_pool {
+                pity = 0;
+            }
+            big_pity = 0;
+        }
+        current_pool_id = Some(pull.pool_id.as_str());
+
+        let rule = rules.free_pull_rule(kind, pool_key, &pull.pool_id);
+        let counts_small = !pull.is_free || rule.counts_small_pity;
+        let counts_big = !pull.is_free || rule.counts_big_pity;
+        if counts_small {
+            pity += 1;
+        }
+        if counts_big {
+            big_pity += 1;
+        }
+
+        let is_up = pull.rarity == 6 && {
+            let up6_ids = catalog.up6_ids(&pull.pool_id);
+            !up6_ids.is_empty() && up6_ids.iter().any(|id| id == &pull.item_id)
+        };
+
+        result.push(PityPull {
+            pull: pull.clone(),
+            pity: if counts_small || !resets_per_pool {
+                pity
+            } else {
+                0
+            },
+            counts_pity: counts_small,
+            big_pity: big_pity_max.map(|max| big_pity.min(max)),
+            is_up,
+        });
+
+        if pull.rarity == 6 && counts_small {
+            pity = 0;
+        }
+    }
+
+    result
+}
+
+/// 限定池出 6★ 但不是 UP 角色 / 武器，即“歪”
+pub(crate) fn is_off_banner(item: &PityPull, catalog: &PoolCatalog) -> bool {
+    item.pull.rarity == 6 && !item.is_up && !catalog.up6_ids(&item.pull.pool_id).is_empty()
+}
+
+pub(crate) fn annotate_account(
+    account: &AccountPulls,
+    kind: Option<GachaKind>,
+    catalog: &PoolCatalog,
+    rules: &PityRules,
+) -> Vec<PityPull> {
+    let mut result = Vec::new();
+    for current_kind in [GachaKind::Character, GachaKind::Weapon] {
+        if kind.is_some_and(|kind| kind != current_kind) {
+            continue;
+        }
+        for (pool_key, pulls) in account.pools(current_kind).iter() {
+            result.extend(annotate_pity(current_kind, pool_key, pulls, catalog, rules));
+        }
+    }
+    result.sort_by(|a, b| compare_pull_time(&a.pull, &b.pull));
+    result
+}
+
+/// 6★ 出货概率模型：基础概率、软保底起点与每抽递增、硬保底
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PityModel {
+    base_rate: f64,
+    soft_pity_start: u32,
+    soft_pity_step: f64,
+    hard_pity: u32,
+}
+
+const CHARACTER_PITY_MODEL: PityModel = PityModel {
+    base_rate: 0.008,
+    soft_pity_start: 65,
+    soft_pity_step: 0.05,
+    hard_pity: 80,
+};
+
+const WEAPON_PITY_MODEL: PityModel = PityModel {
+    base_rate: 0.04,
+    soft_pity_start: 40,
+    soft_pity_step: 0.0,
+    hard_pity: 40,
+};
+
+impl PityModel {
+    pub(crate) fn for_kind(kind: GachaKind) -> Self {
+        match kind {
+            GachaKind::Character => CHARACTER_PITY_MODEL,
+            GachaKind::Weapon => WEAPON_PITY_MODEL,
+        }
+    }
+
+    fn rate_at(&self, pity: u32) -> f64 {
+        if pity >= self.hard_pity {
+            return 1.0;
+        }
+        if pity > self.soft_pity_start {
+            let boosted =
+                self.base_rate + self.soft_pity_step * (pity - self.soft_pity_start) as f64;
+            return boosted.min(1.0);
+        }
+        self.base_rate
+    }
+
+    /// 单次出货所需抽数的期望与方差
+    fn moments(&self) -> (f64, f64) {
+        let mut survive = 1.0;
+        let mut mean = 0.0;
+        let mut second = 0.0;
+        for pity in 1..=self.hard_pity {
+            let rate = self.rate_at(pity);
+            let probability = survive * rate;
+            mean += probability * pity as f64;
+            second += probability * (pity as f64) * (pity as f64);
+            survive *= 1.0 - rate;
+        }
+        (mean, (second - mean * mean).max(0.0))
+    }
+
+    /// 平均出货抽数优于多少比例的玩家（0-100），样本不足时返回 None
+    pub(crate) fn luck_percentile(&self, pities: &[u32]) -> Option<f64> {
+        if pities.is_empty() {
+            return None;
+        }
+        let (mean, variance) = self.moments();
+        let observed = pities.iter().map(|pity| *pity as f64).sum::<f64>() / pities.len() as f64;
+        let std_error = (variance / pities.len() as f64).sqrt();
+        if std_error <= f64::EPSILON {
+            return Some(50.0);
+        }
+        let z = (mean - observed) / std_error;
+        Some((normal_cdf(z) * 100.0).clamp(0.0, 100.0))
+    }
+}
+
+fn normal_cdf(value: f64) -> f64 {
+    // Abramowitz-Stegun 7.1.26
+    let x = value.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if value >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
+
+pub(crate) fn parse_gacha_kind(value: Option<&str>) -> Result<Option<GachaKind>, String> {
+    match value.map(str::trim).unwrap_or("") {
+        "" | "all" => Ok(None),
+        "char" | "character" => Ok(Some(GachaKind::Character)),
+        "weapon" => Ok(Some(GachaKind::Weapon)),
+        other => Err(format!("Unknown gacha type: {}", other)),
+    }
+}
+
+pub(crate) fn local_datetime(ts: i64) -> Option<DateTime<Local>> {
+    if ts <= 0 {
+        return None;
+    }
+    Local.timestamp_millis_opt(ts).single()
+}
+
+/// 从 special_1_2_1 / weponbox_1_2_1 / joint_1_2_2 这类卡池 ID 中解析版本号
+pub(crate) fn version_from_pool_id(pool_id: &str) -> Option<String> {
+    let segments: Vec<&str> = pool_id.split('_').collect();
+    segments.windows(2).find_map(|pair| {
+        let is_number =
+            |value: &str| !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit());
+        if is_number(pair[0]) && is_number(pair[1]) {
+            Some(format!("{}.{}", pair[0], pair[1]))
+        } else {
+            None
+        }
+    })
+}
+
+fn compare_version(a: &str, b: &str) -> Ordering {
+    let parse = |value: &str| {
+        value
+            .split('.')
+            .map(|part| part.parse::<u32>().unwrap_or(u32::MAX))
+            .collect::<Vec<_>>()
+    };
+    parse(a).cmp(&parse(b))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimelineGranularity {
+    Day,
+    Week,
+    Month,
+    Version,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineBucket {
+    key: String,
+    start_ts: i64,
+    end_ts: i64,
+    pulls: u32,
+    paid_pulls: u32,
+    free_pulls: u32,
+    count6: u32,
+    count5: u32,
+    cumulative_pulls: u32,
+    cumulative_count6: u32,
+    cumulative_avg_pity: f64,
+    banner_starts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineBanner {
+    pool_id: String,
+    pool_name: String,
+    pool_type: String,
+    gacha_type: GachaKind,
+    first_ts: i64,
+    last_ts: i64,
+    bucket_key: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineReport {
+    user_key: String,
+    granularity: TimelineGranularity,
+    undated_pulls: u32,
+    buckets: Vec<TimelineBucket>,
+    banners: Vec<TimelineBanner>,
+}
+
+/// 常驻池等不带版本号的卡池，按时间归属到最近一次出现的版本
+fn assign_versions(pulls: &[PityPull]) -> Vec<String> {
+    let mut known: Vec<(i64, String)> = pulls
+        .iter()
+        .filter_map(|item| {
+            version_from_pool_id(&item.pull.pool_id).map(|version| (item.pull.gacha_ts, version))
+        })
+        .collect();
+    known.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| compare_version(&a.1, &b.1)));
+
+    pulls
+        .iter()
+        .map(|item| {
+            if let Some(version) = version_from_pool_id(&item.pull.pool_id) {
+                return version;
+            }
+            let ts = item.pull.gacha_ts;
+            known
+                .iter()
+                .rev()
+                .find(|(known_ts, _)| *known_ts <= ts)
+                .or_else(|| known.first())
+                .map(|(_, version)| version.clone())
+                .unwrap_or_else(|| "unknown".into())
+        })
+        .collect()
+}
+
+fn bucket_key(granularity: TimelineGranularity, ts: i64) -> Option<String> {
+    let time = local_datetime(ts)?;
+    Some(match granularity {
+        TimelineGranularity::Day => time.format("%Y-%m-%d").to_string(),
+        TimelineGranularity::Week => {
+            let week = time.iso_week();
+            format!("{}-W{:02}", week.year(), week.week())
+        }
+        TimelineGranularity::Month => time.format("%Y-%m").to_string(),
+        TimelineGranularity::Version => return None,
+    })
+}
+
+pub(crate) fn build_timeline(
+    user_key: &str,
+    granularity: TimelineGranularity,
+    pulls: &[PityPull],
+    catalog: &PoolCatalog,
+) -> TimelineReport {
+    let versions = if granularity == TimelineGranularity::Version {
+        assign_versions(pulls)
+    } else {
+        Vec::new()
+    };
+
+    let mut buckets: Vec<TimelineBucket> = Vec::new();
+    let mut bucket_index: HashMap<String, usize> = HashMap::new();
+    let mut banners: Vec<TimelineBanner> = Vec::new();
+    let mut banner_index: HashMap<(GachaKind, String), usize> = HashMap::new();
+    let mut undated_pulls = 0;
+
+    for (index, item) in pulls.iter().enumerate() {
+        let key = if granularity == TimelineGranularity::Version {
+            Some(versions[index].clone())
+        } else {
+            bucket_key(granularity, item.pull.gacha_ts)
+        };
+        let Some(key) = key else {
+            undated_pulls += 1;
+            continue;
+        };
+
+        let position = *bucket_index.entry(key.clone()).or_insert_with(|| {
+            buckets.push(TimelineBucket {
+                key: key.clone(),
+                start_ts: item.pull.gacha_ts,
+                end_ts: item.pull.gacha_ts,
+                pulls: 0,
+                paid_pulls: 0,
+                free_pulls: 0,
+                count6: 0,
+                count5: 0,
+                cumulative_pulls: 0,
+                cumulative_count6: 0,
+                cumulative_avg_pity: 0.0,
+                banner_starts: Vec::new(),
+            });
+            buckets.len() - 1
+        });
+        let bucket = &mut buckets[position];
+        bucket.pulls += 1;
+        if item.pull.is_free {
+            bucket.free_pulls += 1;
+        } else {
+            bucket.paid_pulls += 1;
+        }
+        match item.pull.rarity {
+            6 => bucket.count6 += 1,
+            5 => bucket.count5 += 1,
+            _ => {}
+        }
+        if item.pull.gacha_ts > 0 {
+            if bucket.start_ts <= 0 || item.pull.gacha_ts < bucket.start_ts {
+                bucket.start_ts = item.pull.gacha_ts;
+            }
+            bucket.end_ts = bucket.end_ts.max(item.pull.gacha_ts);
+        }
+
+        let pool_id = item.pull.pool_id.clone();
+        if pool_id.is_empty() || catalog.get(&pool_id).is_none() {
+            continue;
+        }
+        let banner_key = (item.pull.kind, pool_id.clone());
+        if let Some(existing) = banner_index.get(&banner_key) {
+            banners[*existing].last_ts = banners[*existing].last_ts.max(item.pull.gacha_ts);
+            continue;
+        }
+        let info = catalog.get(&pool_id).cloned().unwrap_or_default();
+        banner_index.insert(banner_key, banners.len());
+        bucket.banner_starts.push(pool_id.clone());
+        banners.push(TimelineBanner {
+            pool_name: if info.pool_name.is_empty() {
+                item.pull.pool_name.clone()
+            } else {
+                info.pool_name
+            },
+            pool_type: info.pool_type,
+            pool_id,
+            gacha_type: item.pull.kind,
+            first_ts: item.pull.gacha_ts,
+            last_ts: item.pull.gacha_ts,
+            bucket_key: key,
+        });
+    }
+
+    if granularity == TimelineGranularity::Version {
+        buckets.sort_by(|a, b| compare_version(&a.key, &b.key));
+    } else {
+        buckets.sort_by(|a, b| a.key.cmp(&b.key));
+    }
+
+    // 累计平均出货抽数需要按桶顺序重新累加，未计入垫抽的免费 6★ 不参与平均
+    let mut pity_by_key: HashMap<String, (u32, u32, u64)> = HashMap::new();
+    for (index, item) in pulls.iter().enumerate() {
+        if item.pull.rarity != 6 {
+            continue;
+        }
+        let key = if granularity == TimelineGranularity::Version {
+            Some(versions[index].clone())
+        } else {
+            bucket_key(granularity, item.pull.gacha_ts)
+        };
+        let Some(key) = key else {
+            continue;
+        };
+        let entry = pity_by_key.entry(key).or_insert((0, 0, 0));
+        entry.0 += 1;
+        if item.counts_pity && item.pity > 0 {
+            entry.1 += 1;
+            entry.2 += item.pity as u64;
+        }
+    }
+
+    let mut cumulative_pulls = 0;
+    let mut cumulative_count6 = 0;
+    let mut cumulative_counted = 0;
+    let mut cumulative_pity: u64 = 0;
+    for bucket in buckets.iter_mut() {
+        cumulative_pulls += bucket.pulls;
+        if let Some((count, counted, pity)) = pity_by_key.get(&bucket.key) {
+            cumulative_count6 += count;
+            cumulative_counted += counted;
+            cumulative_pity += pity;
+        }
+        bucket.cumulative_pulls = cumulative_pulls;
+        bucket.cumulative_count6 = cumulative_count6;
+        bucket.cumulative_avg_pity = if cumulative_counted > 0 {
+            cumulative_pity as f64 / cumulative_counted as f64
+        } else {
+            0.0
+        };
+    }
+
+    banners.sort_by_key(|item| item.first_ts);
+
+    TimelineReport {
+        user_key: user_key.to_string(),
+        granularity,
+        undated_pulls,
+        buckets,
+        banners,
+    }
+}
+
+#[command]
+pub fn stats_timeline(
+    user_key: String,
+    granularity: TimelineGranularity,
+    gacha_type: Option<String>,
+    free_pull_rules: Option<HashMap<String, FreePullRule>>,
+) -> Result<TimelineReport, String> {
+    let kind = parse_gacha_kind(gacha_type.as_deref())?;
+    let account = load_account_pulls(&user_key)?;
+    let catalog = PoolCatalog::load()?;
+    let rules = PityRules::new(free_pull_rules.unwrap_or_default());
+    let pulls = annotate_account(&account, kind, &catalog, &rules);
+    Ok(build_timeline(
+        user_key.trim(),
+        granularity,
+        &pulls,
+        &catalog,
+    ))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KindStats {
+    pub(crate) gacha_type: GachaKind,
+    pub(crate) total_pulls: u32,
+    pub(crate) paid_pulls: u32,
+    pub(crate) free_pulls: u32,
+    pub(crate) count6: u32,
+    pub(crate) count5: u32,
+    pub(crate) free_count6: u32,
+    pub(crate) free_count5: u32,
+    pub(crate) rate6: f64,
+    pub(crate) rate5: f64,
+    pub(crate) avg_pity: Option<f64>,
+    pub(crate) luck_percentile: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountStatsBreakdown {
+    user_key: String,
+    stats: Vec<KindStats>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateReport {
+    user_keys: Vec<String>,
+    combined: Vec<KindStats>,
+    accounts: Vec<AccountStatsBreakdown>,
+}
+
+fn ratio(count: u32, total: u32) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+pub(crate) fn summarize_kind(kind: GachaKind, pulls: &[PityPull]) -> KindStats {
+    let mut stats = KindStats {
+        gacha_type: kind,
+        total_pulls: 0,
+        paid_pulls: 0,
+        free_pulls: 0,
+        count6: 0,
+        count5: 0,
+        free_count6: 0,
+        free_count5: 0,
+        rate6: 0.0,
+        rate5: 0.0,
+        avg_pity: None,
+        luck_percentile: None,
+    };
+    let mut pities = Vec::new();
+
+    for item in pulls.iter().filter(|item| item.pull.kind == kind) {
+        stats.total_pulls += 1;
+        if item.pull.is_free {
+            stats.free_pulls += 1;
+        } else {
+            stats.paid_pulls += 1;
+        }
+        match item.pull.rarity {
+            6 => {
+                stats.count6 += 1;
+                if item.pull.is_free {
+                    stats.free_count6 += 1;
+                }
+                if item.counts_pity && item.pity > 0 {
+                    pities.push(item.pity);
+                }
+            }
+            5 => {
+                stats.count5 += 1;
+                if item.pull.is_free {
+                    stats.free_count5 += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // 出货率只按付费抽计算，免费抽产出单独统计
+    stats.rate6 = ratio(stats.count6 - stats.free_count6, stats.paid_pulls);
+    stats.rate5 = ratio(stats.count5 - stats.free_count5, stats.paid_pulls);
+    if !pities.is_empty() {
+        stats.avg_pity =
+            Some(pities.iter().map(|pity| *pity as f64).sum::<f64>() / pities.len() as f64);
+    }
+    stats.luck_percentile = PityModel::for_kind(kind).luck_percentile(&pities);
+    stats
+}
+
+/// 多账号合并统计：垫抽在各账号内部独立计算后再合并，避免跨账号串保底
+#[command]
+pub fn stats_aggregate(
+    user_keys: Vec<String>,
+    free_pull_rules: Option<HashMap<String, FreePullRule>>,
+) -> Result<AggregateReport, String> {
+    let mut keys: Vec<String> = Vec::new();
+    for key in user_keys {
+        let key = key.trim().to_string();
+        if !key.is_empty() && !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    if keys.is_empty() {
+        return Err("At least one account is required".into());
+    }
+
+    let catalog = PoolCatalog::load()?;
+    let rules = PityRules::new(free_pull_rules.unwrap_or_default());
+    let mut combined_pulls = Vec::new();
+    let mut accounts = Vec::new();
+
+    for key in keys.iter() {
+        let account = load_account_pulls(key)?;
+        let pulls = annotate_account(&account, None, &catalog, &rules);
+        accounts.push(AccountStatsBreakdown {
+            user_key: key.clone(),
+            stats: [GachaKind::Character, GachaKind::Weapon]
+                .into_iter()
+                .map(|kind| summarize_kind(kind, &pulls))
+                .collect(),
+        });
+        combined_pulls.extend(pulls);
+    }
+    combined_pulls.sort_by(|a, b| compare_pull_time(&a.pull, &b.pull));
+
+    Ok(AggregateReport {
+        user_keys: keys,
+        combined: [GachaKind::Character, GachaKind::Weapon]
+            .into_iter()
+            .map(|kind| summarize_kind(kind, &combined_pulls))
+            .collect(),
+        accounts,
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FreePullPoolStats {
+    gacha_type: GachaKind,
+    pool_key: String,
+    pool_id: String,
+    pool_name: String,
+    rule: FreePullRule,
+    total_pulls: u32,
+    free_pulls: u32,
+    free_count6: u32,
+    free_count5: u32,
+    free_up6: u32,
+    paid_pulls: u32,
+    paid_count6: u32,
+    paid_avg_pity: Option<f64>,
+    paid_luck_percentile: Option<f64>,
+    big_pity_max: Option<u32>,
+    big_pity_count: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FreePullReport {
+    user_key: String,
+    pools: Vec<FreePullPoolStats>,
+}
+
+/// 按卡池拆分免费抽与付费抽产出，付费部分的平均出货与欧非分位不受活动赠送抽影响
+#[command]
+pub fn stats_free_pulls(
+    user_key: String,
+    free_pull_rules: Option<HashMap<String, FreePullRule>>,
+) -> Result<FreePullReport, String> {
+    let account = load_account_pulls(&user_key)?;
+    let catalog = PoolCatalog::load()?;
+    let rules = PityRules::new(free_pull_rules.unwrap_or_default());
+
+    let mut pools: Vec<FreePullPoolStats> = Vec::new();
+    let mut pool_index: HashMap<(GachaKind, String, String), usize> = HashMap::new();
+    let mut paid_pities: Vec<Vec<u32>> = Vec::new();
+
+    for kind in [GachaKind::Character, GachaKind::Weapon] {
+        for (pool_key, pulls) in account.pools(kind).iter() {
+            for item in annotate_pity(kind, pool_key, pulls, &catalog, &rules) {
+                let key = (kind, pool_key.clone(), item.pull.pool_id.clone());
+                let index = *pool_index.entry(key).or_insert_with(|| {
+                    pools.push(FreePullPoolStats {
+                        gacha_type: kind,
+                        pool_key: pool_key.clone(),
+                        pool_id: item.pull.pool_id.clone(),
+                        pool_name: catalog
+                            .get(&item.pull.pool_id)
+                            .map(|info| info.pool_name.clone())
+                            .filter(|name| !name.is_empty())
+                            .unwrap_or_else(|| item.pull.pool_name.clone()),
+                        rule: rules.free_pull_rule(kind, pool_key, &item.pull.pool_id),
+                        total_pulls: 0,
+                        free_pulls: 0,
+                        free_count6: 0,
+                        free_count5: 0,
+                        free_up6: 0,
+                        paid_pulls: 0,
+                        paid_count6: 0,
+                        paid_avg_pity: None,
+                        paid_luck_percentile: None,
+                        big_pity_max: PityRules::big_pity_max(kind, pool_key),
+                        big_pity_count: None,
+                    });
+                    paid_pities.push(Vec::new());
+                    pools.len() - 1
+                });
+
+                let stats = &mut pools[index];
+                stats.total_pulls += 1;
+                if item.big_pity.is_some() {
+                    stats.big_pity_count = item.big_pity;
+                }
+                if item.pull.is_free {
+                    stats.free_pulls += 1;
+                    match item.pull.rarity {
+                        6 => {
+                            stats.free_count6 += 1;
+                            if item.is_up {
+                                stats.free_up6 += 1;
+                            }
+                        }
+                        5 => stats.free_count5 += 1,
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                stats.paid_pulls += 1;
+                if item.pull.rarity == 6 {
+                    stats.paid_count6 += 1;
+                    if item.pity > 0 {
+                        paid_pities[index].push(item.pity);
+                    }
+                }
+            }
+        }
+    }
+
+    for (stats, pities) in pools.iter_mut().zip(paid_pities.iter()) {
+        if !pities.is_empty() {
+            stats.paid_avg_pity =
+                Some(pities.iter().map(|pity| *pity as f64).sum::<f64>() / pities.len() as f64);
+        }
+        stats.paid_luck_percentile = PityModel::for_kind(stats.gacha_type).luck_percentile(pities);
+    }
+
+    Ok(FreePullReport {
+        user_key: user_key.trim().to_string(),
+        pools,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const HOUR: i64 = 3_600_000;
+    const BASE_TS: i64 = 1_768_000_000_000;
+
+    fn pull(
+        kind: GachaKind,
+        pool_key: &str,
+        pool_id: &str,
+        rarity: u8,
+        is_free: bool,
+        index: i64,
+    ) -> PullRecord {
+        PullRecord {
+            kind,
+            pool_key: pool_key.into(),
+            pool_id: pool_id.into(),
+            pool_name: pool_id.into(),
+            item_id: format!("item_{}", rarity),
+            item_name: String::new(),
+            rarity,
+            is_free,
+            is_new: false,
+            gacha_ts: BASE_TS + index * HOUR,
+            seq_id: (1000 + index).to_string(),
+        }
+    }
+
+    /// 按顺序生成一串角色抽卡，`spec` 中每项为 (卡池 ID, 星级, 是否免费)
+    fn character_pulls(pool_key: &str, spec: &[(&str, u8, bool)]) -> Vec<PullRecord> {
+        spec.iter()
+            .enumerate()
+            .map(|(index, (pool_id, rarity, free))| {
+                pull(
+                    GachaKind::Character,
+                    pool_key,
+                    pool_id,
+                    *rarity,
+                    *free,
+                    index as i64,
+                )
+            })
+            .collect()
+    }
+
+    fn annotate(pool_key: &str, pulls: &[PullRecord]) -> Vec<PityPull> {
+        annotate_pity(
+            GachaKind::Character,
+            pool_key,
+            pulls,
+            &PoolCatalog::default(),
+            &PityRules::default(),
+        )
+    }
+
+    #[test]
+    fn special_pool_small_pity_carries_over_between_banners() {
+        let mut spec = vec![("special_1_0_1", 4, false); 5];
+        spec.extend([("special_1_1_1", 4, false); 2]);
+        spec.push(("special_1_1_1", 6, false));
+        spec.push(("special_1_1_1", 4, false));
+        let result = annotate(SPECIAL_POOL_KEY, &character_pulls(SPECIAL_POOL_KEY, &spec));
+
+        assert_eq!(result[7].pity, 8);
+        // 大保底按卡池独立计数
+        assert_eq!(result[7].big_pity, Some(3));
+        assert_eq!(result[8].pity, 1);
+    }
+
+    #[test]
+    fn joint_pool_small_pity_resets_per_banner() {
+        let mut spec = vec![("joint_1_0_1", 4, false); 5];
+        spec.extend([("joint_1_1_1", 4, false); 2]);
+        spec.push(("joint_1_1_1", 6, false));
+        let result = annotate(JOINT_POOL_KEY, &character_pulls(JOINT_POOL_KEY, &spec));
+
+        assert_eq!(result[7].pity, 3);
+        assert_eq!(result[7].big_pity, None);
+    }
+
+    #[test]
+    fn free_special_six_star_keeps_current_pity_without_reset() {
+        let mut spec = vec![("special_1_0_1", 4, false); 10];
+        spec.push(("special_1_0_1", 6, true));
+        spec.push(("special_1_0_1", 6, false));
+        let result = annotate(SPECIAL_POOL_KEY, &character_pulls(SPECIAL_POOL_KEY, &spec));
+
+        let free = &result[10];
+        assert_eq!(free.pity, 10);
+        assert!(!free.counts_pity);
+        assert_eq!(free.big_pity, Some(10));
+        let paid = &result[11];
+        assert_eq!(paid.pity, 11);
+        assert!(paid.counts_pity);
+        assert_eq!(paid.big_pity, Some(11));
+    }
+
+    #[test]
+    fn free_joint_six_star_records_zero_pity() {
+        let spec = [
+            ("joint_1_0_1", 4, false),
+            ("joint_1_0_1", 4, false),
+            ("joint_1_0_1", 6, true),
+            ("joint_1_0_1", 6, false),
+        ];
+        let result = annotate(JOINT_POOL_KEY, &character_pulls(JOINT_POOL_KEY, &spec));
+
+        assert_eq!(result[2].pity, 0);
+        assert_eq!(result[3].pity, 3);
+    }
+
+    #[test]
+    fn free_pulls_count_outside_event_pools_unless_overridden() {
+        let pool_key = "E_CharacterGachaPoolType_Standard";
+        let spec = [
+            ("standard", 4, true),
+            ("standard", 4, false),
+            ("standard", 6, true),
+            ("standard", 4, false),
+        ];
+        let pulls = character_pulls(pool_key, &spec);
+        let result = annotate(pool_key, &pulls);
+        assert_eq!(result[2].pity, 3);
+        assert!(result[2].counts_pity);
+        assert_eq!(result[3].pity, 1);
+
+        let rules: PityRules = PityRules::new(HashMap::from([(
+            pool_key.to_string(),
+            serde_json::from_value::<FreePullRule>(
+                json!({ "countsSmallPity": false, "countsBigPity": false }),
+            )
+            .unwrap(),
+        )]));
+        let result = annotate_pity(
+            GachaKind::Character,
+            pool_key,
+            &pulls,
+            &PoolCatalog::default(),
+            &rules,
+        );
+        assert_eq!(result[2].pity, 1);
+        assert!(!result[2].counts_pity);
+        assert_eq!(result[3].pity, 2);
+    }
+
+    #[test]
+    fn up_detection_uses_pool_catalog() {
+        let catalog = PoolCatalog::from_value(&json!([
+            { "pool_id": "special_1_0_1", "pool_gacha_type": "char", "up6_id": "item_6" },
+        ]));
+        let mut pulls = character_pulls(SPECIAL_POOL_KEY, &[("special_1_0_1", 6, false); 2]);
+        pulls[1].item_id = "other".into();
+        let result = annotate_pity(
+            GachaKind::Character,
+            SPECIAL_POOL_KEY,
+            &pulls,
+            &catalog,
+            &PityRules::default(),
+        );
+
+        assert!(result[0].is_up);
+        assert!(!is_off_banner(&result[0], &catalog));
+        assert!(!result[1].is_up);
+        assert!(is_off_banner(&result[1], &catalog));
+    }
+
+    #[test]
+    fn luck_percentile_centers_on_model_mean() {
+        let model = PityModel::for_kind(GachaKind::Character);
+        assert_eq!(model.luck_percentile(&[]), None);
+
+        let (mean, _) = model.moments();
+        let average = model.luck_percentile(&[mean.round() as u32; 20]).unwrap();
+        assert!((average - 50.0).abs() < 10.0, "{}", average);
+        let lucky = model.luck_percentile(&[10; 5]).unwrap();
+        let unlucky = model.luck_percentile(&[80; 5]).unwrap();
+        assert!(lucky > 90.0, "{}", lucky);
+        assert!(unlucky < 10.0, "{}", unlucky);
+    }
+
+    #[test]
+    fn parses_versions_and_timestamps() {
+        assert_eq!(
+            version_from_pool_id("special_1_2_1").as_deref(),
+            Some("1.2")
+        );
+        assert_eq!(
+            version_from_pool_id("weponbox_1_10_3").as_deref(),
+            Some("1.10")
+        );
+        assert_eq!(version_from_pool_id("standard"), None);
+        assert_eq!(compare_version("1.10", "1.9"), Ordering::Greater);
+        assert_eq!(parse_gacha_ts("1768000000"), Some(1_768_000_000_000));
+        assert_eq!(parse_gacha_ts("1768000000123"), Some(1_768_000_000_123));
+        assert_eq!(parse_gacha_ts("17x"), None);
+    }
+
+    #[test]
+    fn version_timeline_assigns_unversioned_pools_and_skips_free_pity() {
+        let mut pulls = character_pulls(
+            SPECIAL_POOL_KEY,
+            &[
+                ("special_1_0_1", 4, false),
+                ("special_1_0_1", 6, false),
+                ("special_1_1_1", 4, false),
+                ("special_1_1_1", 6, true),
+            ],
+        );
+        pulls.push(pull(
+            GachaKind::Character,
+            "E_CharacterGachaPoolType_Standard",
+            "standard",
+            6,
+            false,
+            10,
+        ));
+        let mut annotated = annotate(SPECIAL_POOL_KEY, &pulls[..4]);
+        annotated.extend(annotate("E_CharacterGachaPoolType_Standard", &pulls[4..]));
+
+        let report = build_timeline(
+            "u1",
+            TimelineGranularity::Version,
+            &annotated,
+            &PoolCatalog::default(),
+        );
+        let keys = report
+            .buckets
+            .iter()
+            .map(|bucket| bucket.key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["1.0", "1.1"]);
+
+        let latest = &report.buckets[1];
+        assert_eq!(latest.pulls, 3);
+        assert_eq!(latest.free_pulls, 1);
+        assert_eq!(latest.cumulative_count6, 3);
+        // 免费 6★ 不计入平均：(2 + 1) / 2
+        assert_eq!(latest.cumulative_avg_pity, 1.5);
+        assert_eq!(report.undated_pulls, 0);
+    }
+}
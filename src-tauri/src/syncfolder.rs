@@ -0,0 +1,349 @@
+//! 本地/同步盘目录后端：Syncthing、Dropbox、网络共享等由外部工具负责在设备间传输文件
+//!
+//! ETag 取文件内容的 SHA-256；写入先落临时文件再重命名，避免同步工具读到半截文件。
+
+use crate::remote::{PutOutcome, PutPrecondition, RemoteEntry, RemoteFile, RemoteStore};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 同步工具生成的冲突副本，不当作账号文件读取
+const CONFLICT_MARKERS: [&str; 3] = [".sync-conflict-", "conflicted copy", "冲突副本"];
+
+pub(crate) struct FolderStore {
+    root: PathBuf,
+    /// 同一进程内串行化“检查条件 + 写入”
+    write_lock: Mutex<()>,
+}
+
+fn content_etag(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    let hex = digest
+        .iter()
+        .take(16)
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    format!("\"{}\"", hex)
+}
+
+fn is_ignored_name(name: &str) -> bool {
+    name.starts_with('.') || CONFLICT_MARKERS.iter().any(|marker| name.contains(marker))
+}
+
+impl FolderStore {
+    pub(crate) fn new(folder_path: &str, base_path: &str) -> Result<Self, String> {
+        let folder_path = folder_path.trim();
+        if folder_path.is_empty() {
+            return Err("请先填写同步目录".into());
+        }
+        let mut root = PathBuf::from(folder_path);
+        if !root.is_absolute() {
+            return Err("同步目录必须是绝对路径".into());
+        }
+        for segment in base_path.split('/').filter(|item| !item.is_empty()) {
+            if segment == ".." {
+                return Err("目录路径无效".into());
+            }
+            root.push(segment);
+        }
+        Ok(Self {
+            root,
+            write_lock: Mutex::new(()),
+        })
+    }
+
+    /// 远端路径来自 manifest，拒绝 `..` 以免越出同步目录
+    fn resolve(&self, relative_path: &str) -> Result<PathBuf, String> {
+        let mut path = self.root.clone();
+        for segment in relative_path.split('/').filter(|item| !item.is_empty()) {
+            if segment == ".." || segment == "." || segment.contains('\\') {
+                return Err(format!("远端路径无效：{}", relative_path));
+            }
+            path.push(segment);
+        }
+        Ok(path)
+    }
+
+    fn read_current(path: &Path) -> Result<Option<Vec<u8>>, String> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("读取 {} 失败: {}", path.display(), e)),
+        }
+    }
+}
+
+impl RemoteStore for FolderStore {
+    async fn ensure_dir(&self, dir: &str) -> Result<(), String> {
+        let path = self.resolve(dir)?;
+        fs::create_dir_all(&path).map_err(|e| format!("初始化目录失败 {}: {}", path.display(), e))
+    }
+
+    async fn get(&self, path: &str) -> Result<Option<RemoteFile>, String> {
+        let file_path = self.resolve(path)?;
+        let Some(bytes) = Self::read_current(&file_path)? else {
+            return Ok(None);
+        };
+        let etag = content_etag(&bytes);
+        let text = String::from_utf8(bytes)
+            .map_err(|_| format!("{} 不是有效的 UTF-8 文本", file_path.display()))?;
+        Ok(Some(RemoteFile {
+            path: path.trim_start_matches('/').to_string(),
+            text,
+            etag: Some(etag),
+        }))
+    }
+
+    async fn put(
+        &self,
+        path: &str,
+        body: String,
+        precondition: &PutPrecondition,
+    ) -> Result<PutOutcome, String> {
+        let file_path = self.resolve(path)?;
+        let _guard = self.write_lock.lock().map_err(|e| e.to_string())?;
+
+        let current = Self::read_current(&file_path)?;
+        let satisfied = match precondition {
+            PutPrecondition::None => true,
+            PutPrecondition::IfMatch(etag) => {
+                current.as_deref().map(content_etag).as_deref() == Some(etag.as_str())
+            }
+            PutPrecondition::IfNoneMatch => current.is_none(),
+        };
+        if !satisfied {
+            return Ok(PutOutcome::PreconditionFailed);
+        }
+
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let file_name = file_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let temp_path = file_path.with_file_name(format!(".{}.tmp", file_name));
+        fs::write(&temp_path, body.as_bytes())
+            .map_err(|e| format!("写入 {} 失败: {}", temp_path.display(), e))?;
+        fs::rename(&temp_path, &file_path).map_err(|e| {
+            let _ = fs::remove_file(&temp_path);
+            format!("写入 {} 失败: {}", file_path.display(), e)
+        })?;
+        Ok(PutOutcome::Written)
+    }
+
+    async fn list(&self, dir: &str) -> Result<Vec<RemoteEntry>, String> {
+        let dir = dir.trim_matches('/');
+        let dir_path = self.resolve(dir)?;
+        let entries = match fs::read_dir(&dir_path) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("扫描 {} 目录失败: {}", dir_path.display(), e)),
+        };
+
+        let mut paths = Vec::new();
+        for entry in entries.flatten() {
+            if !entry
+                .file_type()
+                .map(|kind| kind.is_file())
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            if is_ignored_name(&name) {
+                continue;
+            }
+            paths.push(RemoteEntry {
+                path: if dir.is_empty() {
+                    name
+                } else {
+                    format!("{}/{}", dir, name)
+                },
+                modified: entry
+                    .metadata()
+                    .and_then(|meta| meta.modified())
+                    .ok()
+                    .map(DateTime::<Utc>::from),
+            });
+        }
+        paths.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(paths)
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), String> {
+        let file_path = self.resolve(path)?;
+        match fs::remove_file(&file_path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("删除 {} 失败: {}", file_path.display(), e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 进程专属的临时同步目录，析构时删除
+    struct TempFolder(PathBuf);
+
+    impl TempFolder {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "endfield-gacha-folder-{}-{}",
+                std::process::id(),
+                name
+            ));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn store(&self) -> FolderStore {
+            FolderStore::new(self.0.to_str().unwrap(), "/ef/").unwrap()
+        }
+    }
+
+    impl Drop for TempFolder {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn rejects_relative_folder_and_parent_segments() {
+        assert!(FolderStore::new("", "/ef").is_err());
+        assert!(FolderStore::new("relative/dir", "/ef").is_err());
+        let root = std::env::temp_dir();
+        assert!(FolderStore::new(root.to_str().unwrap(), "/ef/../..").is_err());
+
+        let store = FolderStore::new(root.to_str().unwrap(), "/ef").unwrap();
+        assert!(store.resolve("../escape.json").is_err());
+        assert!(store.resolve("history/./a.json").is_err());
+        assert!(store.resolve("a\\..\\b.json").is_err());
+        assert_eq!(
+            store.resolve("/history/a.json").unwrap(),
+            root.join("ef").join("history").join("a.json")
+        );
+    }
+
+    #[tokio::test]
+    async fn put_honours_preconditions_and_replaces_atomically() {
+        let folder = TempFolder::new("put");
+        let store = folder.store();
+
+        let written = store
+            .put(
+                "manifest.json",
+                "{\"v\":1}".into(),
+                &PutPrecondition::IfNoneMatch,
+            )
+            .await
+            .unwrap();
+        assert_eq!(written, PutOutcome::Written);
+        let current = store.get("manifest.json").await.unwrap().unwrap();
+        assert_eq!(current.text, "{\"v\":1}");
+        assert_eq!(current.etag, Some(content_etag(b"{\"v\":1}")));
+
+        let exists = store
+            .put(
+                "manifest.json",
+                "{\"v\":2}".into(),
+                &PutPrecondition::IfNoneMatch,
+            )
+            .await
+            .unwrap();
+        assert_eq!(exists, PutOutcome::PreconditionFailed);
+        let stale = store
+            .put(
+                "manifest.json",
+                "{\"v\":3}".into(),
+                &PutPrecondition::IfMatch(content_etag(b"other")),
+            )
+            .await
+            .unwrap();
+        assert_eq!(stale, PutOutcome::PreconditionFailed);
+        assert_eq!(
+            fs::read_to_string(folder.0.join("ef/manifest.json")).unwrap(),
+            "{\"v\":1}"
+        );
+
+        let replaced = store
+            .put(
+                "manifest.json",
+                "{\"v\":4}".into(),
+                &PutPrecondition::IfMatch(current.etag.unwrap()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(replaced, PutOutcome::Written);
+        let missing = store
+            .put(
+                "history/a.json",
+                "{}".into(),
+                &PutPrecondition::IfMatch(content_etag(b"{}")),
+            )
+            .await
+            .unwrap();
+        assert_eq!(missing, PutOutcome::PreconditionFailed);
+
+        assert_eq!(
+            store.get("manifest.json").await.unwrap().unwrap().text,
+            "{\"v\":4}"
+        );
+        let leftovers = fs::read_dir(folder.0.join("ef"))
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(leftovers, ["manifest.json"]);
+    }
+
+    #[tokio::test]
+    async fn list_skips_hidden_conflict_copies_and_directories() {
+        let folder = TempFolder::new("list");
+        let store = folder.store();
+        assert!(store.list("accounts").await.unwrap().is_empty());
+
+        store.ensure_dir("accounts/nested").await.unwrap();
+        let dir = folder.0.join("ef/accounts");
+        for name in [
+            "b.json",
+            "a.json",
+            ".a.json.tmp",
+            "a.sync-conflict-20250101-120000-ABCDEFG.json",
+            "a (conflicted copy 2025-01-01).json",
+            "a (冲突副本).json",
+        ] {
+            fs::write(dir.join(name), "{}").unwrap();
+        }
+        fs::write(dir.join("nested/c.json"), "{}").unwrap();
+
+        let entries = store.list("/accounts/").await.unwrap();
+        let paths = entries
+            .iter()
+            .map(|entry| entry.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["accounts/a.json", "accounts/b.json"]);
+        assert!(entries.iter().all(|entry| entry.modified.is_some()));
+    }
+
+    #[tokio::test]
+    async fn delete_is_idempotent() {
+        let folder = TempFolder::new("delete");
+        let store = folder.store();
+        store
+            .put("a.json", "{}".into(), &PutPrecondition::None)
+            .await
+            .unwrap();
+
+        store.delete("a.json").await.unwrap();
+        assert!(store.get("a.json").await.unwrap().is_none());
+        store.delete("a.json").await.unwrap();
+        assert!(store.delete("../a.json").await.is_err());
+    }
+}
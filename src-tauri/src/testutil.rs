@@ -0,0 +1,306 @@
+//! 单元测试共用工具：临时 userData 目录、本地 HTTP 桩与按 ETag 校验写入的 WebDAV 替身
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+use serde_json::Value;
+
+static USERDATA_LOCK: Mutex<()> = Mutex::new(());
+
+/// 持有期间其他测试不会读写 userData；异步测试在单线程运行时中跨 await 持有
+pub(crate) struct UserDataGuard(#[allow(dead_code)] MutexGuard<'static, ()>);
+
+/// 将 userData 指向进程专属的临时目录并清空
+pub(crate) fn userdata() -> UserDataGuard {
+    let guard = USERDATA_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let dir = crate::USERDATA_DIR.get_or_init(|| {
+        std::env::temp_dir().join(format!("endfield-gacha-test-{}", process::id()))
+    });
+    let _ = fs::remove_dir_all(dir);
+    fs::create_dir_all(dir).expect("create userData dir");
+    UserDataGuard(guard)
+}
+
+/// userData 目录下的临时文件路径
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    crate::get_userdata_dir().expect("userData dir").join(name)
+}
+
+pub(crate) fn write_config(config: &Value) {
+    let path = crate::get_config_path().expect("config path");
+    fs::write(path, serde_json::to_string_pretty(config).unwrap()).expect("write config");
+}
+
+pub(crate) fn write_record(user_key: &str, record: &Value) {
+    let path = crate::get_record_path(user_key).expect("record path");
+    fs::write(path, serde_json::to_string_pretty(record).unwrap()).expect("write record");
+}
+
+pub(crate) fn read_record(user_key: &str) -> Value {
+    let path = crate::get_record_path(user_key).expect("record path");
+    serde_json::from_str(&fs::read_to_string(path).expect("read record")).expect("record json")
+}
+
+/// 国服账号：key 为 `<uid>_<roleId>`
+pub(crate) fn hypergryph_user(uid: &str, role_id: &str) -> Value {
+    serde_json::json!({
+        "uid": uid,
+        "provider": "hypergryph",
+        "roleId": { "serverId": "1", "serverName": "China", "nickName": "tester", "roleId": role_id },
+        "source": "log"
+    })
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct StubRequest {
+    pub(crate) method: String,
+    /// 请求行中的路径，含查询串
+    pub(crate) target: String,
+    /// 头名称均为小写
+    pub(crate) headers: HashMap<String, String>,
+    pub(crate) body: Vec<u8>,
+}
+
+impl StubRequest {
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+
+    /// 去掉查询串与末尾 `/`、已解码的路径
+    pub(crate) fn path(&self) -> String {
+        let path = self.target.split('?').next().unwrap_or_default();
+        percent_encoding::percent_decode_str(path.trim_end_matches('/'))
+            .decode_utf8_lossy()
+            .to_string()
+    }
+}
+
+pub(crate) struct StubResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl StubResponse {
+    pub(crate) fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub(crate) fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub(crate) fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+}
+
+/// 每个连接只处理一个请求的 HTTP/1.1 桩，记录收到的全部请求
+pub(crate) struct HttpStub {
+    pub(crate) base_url: String,
+    requests: Arc<Mutex<Vec<StubRequest>>>,
+}
+
+impl HttpStub {
+    pub(crate) fn start<F>(handler: F) -> Self
+    where
+        F: FnMut(&StubRequest) -> StubResponse + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind stub");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let log = requests.clone();
+        let handler = Mutex::new(handler);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                let Some(request) = read_request(&mut BufReader::new(&stream)) else {
+                    continue;
+                };
+                log.lock().unwrap().push(request.clone());
+                let response = (handler.lock().unwrap())(&request);
+                let mut head = format!("HTTP/1.1 {} Stub\r\n", response.status);
+                for (name, value) in response.headers.iter() {
+                    head.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                head.push_str(&format!(
+                    "Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    response.body.len()
+                ));
+                let _ = stream.write_all(head.as_bytes());
+                let _ = stream.write_all(&response.body);
+            }
+        });
+        Self { base_url, requests }
+    }
+
+    pub(crate) fn requests(&self) -> Vec<StubRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn read_request<R: BufRead>(reader: &mut R) -> Option<StubRequest> {
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?.to_string();
+    let mut headers = HashMap::new();
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).ok()?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    let length = headers
+        .get("content-length")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body).ok()?;
+    Some(StubRequest {
+        method,
+        target,
+        headers,
+        body,
+    })
+}
+
+/// 内存中的 WebDAV 服务：PUT 按 If-Match / If-None-Match 做强比较，不满足时返回 412
+#[derive(Default)]
+pub(crate) struct DavState {
+    /// 路径 -> (内容, ETag 序号, Last-Modified)
+    pub(crate) files: BTreeMap<String, (String, u64, String)>,
+    pub(crate) dirs: BTreeSet<String>,
+    counter: u64,
+    /// 返回弱 ETag（W/"n"），模拟部分网盘与反向代理
+    pub(crate) weak_etags: bool,
+    /// 某路径被 GET 后立即替换为给定内容，模拟其他设备在读写之间写入
+    pub(crate) replace_after_get: HashMap<String, String>,
+}
+
+impl DavState {
+    fn etag(&self, tag: u64) -> String {
+        if self.weak_etags {
+            format!("W/\"{}\"", tag)
+        } else {
+            format!("\"{}\"", tag)
+        }
+    }
+
+    pub(crate) fn write(&mut self, path: &str, body: String) {
+        self.write_at(path, body, "Mon, 19 Oct 2026 00:00:00 GMT");
+    }
+
+    pub(crate) fn write_at(&mut self, path: &str, body: String, modified: &str) {
+        self.counter += 1;
+        self.files
+            .insert(path.to_string(), (body, self.counter, modified.to_string()));
+    }
+
+    pub(crate) fn json(&self, path: &str) -> Option<Value> {
+        self.files
+            .get(path)
+            .map(|(body, _, _)| serde_json::from_str(body).expect("remote json"))
+    }
+
+    pub(crate) fn handle(&mut self, request: &StubRequest) -> StubResponse {
+        let path = request.path();
+        match request.method.as_str() {
+            "PROPFIND" => self.propfind(&path, request.header("depth") == Some("1")),
+            "MKCOL" => {
+                self.dirs.insert(path);
+                StubResponse::new(201)
+            }
+            "GET" => {
+                let Some((body, tag, _)) = self.files.get(&path).cloned() else {
+                    return StubResponse::new(404);
+                };
+                if let Some(next) = self.replace_after_get.remove(&path) {
+                    self.write(&path, next);
+                }
+                StubResponse::new(200)
+                    .header("ETag", &self.etag(tag))
+                    .body(body)
+            }
+            "PUT" => {
+                let current = self
+                    .files
+                    .get(&path)
+                    .map(|(_, tag, _)| format!("\"{}\"", tag));
+                let failed = match (request.header("if-match"), request.header("if-none-match")) {
+                    (Some(expected), _) => current.as_deref() != Some(expected),
+                    (None, Some(_)) => current.is_some(),
+                    (None, None) => false,
+                };
+                if failed {
+                    return StubResponse::new(412);
+                }
+                self.write(&path, String::from_utf8_lossy(&request.body).to_string());
+                StubResponse::new(201).header("ETag", &self.etag(self.counter))
+            }
+            "DELETE" => {
+                self.files.remove(&path);
+                StubResponse::new(204)
+            }
+            _ => StubResponse::new(405),
+        }
+    }
+
+    fn propfind(&self, path: &str, children: bool) -> StubResponse {
+        if !self.dirs.contains(path) && !self.files.contains_key(path) {
+            return StubResponse::new(404);
+        }
+        let mut xml = String::from(r#"<?xml version="1.0"?><d:multistatus xmlns:d="DAV:">"#);
+        xml.push_str(&format!(
+            "<d:response><d:href>{}/</d:href><d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+            path
+        ));
+        if children {
+            let prefix = format!("{}/", path);
+            for (file, (body, tag, modified)) in self.files.iter() {
+                if file.starts_with(&prefix) && !file[prefix.len()..].contains('/') {
+                    xml.push_str(&format!(
+                        "<d:response><d:href>{}</d:href><d:propstat><d:prop><d:resourcetype/><d:getetag>{}</d:getetag><d:getcontentlength>{}</d:getcontentlength><d:getlastmodified>{}</d:getlastmodified></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+                        file,
+                        self.etag(*tag),
+                        body.len(),
+                        modified
+                    ));
+                }
+            }
+        }
+        xml.push_str("</d:multistatus>");
+        StubResponse::new(207)
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(xml)
+    }
+}
+
+pub(crate) fn dav_server() -> (HttpStub, Arc<Mutex<DavState>>) {
+    let state = Arc::new(Mutex::new(DavState::default()));
+    let shared = state.clone();
+    let stub = HttpStub::start(move |request| shared.lock().unwrap().handle(request));
+    (stub, state)
+}
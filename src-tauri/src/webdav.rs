@@ -1,22 +1,36 @@
+use crate::conflict::{apply_resolutions, clear_conflict_set, prepare_resolutions};
+use crate::crypto::{parse_envelope, parse_meta, BundleCipher, EncryptionMeta, StoredKey};
+use crate::davauth::{validate_credentials, DavAuthenticator, WebDavAuthKind, WebDavHeader};
+use crate::network::{build_sync_client, describe_request_error, NetworkConfigData};
+use crate::propfind::{href_to_path, parse_multistatus, DavResource};
+use crate::remote::{
+    PutOutcome, PutPrecondition, RemoteBackend, RemoteBackendKind, RemoteEntry, RemoteFile,
+    RemoteStore,
+};
+use crate::s3::{S3ConfigData, S3Store};
+use crate::schema::{ensure_valid, CharacterPools, SchemaKind, WeaponPools};
+use crate::syncfolder::FolderStore;
 use crate::{get_config_path, get_record_path, get_userdata_dir};
-use chrono::{SecondsFormat, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE, ETAG, IF_MATCH, IF_NONE_MATCH};
 use reqwest::{Client, Method, StatusCode};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Map, Value};
 use sha2::{Digest, Sha256};
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
-use std::time::Duration;
+use std::path::PathBuf;
 use tauri::command;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
-struct AppConfigData {
+pub(crate) struct AppConfigData {
     #[serde(default)]
-    users: Vec<AppUser>,
+    pub(crate) users: Vec<AppUser>,
     #[serde(default)]
-    current_user: String,
+    pub(crate) current_user: String,
     #[serde(default)]
     theme: String,
     #[serde(default)]
@@ -25,64 +39,142 @@ struct AppConfigData {
     webdav: WebDavConfigData,
     #[serde(default)]
     webdav_state: HashMap<String, WebDavStateItem>,
+    /// 所有出站 HTTP 请求共用的 TLS、代理与超时设置
+    #[serde(default)]
+    network: NetworkConfigData,
+}
+
+impl AppConfigData {
+    pub(crate) fn theme(&self) -> &str {
+        &self.theme
+    }
+
+    pub(crate) fn network(&self) -> &NetworkConfigData {
+        &self.network
+    }
+
+    /// 清除全部同步凭据：WebDAV 密码、加密密钥、Bearer 令牌、自定义请求头的值、S3 密钥与代理密码
+    pub(crate) fn clear_secrets(&mut self) {
+        self.clear_encryption_key();
+        self.network.clear_proxy_password();
+        self.webdav.password.clear();
+        self.webdav.bearer_token.clear();
+        self.webdav.s3.secret_access_key.clear();
+        for header in self.webdav.extra_headers.iter_mut() {
+            header.value.clear();
+        }
+    }
+
+    /// 派生密钥与旧版明文口令只属于本机，导出归档时总是清除
+    pub(crate) fn clear_encryption_key(&mut self) {
+        self.webdav.encryption_key = None;
+        self.webdav.encryption_passphrase.clear();
+    }
+
+    /// 本地尚未配置 WebDAV 时沿用导入配置中的设置
+    pub(crate) fn adopt_webdav_from(&mut self, other: &AppConfigData) {
+        if !self.webdav.has_target() && other.webdav.has_target() {
+            self.webdav = other.webdav.clone();
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
-struct AppUser {
+pub(crate) struct AppUser {
     #[serde(default)]
-    key: String,
+    pub(crate) key: String,
     #[serde(default)]
-    uid: String,
+    pub(crate) uid: String,
     #[serde(default)]
-    token: String,
+    pub(crate) token: String,
     #[serde(default)]
-    provider: String,
+    pub(crate) provider: String,
     #[serde(default, rename = "roleId")]
-    role_id: Option<UserRoleMeta>,
+    pub(crate) role_id: Option<UserRoleMeta>,
     #[serde(default)]
-    source: String,
+    pub(crate) source: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(rename_all = "camelCase")]
-struct UserRoleMeta {
+pub(crate) struct UserRoleMeta {
     #[serde(default)]
-    server_id: String,
+    pub(crate) server_id: String,
     #[serde(default)]
-    server_name: String,
+    pub(crate) server_name: String,
     #[serde(default)]
-    nick_name: String,
+    pub(crate) nick_name: String,
     #[serde(default)]
-    role_id: String,
+    pub(crate) role_id: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct WebDavConfigData {
+    /// 同步后端：WebDAV、本地/同步盘目录或 S3 兼容存储
+    #[serde(default)]
+    backend: RemoteBackendKind,
     #[serde(default)]
     base_url: String,
     #[serde(default)]
     username: String,
     #[serde(default)]
     password: String,
+    #[serde(default)]
+    auth_kind: WebDavAuthKind,
+    #[serde(default)]
+    bearer_token: String,
+    /// 附加到每个请求上的静态请求头
+    #[serde(default)]
+    extra_headers: Vec<WebDavHeader>,
+    /// 本地目录后端的根目录（绝对路径），basePath 位于其下
+    #[serde(default)]
+    folder_path: String,
+    #[serde(default)]
+    s3: S3ConfigData,
     #[serde(default = "default_base_path")]
     base_path: String,
     #[serde(default)]
     auto_sync: bool,
     #[serde(default = "default_silent_auto_sync")]
     silent_auto_sync: bool,
+    /// 端到端加密密钥（由口令与远端盐派生）；为空表示远端明文存储
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encryption_key: Option<StoredKey>,
+    /// 旧版配置明文保存的口令，仅用于读取；解锁成功后换成 encryption_key
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    encryption_passphrase: String,
 }
 
 impl Default for WebDavConfigData {
     fn default() -> Self {
         Self {
+            backend: RemoteBackendKind::default(),
             base_url: String::new(),
             username: String::new(),
             password: String::new(),
+            auth_kind: WebDavAuthKind::default(),
+            bearer_token: String::new(),
+            extra_headers: Vec::new(),
+            folder_path: String::new(),
+            s3: S3ConfigData::default(),
             base_path: default_base_path(),
             auto_sync: false,
             silent_auto_sync: default_silent_auto_sync(),
+            encryption_key: None,
+            encryption_passphrase: String::new(),
+        }
+    }
+}
+
+impl WebDavConfigData {
+    /// 是否已填写所选后端的远端位置
+    fn has_target(&self) -> bool {
+        match self.backend {
+            RemoteBackendKind::Webdav => !self.base_url.trim().is_empty(),
+            RemoteBackendKind::Folder => !self.folder_path.trim().is_empty(),
+            RemoteBackendKind::S3 => !self.s3.endpoint.trim().is_empty(),
         }
     }
 }
@@ -96,48 +188,66 @@ struct WebDavStateItem {
     last_remote_hash: String,
     #[serde(default)]
     last_sync_at: String,
+    /// 本设备从远端删除该账号的时间；非空时停止同步，直到显式重新上传
+    #[serde(default)]
+    deleted_at: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-struct AccountBundle {
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+pub(crate) struct AccountBundle {
     #[serde(default)]
-    schema_version: u32,
+    pub(crate) schema_version: u32,
     #[serde(default)]
-    account: BundleAccount,
+    pub(crate) account: BundleAccount,
     #[serde(default, rename = "updatedAt")]
-    updated_at: String,
+    pub(crate) updated_at: String,
     #[serde(default)]
-    character_max_seqid: String,
+    pub(crate) character_max_seqid: String,
     #[serde(default)]
-    weapon_max_seqid: String,
+    pub(crate) weapon_max_seqid: String,
     #[serde(default)]
-    character: Value,
+    #[schemars(with = "CharacterPools")]
+    pub(crate) character: Value,
     #[serde(default)]
-    weapon: Value,
+    #[schemars(with = "WeaponPools")]
+    pub(crate) weapon: Value,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(rename_all = "camelCase")]
-struct BundleAccount {
+pub(crate) struct BundleAccount {
     #[serde(default)]
-    key: String,
+    pub(crate) key: String,
     #[serde(default)]
-    provider: String,
+    pub(crate) provider: String,
     #[serde(default)]
-    uid: String,
+    pub(crate) uid: String,
     #[serde(default, rename = "roleId")]
-    role_id: UserRoleMeta,
+    pub(crate) role_id: UserRoleMeta,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-struct ManifestFile {
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+pub(crate) struct ManifestFile {
     #[serde(default)]
     schema_version: u32,
     #[serde(default)]
     accounts: BTreeMap<String, ManifestEntry>,
+    /// 已从远端删除的账号，其他设备据此停止同步
+    #[serde(default)]
+    tombstones: BTreeMap<String, ManifestTombstone>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct ManifestTombstone {
+    #[serde(default)]
+    deleted_at: String,
+    /// 删除前最后一个版本的哈希
+    #[serde(default)]
+    content_hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct ManifestEntry {
     #[serde(default)]
@@ -146,6 +256,23 @@ struct ManifestEntry {
     updated_at: String,
     #[serde(default)]
     content_hash: String,
+    /// 远端保留的历史版本，新版本在前
+    #[serde(default)]
+    history: Vec<ManifestVersion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct ManifestVersion {
+    /// history/<key>/<时间戳>.json
+    #[serde(default)]
+    path: String,
+    #[serde(default)]
+    updated_at: String,
+    #[serde(default)]
+    content_hash: String,
+    #[serde(default)]
+    saved_at: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -184,12 +311,44 @@ enum ManifestLoadState {
     Invalid,
 }
 
-struct WebDavClient {
+const MAX_WRITE_ATTEMPTS: usize = 3;
+
+/// 每个账号在远端保留的历史版本数
+const HISTORY_LIMIT: usize = 10;
+
+/// 清理孤立文件时跳过最近修改的文件，其他设备可能已写入账号文件但尚未更新 manifest
+const PRUNE_GRACE_HOURS: i64 = 24;
+
+/// 远端加密参数文件，始终明文
+const ENCRYPTION_META_PATH: &str = "encryption.json";
+const PASSPHRASE_REQUIRED_ERROR: &str = "远端数据已加密，请先输入加密口令";
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?><d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype /><d:getetag /><d:getcontentlength /><d:getlastmodified /></d:prop></d:propfind>"#;
+
+fn propfind_headers(depth: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(depth) {
+        headers.insert("Depth", value);
+    }
+    headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("application/xml; charset=utf-8"),
+    );
+    headers
+}
+
+/// WebDAV 传输层，实现 `RemoteStore`
+pub(crate) struct WebDavClient {
     client: Client,
     base_url: String,
     base_path: String,
-    username: String,
-    password: String,
+    auth: DavAuthenticator,
+}
+
+/// 同步引擎使用的远端：任一后端加上可选的端到端加密
+struct SyncClient {
+    store: RemoteBackend,
+    cipher: Option<BundleCipher>,
 }
 
 fn default_base_path() -> String {
@@ -204,7 +363,7 @@ fn normalize_string(value: &str) -> String {
     value.trim().to_string()
 }
 
-fn normalize_provider(value: &str) -> Result<String, String> {
+pub(crate) fn normalize_provider(value: &str) -> Result<String, String> {
     let provider = normalize_string(value);
     match provider.as_str() {
         "hypergryph" => Ok("hypergryph".into()),
@@ -232,11 +391,11 @@ fn normalize_base_path(value: &str) -> String {
     path
 }
 
-fn now_iso_string() -> String {
+pub(crate) fn now_iso_string() -> String {
     Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
 }
 
-fn get_user_key(user: &AppUser) -> String {
+pub(crate) fn get_user_key(user: &AppUser) -> String {
     let key = normalize_string(&user.key);
     if !key.is_empty() {
         return key;
@@ -253,7 +412,7 @@ fn get_user_key(user: &AppUser) -> String {
     uid
 }
 
-fn normalize_user(user: &mut AppUser) -> Result<(), String> {
+pub(crate) fn normalize_user(user: &mut AppUser) -> Result<(), String> {
     user.uid = normalize_string(&user.uid);
     user.provider = normalize_provider(&user.provider)?;
     if user.source.trim().is_empty() {
@@ -279,16 +438,19 @@ fn normalize_config(config: &mut AppConfigData) -> Result<(), String> {
     }
     config.webdav.base_url = normalize_string(&config.webdav.base_url);
     config.webdav.username = normalize_string(&config.webdav.username);
+    config.webdav.folder_path = normalize_string(&config.webdav.folder_path);
     config.webdav.base_path = normalize_base_path(&config.webdav.base_path);
+    config.network.normalize();
     for state in config.webdav_state.values_mut() {
         state.last_local_hash = normalize_string(&state.last_local_hash);
         state.last_remote_hash = normalize_string(&state.last_remote_hash);
         state.last_sync_at = normalize_string(&state.last_sync_at);
+        state.deleted_at = normalize_string(&state.deleted_at);
     }
     Ok(())
 }
 
-fn load_config_data() -> Result<AppConfigData, String> {
+pub(crate) fn load_config_data() -> Result<AppConfigData, String> {
     let path = get_config_path()?;
     if !path.exists() {
         return Ok(AppConfigData::default());
@@ -300,13 +462,13 @@ fn load_config_data() -> Result<AppConfigData, String> {
     Ok(config)
 }
 
-fn save_config_data(config: &AppConfigData) -> Result<(), String> {
+pub(crate) fn save_config_data(config: &AppConfigData) -> Result<(), String> {
     let path = get_config_path()?;
     let json = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
     fs::write(path, json).map_err(|e| e.to_string())
 }
 
-fn load_local_record_value(user_key: &str) -> Result<Value, String> {
+pub(crate) fn load_local_record_value(user_key: &str) -> Result<Value, String> {
     let path = get_record_path(user_key)?;
     if !path.exists() {
         return Ok(json!({
@@ -331,7 +493,10 @@ fn load_local_record_value(user_key: &str) -> Result<Value, String> {
     }
 }
 
-fn write_bundle_to_local_record(user_key: &str, bundle: &AccountBundle) -> Result<(), String> {
+pub(crate) fn write_bundle_to_local_record(
+    user_key: &str,
+    bundle: &AccountBundle,
+) -> Result<(), String> {
     let path = get_record_path(user_key)?;
     let json = serde_json::to_string_pretty(bundle).map_err(|e| e.to_string())?;
     fs::write(path, json).map_err(|e| e.to_string())
@@ -341,18 +506,19 @@ fn default_manifest() -> ManifestFile {
     ManifestFile {
         schema_version: 1,
         accounts: BTreeMap::new(),
+        tombstones: BTreeMap::new(),
     }
 }
 
-fn build_bundle_account(user: &AppUser) -> Result<BundleAccount, String> {
+pub(crate) fn build_bundle_account(user: &AppUser) -> Result<BundleAccount, String> {
     let role = user
         .role_id
         .clone()
-        .ok_or_else(|| "当前账号缺少 roleId，无法写入 WebDAV".to_string())?;
+        .ok_or_else(|| "当前账号缺少 roleId，无法写入远端".to_string())?;
     let role_id = normalize_string(&role.role_id);
     let provider = normalize_provider(&user.provider)?;
     if user.key.trim().is_empty() || user.uid.trim().is_empty() || role_id.is_empty() {
-        return Err("当前账号缺少关键字段，无法写入 WebDAV".into());
+        return Err("当前账号缺少关键字段，无法写入远端".into());
     }
 
     Ok(BundleAccount {
@@ -375,7 +541,7 @@ fn normalize_record_object(value: &Value) -> Value {
     json!({})
 }
 
-fn build_local_bundle(user: &AppUser) -> Result<AccountBundle, String> {
+pub(crate) fn build_local_bundle(user: &AppUser) -> Result<AccountBundle, String> {
     let full_data = load_local_record_value(&user.key)?;
     let character = normalize_record_object(full_data.get("character").unwrap_or(&json!({})));
     let weapon = normalize_record_object(full_data.get("weapon").unwrap_or(&json!({})));
@@ -650,7 +816,7 @@ fn record_score(value: &Value) -> usize {
         .unwrap_or(0)
 }
 
-fn normalize_record_for_conflict_compare(value: &Value) -> Value {
+pub(crate) fn normalize_record_for_conflict_compare(value: &Value) -> Value {
     let Some(obj) = value.as_object() else {
         return value.clone();
     };
@@ -679,11 +845,26 @@ fn pick_richer_record(current: &Value, candidate: &Value) -> Value {
     }
 }
 
-fn merge_record_lists(local: &[Value], remote: &[Value]) -> Result<Vec<Value>, String> {
+/// 同一 seqId 字段不一致的记录，保留先出现（本地）的一侧
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RecordConflict {
+    pub(crate) pool_key: String,
+    pub(crate) seq_id: String,
+    pub(crate) local: Value,
+    pub(crate) remote: Value,
+}
+
+fn merge_record_lists_reporting(
+    pool_key: &str,
+    local: &[Value],
+    remote: &[Value],
+) -> (Vec<Value>, Vec<RecordConflict>) {
     let mut merged: Vec<(String, Value)> = Vec::new();
     let mut seq_index: HashMap<String, usize> = HashMap::new();
+    let mut conflicts = Vec::new();
 
-    let mut push_item = |item: &Value| -> Result<(), String> {
+    let mut push_item = |item: &Value| {
         let seq_id = item.get("seqId").and_then(value_to_seqid);
         if let Some(seq_id) = seq_id {
             if let Some(index) = seq_index.get(&seq_id).copied() {
@@ -691,7 +872,13 @@ fn merge_record_lists(local: &[Value], remote: &[Value]) -> Result<Vec<Value>, S
                 let current_normalized = normalize_record_for_conflict_compare(&current);
                 let candidate_normalized = normalize_record_for_conflict_compare(item);
                 if current_normalized != candidate_normalized {
-                    return Err(format!("抽卡记录 seqId ({}) 存在字段差异", seq_id));
+                    conflicts.push(RecordConflict {
+                        pool_key: pool_key.to_string(),
+                        seq_id,
+                        local: current,
+                        remote: item.clone(),
+                    });
+                    return;
                 }
                 merged[index].1 = pick_richer_record(&current, item);
             } else {
@@ -702,14 +889,13 @@ fn merge_record_lists(local: &[Value], remote: &[Value]) -> Result<Vec<Value>, S
             let synthetic_key = format!("__missing__{}", merged.len());
             merged.push((synthetic_key, item.clone()));
         }
-        Ok(())
     };
 
     for item in local {
-        push_item(item)?;
+        push_item(item);
     }
     for item in remote {
-        push_item(item)?;
+        push_item(item);
     }
 
     merged.sort_by(|a, b| {
@@ -724,10 +910,17 @@ fn merge_record_lists(local: &[Value], remote: &[Value]) -> Result<Vec<Value>, S
         compare_seqid(&b_seq, &a_seq)
     });
 
-    Ok(merged.into_iter().map(|(_, value)| value).collect())
+    (
+        merged.into_iter().map(|(_, value)| value).collect(),
+        conflicts,
+    )
 }
 
-fn merge_record_maps(local: &Value, remote: &Value) -> Result<Value, String> {
+/// 与 merge_record_maps 相同的并集合并，但遇到字段差异时不中断，而是收集冲突
+pub(crate) fn merge_record_maps_reporting(
+    local: &Value,
+    remote: &Value,
+) -> (Value, Vec<RecordConflict>) {
     let mut keys = BTreeSet::new();
     if let Some(obj) = local.as_object() {
         keys.extend(obj.keys().cloned());
@@ -737,6 +930,7 @@ fn merge_record_maps(local: &Value, remote: &Value) -> Result<Value, String> {
     }
 
     let mut result = Map::new();
+    let mut conflicts = Vec::new();
     for key in keys {
         let local_items = local
             .get(&key)
@@ -748,10 +942,20 @@ fn merge_record_maps(local: &Value, remote: &Value) -> Result<Value, String> {
             .and_then(|value| value.as_array())
             .cloned()
             .unwrap_or_default();
-        let merged = merge_record_lists(&local_items, &remote_items)?;
+        let (merged, pool_conflicts) =
+            merge_record_lists_reporting(&key, &local_items, &remote_items);
+        conflicts.extend(pool_conflicts);
         result.insert(key, Value::Array(merged));
     }
-    Ok(Value::Object(result))
+    (Value::Object(result), conflicts)
+}
+
+fn merge_record_maps(local: &Value, remote: &Value) -> Result<Value, String> {
+    let (merged, conflicts) = merge_record_maps_reporting(local, remote);
+    if let Some(conflict) = conflicts.first() {
+        return Err(format!("抽卡记录 seqId ({}) 存在字段差异", conflict.seq_id));
+    }
+    Ok(merged)
 }
 
 fn choose_recent_non_empty(primary: &str, secondary: &str) -> String {
@@ -807,12 +1011,6 @@ fn validate_identity(local: &AccountBundle, remote: &AccountBundle) -> Result<()
     Ok(())
 }
 
-fn validate_record_conflicts(local: &AccountBundle, remote: &AccountBundle) -> Result<(), String> {
-    merge_record_maps(&local.character, &remote.character)?;
-    merge_record_maps(&local.weapon, &remote.weapon)?;
-    Ok(())
-}
-
 fn merge_bundles(local: &AccountBundle, remote: &AccountBundle) -> Result<AccountBundle, String> {
     validate_identity(local, remote)?;
     let character = merge_record_maps(&local.character, &remote.character)?;
@@ -833,18 +1031,28 @@ fn merge_bundles(local: &AccountBundle, remote: &AccountBundle) -> Result<Accoun
     })
 }
 
+pub(crate) fn conflict_dir() -> Result<PathBuf, String> {
+    Ok(get_userdata_dir()?.join("webdavConflicts"))
+}
+
+pub(crate) fn conflict_snapshot_paths(user_key: &str) -> Result<(PathBuf, PathBuf), String> {
+    let dir = conflict_dir()?;
+    Ok((
+        dir.join(format!("{}.local.json", user_key)),
+        dir.join(format!("{}.remote.json", user_key)),
+    ))
+}
+
 fn save_conflict_snapshots(
     user_key: &str,
     local_value: &Value,
     remote_value: &Value,
 ) -> Result<(), String> {
-    let dir = get_userdata_dir()?.join("webdavConflicts");
-    if !dir.exists() {
-        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let (local_path, remote_path) = conflict_snapshot_paths(user_key)?;
+    if let Some(dir) = local_path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
     }
 
-    let local_path = dir.join(format!("{}.local.json", user_key));
-    let remote_path = dir.join(format!("{}.remote.json", user_key));
     let local_json = serde_json::to_string_pretty(local_value).map_err(|e| e.to_string())?;
     let remote_json = serde_json::to_string_pretty(remote_value).map_err(|e| e.to_string())?;
     fs::write(local_path, local_json).map_err(|e| e.to_string())?;
@@ -852,25 +1060,68 @@ fn save_conflict_snapshots(
     Ok(())
 }
 
-fn build_manifest_entry(bundle: &AccountBundle) -> Result<ManifestEntry, String> {
+fn build_manifest_entry(path: &str, bundle: &AccountBundle) -> Result<ManifestEntry, String> {
     Ok(ManifestEntry {
-        path: account_relative_path(&bundle.account.key),
+        path: path.to_string(),
         updated_at: bundle.updated_at.clone(),
         content_hash: bundle_hash(bundle)?,
+        history: Vec::new(),
     })
 }
 
-fn build_manifest_from_bundles(bundles: &[AccountBundle]) -> Result<ManifestFile, String> {
+/// 追加新版本并截断到 HISTORY_LIMIT，返回保留列表与需要删除的文件
+fn append_history(
+    mut history: Vec<ManifestVersion>,
+    version: Option<&ManifestVersion>,
+) -> (Vec<ManifestVersion>, Vec<String>) {
+    if let Some(version) = version {
+        history.retain(|item| item.path != version.path);
+        history.insert(0, version.clone());
+    }
+    let pruned = if history.len() > HISTORY_LIMIT {
+        history
+            .split_off(HISTORY_LIMIT)
+            .into_iter()
+            .map(|item| item.path)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    (history, pruned)
+}
+
+/// 重建 manifest 时沿用旧 manifest 中的历史版本列表与删除标记
+fn carry_over_history(manifest: &mut ManifestFile, previous: &ManifestFile) {
+    for (key, entry) in manifest.accounts.iter_mut() {
+        if let Some(old) = previous.accounts.get(key) {
+            entry.history = old.history.clone();
+        }
+    }
+    manifest.tombstones = previous.tombstones.clone();
+}
+
+fn manifest_precondition(state: &ManifestLoadState, etag: Option<String>) -> PutPrecondition {
+    match (state, etag) {
+        (ManifestLoadState::Missing, _) => PutPrecondition::IfNoneMatch,
+        (_, etag) => PutPrecondition::if_match(etag),
+    }
+}
+
+/// bundles 为 (远端路径, 账号文件)
+fn build_manifest_from_bundles(
+    bundles: &[(String, AccountBundle)],
+) -> Result<ManifestFile, String> {
     let mut manifest = default_manifest();
-    for bundle in bundles {
-        manifest
-            .accounts
-            .insert(bundle.account.key.clone(), build_manifest_entry(bundle)?);
+    for (path, bundle) in bundles {
+        manifest.accounts.insert(
+            bundle.account.key.clone(),
+            build_manifest_entry(path, bundle)?,
+        );
     }
     Ok(manifest)
 }
 
-fn upsert_user_from_bundle(
+pub(crate) fn upsert_user_from_bundle(
     config: &mut AppConfigData,
     bundle: &AccountBundle,
     restored: bool,
@@ -921,12 +1172,24 @@ fn upsert_user_from_bundle(
     Ok(())
 }
 
-fn account_relative_path(user_key: &str) -> String {
-    format!("accounts/{}.json", user_key)
+/// name 为账号 key，启用加密时为 `BundleCipher::opaque_name`
+fn account_relative_path(name: &str) -> String {
+    format!("accounts/{}.json", name)
+}
+
+fn history_dir_relative(name: &str) -> String {
+    format!("history/{}", name)
+}
+
+/// accounts/<name>.json 中的 name
+fn account_file_name(path: &str) -> Option<String> {
+    path.strip_prefix("accounts/")
+        .and_then(|name| name.strip_suffix(".json"))
+        .map(String::from)
 }
 
 impl WebDavClient {
-    fn new(config: &WebDavConfigData) -> Result<Self, String> {
+    fn new(config: &WebDavConfigData, network: &NetworkConfigData) -> Result<Self, String> {
         let base_url = normalize_string(&config.base_url)
             .trim_end_matches('/')
             .to_string();
@@ -934,23 +1197,31 @@ impl WebDavClient {
         let password = config.password.clone();
         let base_path = normalize_base_path(&config.base_path);
 
-        if base_url.is_empty() || username.is_empty() || password.is_empty() || base_path.is_empty()
-        {
+        if base_url.is_empty() || base_path.is_empty() {
             return Err("请先填写完整的 WebDAV 配置".into());
         }
+        validate_credentials(
+            config.auth_kind,
+            &username,
+            &password,
+            &config.bearer_token,
+            &config.extra_headers,
+        )?;
+        let auth = DavAuthenticator::new(
+            config.auth_kind,
+            username,
+            password,
+            config.bearer_token.clone(),
+            &config.extra_headers,
+        )?;
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(20))
-            .user_agent("endfield-gacha/webdav")
-            .build()
-            .map_err(|e| e.to_string())?;
+        let client = build_sync_client(network, "endfield-gacha/webdav")?;
 
         Ok(Self {
             client,
             base_url,
             base_path,
-            username,
-            password,
+            auth,
         })
     }
 
@@ -967,27 +1238,54 @@ impl WebDavClient {
         }
     }
 
+    /// 所有请求的统一出口：附加认证与自定义请求头，Digest 质询时重试一次
+    async fn execute(
+        &self,
+        method: Method,
+        url: String,
+        headers: HeaderMap,
+        body: Option<&str>,
+    ) -> Result<reqwest::Response, String> {
+        let mut retried = false;
+        loop {
+            let mut request_headers = self.auth.headers_for(&method, &url)?;
+            request_headers.extend(headers.clone());
+            let mut request = self
+                .client
+                .request(method.clone(), url.as_str())
+                .headers(request_headers);
+            if let Some(body) = body {
+                request = request.body(body.to_string());
+            }
+            let response = request
+                .send()
+                .await
+                .map_err(|e| describe_request_error(&e))?;
+            if response.status() == StatusCode::UNAUTHORIZED
+                && !retried
+                && self.auth.accept_challenge(response.headers())?
+            {
+                retried = true;
+                continue;
+            }
+            return Ok(response);
+        }
+    }
+
     async fn send(&self, method: Method, url: String) -> Result<reqwest::Response, String> {
-        self.client
-            .request(method, url)
-            .basic_auth(&self.username, Some(&self.password))
-            .send()
-            .await
-            .map_err(|e| e.to_string())
+        self.execute(method, url, HeaderMap::new(), None).await
     }
 
     async fn propfind_exists_absolute(&self, absolute_path: &str) -> Result<bool, String> {
         let method = Method::from_bytes(b"PROPFIND").map_err(|e| e.to_string())?;
         let response = self
-            .client
-            .request(method, self.url_for_absolute_path(absolute_path))
-            .basic_auth(&self.username, Some(&self.password))
-            .header("Depth", "0")
-            .header("Content-Type", "application/xml; charset=utf-8")
-            .body(r#"<?xml version="1.0" encoding="utf-8"?><d:propfind xmlns:d="DAV:"><d:prop><d:displayname /></d:prop></d:propfind>"#)
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
+            .execute(
+                method,
+                self.url_for_absolute_path(absolute_path),
+                propfind_headers("0"),
+                Some(r#"<?xml version="1.0" encoding="utf-8"?><d:propfind xmlns:d="DAV:"><d:prop><d:displayname /></d:prop></d:propfind>"#),
+            )
+            .await?;
 
         match response.status() {
             StatusCode::OK | StatusCode::MULTI_STATUS => Ok(true),
@@ -1003,12 +1301,8 @@ impl WebDavClient {
 
         let method = Method::from_bytes(b"MKCOL").map_err(|e| e.to_string())?;
         let response = self
-            .client
-            .request(method, self.url_for_absolute_path(absolute_path))
-            .basic_auth(&self.username, Some(&self.password))
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
+            .send(method, self.url_for_absolute_path(absolute_path))
+            .await?;
 
         match response.status() {
             StatusCode::CREATED
@@ -1024,76 +1318,355 @@ impl WebDavClient {
         }
     }
 
-    async fn ensure_structure(&self) -> Result<(), String> {
-        let mut current = String::new();
-        for segment in self
-            .base_path
-            .trim_start_matches('/')
-            .split('/')
-            .filter(|item| !item.is_empty())
-        {
+    /// 目录不存在时返回 None
+    async fn propfind_relative(
+        &self,
+        relative_path: &str,
+        depth: &str,
+    ) -> Result<Option<Vec<DavResource>>, String> {
+        let method = Method::from_bytes(b"PROPFIND").map_err(|e| e.to_string())?;
+        let response = self
+            .execute(
+                method,
+                self.url_for_relative(relative_path),
+                propfind_headers(depth),
+                Some(PROPFIND_BODY),
+            )
+            .await?;
+
+        match response.status() {
+            StatusCode::OK | StatusCode::MULTI_STATUS => {
+                let xml = response.text().await.map_err(|e| e.to_string())?;
+                parse_multistatus(&xml).map(Some)
+            }
+            StatusCode::NOT_FOUND => Ok(None),
+            status => Err(format!("扫描 {} 目录失败: {}", relative_path, status)),
+        }
+    }
+}
+
+impl RemoteStore for WebDavClient {
+    /// 逐级创建集合；dir 为空时创建 base_path 的每一级
+    async fn ensure_dir(&self, dir: &str) -> Result<(), String> {
+        let (mut current, segments) = match dir.trim_matches('/') {
+            "" => (String::new(), self.base_path.trim_start_matches('/')),
+            dir => (self.base_path.clone(), dir),
+        };
+        for segment in segments.split('/').filter(|item| !item.is_empty()) {
             current.push('/');
             current.push_str(segment);
             self.ensure_collection_absolute(&current).await?;
         }
-        self.ensure_collection_absolute(&format!("{}/accounts", self.base_path))
-            .await
+        Ok(())
     }
 
-    async fn get_text_relative(&self, relative_path: &str) -> Result<Option<String>, String> {
+    async fn get(&self, path: &str) -> Result<Option<RemoteFile>, String> {
+        let response = self.send(Method::GET, self.url_for_relative(path)).await?;
+        match response.status() {
+            StatusCode::OK => {
+                let etag = response
+                    .headers()
+                    .get(ETAG)
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| value.trim().to_string())
+                    .filter(|value| !value.is_empty());
+                let text = response.text().await.map_err(|e| e.to_string())?;
+                Ok(Some(RemoteFile {
+                    path: path.trim_start_matches('/').to_string(),
+                    text,
+                    etag,
+                }))
+            }
+            StatusCode::NOT_FOUND => Ok(None),
+            status => Err(format!("GET {} 失败: {}", path, status)),
+        }
+    }
+
+    async fn put(
+        &self,
+        path: &str,
+        body: String,
+        precondition: &PutPrecondition,
+    ) -> Result<PutOutcome, String> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/json; charset=utf-8"),
+        );
+        match precondition {
+            PutPrecondition::None => {}
+            PutPrecondition::IfMatch(etag) => {
+                let value = HeaderValue::from_str(etag).map_err(|e| e.to_string())?;
+                headers.insert(IF_MATCH, value);
+            }
+            PutPrecondition::IfNoneMatch => {
+                headers.insert(IF_NONE_MATCH, HeaderValue::from_static("*"));
+            }
+        }
         let response = self
-            .send(Method::GET, self.url_for_relative(relative_path))
+            .execute(
+                Method::PUT,
+                self.url_for_relative(path),
+                headers,
+                Some(&body),
+            )
             .await?;
+
         match response.status() {
-            StatusCode::OK => response.text().await.map(Some).map_err(|e| e.to_string()),
-            StatusCode::NOT_FOUND => Ok(None),
-            status => Err(format!("GET {} 失败: {}", relative_path, status)),
+            StatusCode::OK | StatusCode::CREATED | StatusCode::NO_CONTENT => {
+                Ok(PutOutcome::Written)
+            }
+            StatusCode::PRECONDITION_FAILED => Ok(PutOutcome::PreconditionFailed),
+            status => Err(format!("PUT {} 失败: {}", path, status)),
         }
     }
 
-    async fn put_json_relative<T: Serialize>(
+    async fn list(&self, dir: &str) -> Result<Vec<RemoteEntry>, String> {
+        let dir = dir.trim_matches('/');
+        let Some(resources) = self.propfind_relative(dir, "1").await? else {
+            return Ok(Vec::new());
+        };
+        // href 可能包含 base_url 自带的路径前缀（如 /dav、/remote.php/dav/files/<user>）
+        let prefixes = [
+            format!("{}/", href_to_path(&self.url_for_relative(dir))),
+            format!("{}/{}/", self.base_path.trim_end_matches('/'), dir),
+        ];
+        let mut paths = BTreeMap::new();
+        for resource in resources.iter().filter(|item| !item.is_collection) {
+            let name = prefixes
+                .iter()
+                .find_map(|prefix| resource.path.strip_prefix(prefix.as_str()));
+            if let Some(name) = name.filter(|name| !name.is_empty() && !name.contains('/')) {
+                let modified = resource
+                    .last_modified
+                    .as_deref()
+                    .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+                    .map(|time| time.with_timezone(&Utc));
+                paths.insert(format!("{}/{}", dir, name), modified);
+            }
+        }
+        Ok(paths
+            .into_iter()
+            .map(|(path, modified)| RemoteEntry { path, modified })
+            .collect())
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), String> {
+        let response = self
+            .send(Method::DELETE, self.url_for_relative(path))
+            .await?;
+        match response.status() {
+            StatusCode::OK | StatusCode::NO_CONTENT | StatusCode::NOT_FOUND => Ok(()),
+            status => Err(format!("DELETE {} 失败: {}", path, status)),
+        }
+    }
+}
+
+impl SyncClient {
+    fn new(config: &WebDavConfigData, network: &NetworkConfigData) -> Result<Self, String> {
+        Self::with_cipher(
+            config,
+            network,
+            BundleCipher::with_stored_key(
+                config.encryption_key.clone(),
+                vec![config.encryption_passphrase.clone()],
+            ),
+        )
+    }
+
+    fn with_cipher(
+        config: &WebDavConfigData,
+        network: &NetworkConfigData,
+        cipher: Option<BundleCipher>,
+    ) -> Result<Self, String> {
+        let base_path = normalize_base_path(&config.base_path);
+        let store = match config.backend {
+            RemoteBackendKind::Webdav => RemoteBackend::WebDav(WebDavClient::new(config, network)?),
+            RemoteBackendKind::Folder => {
+                RemoteBackend::Folder(FolderStore::new(&config.folder_path, &base_path)?)
+            }
+            RemoteBackendKind::S3 => {
+                RemoteBackend::S3(S3Store::new(&config.s3, &base_path, network)?)
+            }
+        };
+        Ok(Self { store, cipher })
+    }
+
+    async fn ensure_structure(&self) -> Result<(), String> {
+        self.ensure_collections().await?;
+        self.verify_encryption().await
+    }
+
+    async fn ensure_collections(&self) -> Result<(), String> {
+        self.store.ensure_dir("").await?;
+        self.store.ensure_dir("accounts").await
+    }
+
+    async fn load_encryption_meta(&self) -> Result<Option<EncryptionMeta>, String> {
+        self.get_file_relative(ENCRYPTION_META_PATH)
+            .await?
+            .map(|file| parse_meta(&file.text))
+            .transpose()
+    }
+
+    /// 远端与本地的加密状态必须一致，避免明文与密文混写
+    async fn verify_encryption(&self) -> Result<(), String> {
+        match (self.load_encryption_meta().await?, self.cipher.as_ref()) {
+            (Some(meta), Some(cipher)) => cipher.unlock(&meta),
+            (Some(_), None) => Err(PASSPHRASE_REQUIRED_ERROR.into()),
+            (None, Some(_)) => Err("远端未启用加密，请重新设置加密口令或清除本地口令".into()),
+            (None, None) => Ok(()),
+        }
+    }
+
+    /// 加密信封解密为明文；未加密的文件原样返回
+    fn open_text(&self, text: String) -> Result<String, String> {
+        let Some(envelope) = parse_envelope(&text) else {
+            return Ok(text);
+        };
+        self.cipher
+            .as_ref()
+            .ok_or_else(|| PASSPHRASE_REQUIRED_ERROR.to_string())?
+            .decrypt(&envelope)
+    }
+
+    /// 账号在远端路径中使用的名称；加密时不暴露账号 key
+    fn remote_name(&self, user_key: &str) -> Result<String, String> {
+        match self.cipher.as_ref() {
+            Some(cipher) => cipher.opaque_name(user_key),
+            None => Ok(user_key.to_string()),
+        }
+    }
+
+    fn account_path(&self, user_key: &str) -> Result<String, String> {
+        Ok(account_relative_path(&self.remote_name(user_key)?))
+    }
+
+    fn history_dir(&self, user_key: &str) -> Result<String, String> {
+        Ok(history_dir_relative(&self.remote_name(user_key)?))
+    }
+
+    fn seal_text(&self, text: String) -> Result<String, String> {
+        match self.cipher.as_ref() {
+            Some(cipher) => cipher.encrypt(&text),
+            None => Ok(text),
+        }
+    }
+
+    async fn get_file_relative(&self, relative_path: &str) -> Result<Option<RemoteFile>, String> {
+        let Some(mut file) = self.store.get(relative_path).await? else {
+            return Ok(None);
+        };
+        file.text = self.open_text(file.text)?;
+        Ok(Some(file))
+    }
+
+    async fn get_text_relative(&self, relative_path: &str) -> Result<Option<String>, String> {
+        Ok(self
+            .get_file_relative(relative_path)
+            .await?
+            .map(|file| file.text))
+    }
+
+    async fn put_json_conditional<T: Serialize>(
         &self,
         relative_path: &str,
         payload: &T,
-    ) -> Result<(), String> {
+        precondition: &PutPrecondition,
+    ) -> Result<PutOutcome, String> {
         let body = serde_json::to_string_pretty(payload).map_err(|e| e.to_string())?;
-        let response = self
-            .client
-            .request(Method::PUT, self.url_for_relative(relative_path))
-            .basic_auth(&self.username, Some(&self.password))
-            .header("Content-Type", "application/json; charset=utf-8")
-            .body(body)
-            .send()
+        self.put_text_conditional(relative_path, self.seal_text(body)?, precondition)
             .await
-            .map_err(|e| e.to_string())?;
+    }
 
-        match response.status() {
-            StatusCode::OK | StatusCode::CREATED | StatusCode::NO_CONTENT => Ok(()),
-            status => Err(format!("PUT {} 失败: {}", relative_path, status)),
+    async fn put_text_conditional(
+        &self,
+        relative_path: &str,
+        body: String,
+        precondition: &PutPrecondition,
+    ) -> Result<PutOutcome, String> {
+        self.store.put(relative_path, body, precondition).await
+    }
+
+    async fn put_json_relative<T: Serialize>(
+        &self,
+        relative_path: &str,
+        payload: &T,
+    ) -> Result<(), String> {
+        match self
+            .put_json_conditional(relative_path, payload, &PutPrecondition::None)
+            .await?
+        {
+            PutOutcome::Written => Ok(()),
+            PutOutcome::PreconditionFailed => Err(format!(
+                "PUT {} 失败: {}",
+                relative_path,
+                StatusCode::PRECONDITION_FAILED
+            )),
         }
     }
 
-    async fn load_manifest_state(&self) -> Result<ManifestLoadState, String> {
-        let Some(text) = self.get_text_relative("manifest.json").await? else {
-            return Ok(ManifestLoadState::Missing);
+    async fn delete_relative(&self, relative_path: &str) -> Result<(), String> {
+        self.store.delete(relative_path).await
+    }
+
+    /// 上传账号文件前按 Schema 校验，避免写入其他客户端无法读取的数据
+    async fn put_bundle(
+        &self,
+        user_key: &str,
+        bundle: &AccountBundle,
+        precondition: &PutPrecondition,
+    ) -> Result<PutOutcome, String> {
+        let payload = bundle_to_remote_value(bundle);
+        ensure_valid(SchemaKind::AccountBundle, &payload)
+            .map_err(|e| format!("账号文件未通过 Schema 校验：{}", e))?;
+        self.put_json_conditional(&self.account_path(user_key)?, &payload, precondition)
+            .await
+    }
+
+    /// 将即将成为当前版本的账号文件另存到 history/<key>/<时间戳>.json
+    async fn put_history_version(&self, bundle: &AccountBundle) -> Result<ManifestVersion, String> {
+        let dir = self.history_dir(&bundle.account.key)?;
+        self.store.ensure_dir(&dir).await?;
+
+        let now = Utc::now();
+        let version = ManifestVersion {
+            path: format!("{}/{}.json", dir, now.format("%Y%m%dT%H%M%S%3fZ")),
+            updated_at: bundle.updated_at.clone(),
+            content_hash: bundle_hash(bundle)?,
+            saved_at: now.to_rfc3339_opts(SecondsFormat::Millis, true),
+        };
+        self.put_json_relative(&version.path, &bundle_to_remote_value(bundle))
+            .await?;
+        Ok(version)
+    }
+
+    async fn load_manifest_with_etag(&self) -> Result<(ManifestLoadState, Option<String>), String> {
+        let Some(file) = self.get_file_relative("manifest.json").await? else {
+            return Ok((ManifestLoadState::Missing, None));
         };
 
-        match serde_json::from_str::<ManifestFile>(&text) {
+        let state = match serde_json::from_str::<ManifestFile>(&file.text) {
             Ok(mut manifest) => {
                 if manifest.schema_version == 0 {
                     manifest.schema_version = 1;
                 }
-                Ok(ManifestLoadState::Existing(manifest))
+                ManifestLoadState::Existing(manifest)
             }
-            Err(_) => Ok(ManifestLoadState::Invalid),
-        }
+            Err(_) => ManifestLoadState::Invalid,
+        };
+        Ok((state, file.etag))
+    }
+
+    async fn load_manifest_state(&self) -> Result<ManifestLoadState, String> {
+        Ok(self.load_manifest_with_etag().await?.0)
     }
 
-    async fn download_account_text_for_key(
+    async fn download_account_file_for_key(
         &self,
         user_key: &str,
         manifest: Option<&ManifestFile>,
-    ) -> Result<Option<(String, String)>, String> {
+    ) -> Result<Option<RemoteFile>, String> {
         let mut candidate_paths = Vec::new();
         if let Some(manifest) = manifest {
             if let Some(entry) = manifest.accounts.get(user_key) {
@@ -1106,14 +1679,14 @@ impl WebDavClient {
             }
         }
 
-        let default_path = account_relative_path(user_key);
+        let default_path = self.account_path(user_key)?;
         if !candidate_paths.iter().any(|path| path == &default_path) {
             candidate_paths.push(default_path);
         }
 
         for path in candidate_paths {
-            if let Some(text) = self.get_text_relative(&path).await? {
-                return Ok(Some((path, text)));
+            if let Some(file) = self.get_file_relative(&path).await? {
+                return Ok(Some(file));
             }
         }
 
@@ -1121,165 +1694,201 @@ impl WebDavClient {
     }
 
     async fn scan_account_paths(&self) -> Result<Vec<String>, String> {
-        let method = Method::from_bytes(b"PROPFIND").map_err(|e| e.to_string())?;
-        let response = self
-            .client
-            .request(method, self.url_for_absolute_path(&format!("{}/accounts", self.base_path)))
-            .basic_auth(&self.username, Some(&self.password))
-            .header("Depth", "1")
-            .header("Content-Type", "application/xml; charset=utf-8")
-            .body(r#"<?xml version="1.0" encoding="utf-8"?><d:propfind xmlns:d="DAV:"><d:prop><d:displayname /></d:prop></d:propfind>"#)
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
+        self.scan_json_paths("accounts").await
+    }
 
-        match response.status() {
-            StatusCode::OK | StatusCode::MULTI_STATUS => {
-                let xml = response.text().await.map_err(|e| e.to_string())?;
-                let mut paths = BTreeSet::new();
-                for href in extract_href_values(&xml) {
-                    let path = href_to_path(&href);
-                    let base_prefix = format!("{}/", self.base_path.trim_end_matches('/'));
-                    if !path.starts_with(&base_prefix) {
-                        continue;
-                    }
-                    let relative = path[self.base_path.trim_end_matches('/').len() + 1..]
-                        .trim()
-                        .trim_start_matches('/')
-                        .to_string();
-                    if relative.starts_with("accounts/") && relative.ends_with(".json") {
-                        paths.insert(relative);
-                    }
-                }
-                Ok(paths.into_iter().collect())
-            }
-            StatusCode::NOT_FOUND => Ok(Vec::new()),
-            status => Err(format!("扫描 accounts 目录失败: {}", status)),
-        }
+    /// 列出 base_path 下某个目录中的 .json 文件（相对路径）
+    async fn scan_json_paths(&self, dir: &str) -> Result<Vec<String>, String> {
+        Ok(self
+            .scan_json_entries(dir)
+            .await?
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect())
     }
-}
 
-fn href_to_path(href: &str) -> String {
-    if href.starts_with("http://") || href.starts_with("https://") {
-        if let Ok(url) = reqwest::Url::parse(href) {
-            return url.path().trim_end_matches('/').to_string();
-        }
+    async fn scan_json_entries(&self, dir: &str) -> Result<Vec<RemoteEntry>, String> {
+        Ok(self
+            .store
+            .list(dir)
+            .await?
+            .into_iter()
+            .filter(|entry| entry.path.ends_with(".json"))
+            .collect())
     }
-    href.trim().trim_end_matches('/').to_string()
 }
 
-fn extract_href_values(xml: &str) -> Vec<String> {
-    let lower = xml.to_ascii_lowercase();
-    let mut result = Vec::new();
-    let mut cursor = 0usize;
-
-    while let Some(tag_start_rel) = lower[cursor..].find('<') {
-        let tag_start = cursor + tag_start_rel;
-        let Some(tag_end_rel) = lower[tag_start..].find('>') else {
-            break;
-        };
-        let tag_end = tag_start + tag_end_rel;
-        let tag_content = lower[tag_start + 1..tag_end].trim();
-        let tag_name = tag_content
-            .trim_start_matches('/')
-            .split_whitespace()
-            .next()
-            .unwrap_or("")
-            .split(':')
-            .last()
-            .unwrap_or("");
-
-        if tag_content.starts_with('/') || tag_name != "href" {
-            cursor = tag_end + 1;
-            continue;
-        }
-
-        let content_start = tag_end + 1;
-        let Some(close_start_rel) = lower[content_start..].find("</") else {
-            break;
-        };
-        let close_start = content_start + close_start_rel;
-        let Some(close_end_rel) = lower[close_start..].find('>') else {
-            break;
-        };
-        let close_end = close_start + close_end_rel;
-        let close_content = lower[close_start + 2..close_end].trim();
-        let close_name = close_content
-            .split_whitespace()
-            .next()
-            .unwrap_or("")
-            .split(':')
-            .last()
-            .unwrap_or("");
-        if close_name == "href" {
-            result.push(xml[content_start..close_start].trim().to_string());
-        }
-        cursor = close_end + 1;
-    }
-
-    result
-}
-
-async fn scan_remote_bundles(client: &WebDavClient) -> Result<Vec<AccountBundle>, String> {
+/// 返回 (远端路径, 账号文件)
+async fn scan_remote_bundles(client: &SyncClient) -> Result<Vec<(String, AccountBundle)>, String> {
     let mut bundles = Vec::new();
     for path in client.scan_account_paths().await? {
         let Some(text) = client.get_text_relative(&path).await? else {
             continue;
         };
         if let Ok(bundle) = parse_bundle_text(&text) {
-            bundles.push(bundle);
+            bundles.push((path, bundle));
         }
     }
     Ok(bundles)
 }
 
-async fn update_manifest_with_bundle(
-    client: &WebDavClient,
-    bundle: &AccountBundle,
+/// 待写入 manifest 的账号条目
+struct ManifestUpdate {
+    bundle: AccountBundle,
+    version: Option<ManifestVersion>,
+    /// 显式重新上传，撤销远端删除标记
+    reupload: bool,
+}
+
+/// 单账号同步立即写入 manifest；批量同步共用开始时读取的 manifest，结束后统一写入
+enum ManifestMode<'a> {
+    Immediate,
+    Batch {
+        state: &'a ManifestLoadState,
+        updates: &'a mut Vec<ManifestUpdate>,
+    },
+}
+
+/// snapshot 为已读取的 manifest 及其 ETag，首次写入直接使用，412 后再重新读取
+async fn update_manifest_with_bundles(
+    client: &SyncClient,
+    updates: &[ManifestUpdate],
+    mut snapshot: Option<(ManifestLoadState, Option<String>)>,
 ) -> Result<Option<String>, String> {
-    let state = client.load_manifest_state().await?;
-    let mut warning = None;
-    let mut manifest = match state {
-        ManifestLoadState::Existing(manifest) => manifest,
-        ManifestLoadState::Missing => {
-            let bundles = scan_remote_bundles(client).await?;
-            if bundles.is_empty() {
-                default_manifest()
-            } else {
-                warning = Some("manifest.json 缺失，已按账号文件重建".into());
-                build_manifest_from_bundles(&bundles)?
+    for _ in 0..MAX_WRITE_ATTEMPTS {
+        let (state, etag) = match snapshot.take() {
+            Some(snapshot) => snapshot,
+            None => client.load_manifest_with_etag().await?,
+        };
+        let mut warning = None;
+        let precondition = manifest_precondition(&state, etag);
+        let mut manifest = match state {
+            ManifestLoadState::Existing(manifest) => manifest,
+            ManifestLoadState::Missing => {
+                let bundles = scan_remote_bundles(client).await?;
+                if bundles.is_empty() {
+                    default_manifest()
+                } else {
+                    warning = Some("manifest.json 缺失，已按账号文件重建".into());
+                    build_manifest_from_bundles(&bundles)?
+                }
+            }
+            ManifestLoadState::Invalid => {
+                let bundles = scan_remote_bundles(client).await?;
+                warning = Some("manifest.json 损坏，已按账号文件重建".into());
+                if bundles.is_empty() {
+                    default_manifest()
+                } else {
+                    build_manifest_from_bundles(&bundles)?
+                }
+            }
+        };
+        manifest.schema_version = 1;
+        let mut pruned = Vec::new();
+        let mut deleted = Vec::new();
+        for ManifestUpdate {
+            bundle,
+            version,
+            reupload,
+        } in updates
+        {
+            // 读取后被其他设备删除的账号不再写回 manifest
+            if !reupload && manifest.tombstones.contains_key(&bundle.account.key) {
+                deleted.push(bundle.account.key.clone());
+                continue;
             }
+            let mut entry =
+                build_manifest_entry(&client.account_path(&bundle.account.key)?, bundle)?;
+            let previous = manifest
+                .accounts
+                .get(&bundle.account.key)
+                .map(|item| item.history.clone())
+                .unwrap_or_default();
+            let (history, removed) = append_history(previous, version.as_ref());
+            entry.history = history;
+            pruned.extend(removed);
+            manifest.accounts.insert(bundle.account.key.clone(), entry);
+            manifest.tombstones.remove(&bundle.account.key);
         }
-        ManifestLoadState::Invalid => {
-            let bundles = scan_remote_bundles(client).await?;
-            warning = Some("manifest.json 损坏，已按账号文件重建".into());
-            if bundles.is_empty() {
-                default_manifest()
-            } else {
-                build_manifest_from_bundles(&bundles)?
+        if !deleted.is_empty() {
+            let message = format!("账号 {} 已从远端删除，未写入 manifest", deleted.join("、"));
+            warning = Some(match warning {
+                Some(warning) => format!("{}；{}", warning, message),
+                None => message,
+            });
+        }
+        // 其他设备在读取后写入了 manifest，重新读取后再合并本次条目
+        if let PutOutcome::Written = client
+            .put_json_conditional("manifest.json", &manifest, &precondition)
+            .await?
+        {
+            // 超出保留数量的旧版本，删除失败不影响同步
+            for path in pruned {
+                let _ = client.delete_relative(&path).await;
             }
+            return Ok(warning);
         }
-    };
-    manifest.schema_version = 1;
-    manifest
-        .accounts
-        .insert(bundle.account.key.clone(), build_manifest_entry(bundle)?);
-    client.put_json_relative("manifest.json", &manifest).await?;
-    Ok(warning)
+    }
+    Err("manifest.json 被其他设备并发修改，重试后仍未写入".into())
+}
+
+/// 返回 manifest 条目是否已写入（批量模式下为已加入待写入列表）
+async fn submit_manifest_update(
+    client: &SyncClient,
+    mode: &mut ManifestMode<'_>,
+    update: ManifestUpdate,
+    warning_parts: &mut Vec<String>,
+) -> bool {
+    match mode {
+        ManifestMode::Batch { updates, .. } => {
+            updates.push(update);
+            true
+        }
+        ManifestMode::Immediate => {
+            match update_manifest_with_bundles(client, &[update], None).await {
+                Ok(warning) => {
+                    warning_parts.extend(warning);
+                    true
+                }
+                Err(error) => {
+                    warning_parts.push(error);
+                    false
+                }
+            }
+        }
+    }
 }
 
 fn extract_webdav_config(config: &AppConfigData) -> Result<WebDavConfigData, String> {
     let mut result = config.webdav.clone();
     result.base_url = normalize_string(&result.base_url);
     result.username = normalize_string(&result.username);
+    result.folder_path = normalize_string(&result.folder_path);
     result.base_path = normalize_base_path(&result.base_path);
 
-    if result.base_url.is_empty()
-        || result.username.is_empty()
-        || result.password.is_empty()
-        || result.base_path.is_empty()
-    {
-        return Err("请先填写完整的 WebDAV 配置".into());
+    match result.backend {
+        RemoteBackendKind::Webdav => {
+            if result.base_url.is_empty() || result.base_path.is_empty() {
+                return Err("请先填写完整的 WebDAV 配置".into());
+            }
+            validate_credentials(
+                result.auth_kind,
+                &result.username,
+                &result.password,
+                &result.bearer_token,
+                &result.extra_headers,
+            )?;
+        }
+        RemoteBackendKind::Folder => {
+            if result.folder_path.is_empty() {
+                return Err("请先填写同步目录".into());
+            }
+        }
+        RemoteBackendKind::S3 => {
+            if !result.s3.is_complete() {
+                return Err("请先填写完整的 S3 配置".into());
+            }
+        }
     }
     Ok(result)
 }
@@ -1288,18 +1897,22 @@ fn extract_webdav_config(config: &AppConfigData) -> Result<WebDavConfigData, Str
 pub async fn webdav_test_connection() -> Result<Value, String> {
     let config = load_config_data()?;
     let webdav = extract_webdav_config(&config)?;
-    let client = WebDavClient::new(&webdav)?;
+    let client = SyncClient::new(&webdav, &config.network)?;
     client.ensure_structure().await?;
 
-    let manifest = match client.load_manifest_state().await? {
-        ManifestLoadState::Existing(manifest) => manifest,
-        ManifestLoadState::Missing => default_manifest(),
+    let (state, etag) = client.load_manifest_with_etag().await?;
+    let (manifest, precondition) = match state {
+        ManifestLoadState::Existing(manifest) => (manifest, PutPrecondition::if_match(etag)),
+        ManifestLoadState::Missing => (default_manifest(), PutPrecondition::IfNoneMatch),
         ManifestLoadState::Invalid => {
             return Err("远端 manifest.json 结构错误，请先修复后再重试".into())
         }
     };
 
-    client.put_json_relative("manifest.json", &manifest).await?;
+    // 412 说明其他设备刚写入过 manifest，写权限同样可用
+    client
+        .put_json_conditional("manifest.json", &manifest, &precondition)
+        .await?;
     match client.load_manifest_state().await? {
         ManifestLoadState::Existing(_) => Ok(json!({ "ok": true })),
         _ => Err("manifest.json 写入后无法再次读取".into()),
@@ -1307,25 +1920,80 @@ pub async fn webdav_test_connection() -> Result<Value, String> {
 }
 
 #[command]
-pub async fn webdav_sync_account(user_key: Option<String>) -> Result<WebDavSyncResult, String> {
+pub async fn webdav_sync_account(
+    user_key: Option<String>,
+    reupload: Option<bool>,
+) -> Result<WebDavSyncResult, String> {
     let mut config = load_config_data()?;
     let webdav = extract_webdav_config(&config)?;
-    let client = WebDavClient::new(&webdav)?;
+    let client = SyncClient::new(&webdav, &config.network)?;
     client.ensure_structure().await?;
+    migrate_legacy_passphrase(&mut config, &client)?;
 
     let target_key = normalize_string(user_key.as_deref().unwrap_or(&config.current_user));
     if target_key.is_empty() || target_key == "none" {
         return Err("请先选择一个账号".into());
     }
 
+    sync_account_with(
+        &client,
+        &mut config,
+        &target_key,
+        &mut ManifestMode::Immediate,
+        reupload.unwrap_or(false),
+    )
+    .await
+}
+
+async fn sync_account_with(
+    client: &SyncClient,
+    config: &mut AppConfigData,
+    target_key: &str,
+    mode: &mut ManifestMode<'_>,
+    reupload: bool,
+) -> Result<WebDavSyncResult, String> {
     let user = config
         .users
         .iter()
         .find(|item| get_user_key(item) == target_key)
         .cloned()
-        .ok_or_else(|| "当前账号不存在，无法执行 WebDAV 同步".to_string())?;
+        .ok_or_else(|| "当前账号不存在，无法执行同步".to_string())?;
 
-    let local_bundle = build_local_bundle(&user)?;
+    // 上传时远端已被其他设备修改（412），重新下载并再次决策 / 合并
+    for _ in 0..MAX_WRITE_ATTEMPTS {
+        if let Some(result) =
+            sync_account_attempt(client, config, target_key, &user, mode, reupload).await?
+        {
+            clear_conflict_set(target_key)?;
+            return Ok(result);
+        }
+    }
+    Err("远端账号文件被其他设备并发修改，重试后仍未写入".into())
+}
+
+/// 远端删除标记或本设备记录的删除时间
+fn deleted_at(
+    manifest: Option<&ManifestFile>,
+    user_key: &str,
+    state: &WebDavStateItem,
+) -> Option<String> {
+    manifest
+        .and_then(|manifest| manifest.tombstones.get(user_key))
+        .map(|tombstone| tombstone.deleted_at.clone())
+        .or_else(|| Some(state.deleted_at.clone()).filter(|item| !item.is_empty()))
+}
+
+/// 单次同步；写入账号文件时遇到 ETag 不匹配返回 None
+async fn sync_account_attempt(
+    client: &SyncClient,
+    config: &mut AppConfigData,
+    target_key: &str,
+    user: &AppUser,
+    mode: &mut ManifestMode<'_>,
+    reupload: bool,
+) -> Result<Option<WebDavSyncResult>, String> {
+    let target_key = target_key.to_string();
+    let mut local_bundle = build_local_bundle(user)?;
     let local_hash = bundle_hash(&local_bundle)?;
     let state = config
         .webdav_state
@@ -1334,16 +2002,39 @@ pub async fn webdav_sync_account(user_key: Option<String>) -> Result<WebDavSyncR
         .unwrap_or_default();
     let has_previous_state = config.webdav_state.contains_key(&target_key);
 
-    let manifest_state = client.load_manifest_state().await?;
-    let manifest = match &manifest_state {
+    let shared_state = match mode {
+        ManifestMode::Batch { state, .. } => Some(*state),
+        ManifestMode::Immediate => None,
+    };
+    let loaded_state;
+    let manifest_state = match shared_state {
+        Some(state) => state,
+        None => {
+            loaded_state = client.load_manifest_state().await?;
+            &loaded_state
+        }
+    };
+    let manifest = match manifest_state {
         ManifestLoadState::Existing(manifest) => Some(manifest),
         _ => None,
     };
+    // 远端删除标记与本地删除记录只能由显式重新上传撤销，manifest 重建丢失标记时仍以本地记录为准
+    if let Some(deleted_at) = deleted_at(manifest, &target_key, &state).filter(|_| !reupload) {
+        return Err(format!(
+            "账号已于 {} 从远端删除，已停止同步；如需恢复请重新上传",
+            deleted_at
+        ));
+    }
 
     let remote_payload = client
-        .download_account_text_for_key(&target_key, manifest)
+        .download_account_file_for_key(&target_key, manifest)
         .await?;
-    let remote_bundle = if let Some((_path, text)) = remote_payload.as_ref() {
+    // 只对即将写入的默认路径使用 ETag 条件
+    let precondition = match remote_payload.as_ref() {
+        Some(file) if file.path != client.account_path(&target_key)? => PutPrecondition::None,
+        file => PutPrecondition::for_remote(file),
+    };
+    let mut remote_bundle = if let Some(RemoteFile { text, .. }) = remote_payload.as_ref() {
         match parse_bundle_text(text) {
             Ok(bundle) => Some(bundle),
             Err(error) => {
@@ -1369,22 +2060,23 @@ pub async fn webdav_sync_account(user_key: Option<String>) -> Result<WebDavSyncR
         None
     };
 
-    if let Some(remote_bundle) = remote_bundle.as_ref() {
-        if let Err(error) = validate_identity(&local_bundle, remote_bundle) {
-            let _ = save_conflict_snapshots(
-                &target_key,
-                &bundle_to_value(&local_bundle),
-                &bundle_to_value(remote_bundle),
-            );
-            return Err(format!("存在账号冲突，已暂停同步：{}", error));
-        }
-        if let Err(error) = validate_record_conflicts(&local_bundle, remote_bundle) {
-            let _ = save_conflict_snapshots(
-                &target_key,
-                &bundle_to_value(&local_bundle),
-                &bundle_to_value(remote_bundle),
-            );
-            return Err(format!("存在账号冲突，已暂停同步：{}", error));
+    // 冲突全部处理后，将结果写入两侧并强制走合并流程
+    let mut resolutions_applied = false;
+    if let Some(remote_bundle) = remote_bundle.as_mut() {
+        match prepare_resolutions(&target_key, &local_bundle, remote_bundle) {
+            Ok(None) => {}
+            Ok(Some(resolutions)) => {
+                apply_resolutions(&mut local_bundle, remote_bundle, &resolutions)?;
+                resolutions_applied = true;
+            }
+            Err(error) => {
+                let _ = save_conflict_snapshots(
+                    &target_key,
+                    &bundle_to_value(&local_bundle),
+                    &bundle_to_value(remote_bundle),
+                );
+                return Err(format!("存在账号冲突，已暂停同步：{}", error));
+            }
         }
     }
 
@@ -1413,7 +2105,9 @@ pub async fn webdav_sync_account(user_key: Option<String>) -> Result<WebDavSyncR
         "merged".to_string()
     };
 
-    let action = if !has_previous_state {
+    let action = if resolutions_applied {
+        "merged".to_string()
+    } else if !has_previous_state {
         decide_without_state(
             &local_bundle,
             remote_bundle.as_ref(),
@@ -1449,10 +2143,11 @@ pub async fn webdav_sync_account(user_key: Option<String>) -> Result<WebDavSyncR
                     } else {
                         state.last_sync_at.clone()
                     },
+                    deleted_at: String::new(),
                 },
             );
-            save_config_data(&config)?;
-            return Ok(WebDavSyncResult {
+            save_config_data(config)?;
+            return Ok(Some(WebDavSyncResult {
                 account_key: target_key,
                 status: "noop".into(),
                 message: "双端无变化，未执行写入。".into(),
@@ -1460,32 +2155,41 @@ pub async fn webdav_sync_account(user_key: Option<String>) -> Result<WebDavSyncR
                 local_changed: false,
                 manifest_updated: false,
                 updated_at: now,
-            });
+            }));
         }
         "uploaded" => {
             final_bundle.updated_at = now.clone();
-            client
-                .put_json_relative(
-                    &account_relative_path(&target_key),
-                    &bundle_to_remote_value(&final_bundle),
-                )
-                .await?;
+            if let PutOutcome::PreconditionFailed = client
+                .put_bundle(&target_key, &final_bundle, &precondition)
+                .await?
+            {
+                return Ok(None);
+            }
             write_bundle_to_local_record(&target_key, &final_bundle)?;
-            match update_manifest_with_bundle(&client, &final_bundle).await {
-                Ok(warning) => {
-                    manifest_updated = true;
-                    if let Some(warning) = warning {
-                        warning_parts.push(warning);
-                    }
+            let version = match client.put_history_version(&final_bundle).await {
+                Ok(version) => Some(version),
+                Err(error) => {
+                    warning_parts.push(format!("历史版本保存失败：{}", error));
+                    None
                 }
-                Err(error) => warning_parts.push(error),
-            }
+            };
+            manifest_updated = submit_manifest_update(
+                client,
+                mode,
+                ManifestUpdate {
+                    bundle: final_bundle.clone(),
+                    version,
+                    reupload,
+                },
+                &mut warning_parts,
+            )
+            .await;
             bundle_hash(&final_bundle)?
         }
         "downloaded" => {
             let remote_bundle = remote_bundle.ok_or_else(|| "远端账号文件不存在".to_string())?;
             write_bundle_to_local_record(&target_key, &remote_bundle)?;
-            upsert_user_from_bundle(&mut config, &remote_bundle, false)?;
+            upsert_user_from_bundle(config, &remote_bundle, false)?;
             let hash = bundle_hash(&remote_bundle)?;
             final_bundle = remote_bundle;
             local_changed = true;
@@ -1504,23 +2208,32 @@ pub async fn webdav_sync_account(user_key: Option<String>) -> Result<WebDavSyncR
                     return Err(format!("检测到账号冲突，已暂停同步：{}", error));
                 }
             };
-            client
-                .put_json_relative(
-                    &account_relative_path(&target_key),
-                    &bundle_to_remote_value(&merged),
-                )
-                .await?;
+            if let PutOutcome::PreconditionFailed = client
+                .put_bundle(&target_key, &merged, &precondition)
+                .await?
+            {
+                return Ok(None);
+            }
             write_bundle_to_local_record(&target_key, &merged)?;
-            upsert_user_from_bundle(&mut config, &merged, false)?;
-            match update_manifest_with_bundle(&client, &merged).await {
-                Ok(warning) => {
-                    manifest_updated = true;
-                    if let Some(warning) = warning {
-                        warning_parts.push(warning);
-                    }
+            upsert_user_from_bundle(config, &merged, false)?;
+            let version = match client.put_history_version(&merged).await {
+                Ok(version) => Some(version),
+                Err(error) => {
+                    warning_parts.push(format!("历史版本保存失败：{}", error));
+                    None
                 }
-                Err(error) => warning_parts.push(error),
-            }
+            };
+            manifest_updated = submit_manifest_update(
+                client,
+                mode,
+                ManifestUpdate {
+                    bundle: merged.clone(),
+                    version,
+                    reupload,
+                },
+                &mut warning_parts,
+            )
+            .await;
             let hash = bundle_hash(&merged)?;
             final_bundle = merged;
             local_changed = true;
@@ -1535,18 +2248,19 @@ pub async fn webdav_sync_account(user_key: Option<String>) -> Result<WebDavSyncR
             last_local_hash: final_hash.clone(),
             last_remote_hash: final_hash.clone(),
             last_sync_at: final_bundle.updated_at.clone(),
+            deleted_at: String::new(),
         },
     );
-    save_config_data(&config)?;
+    save_config_data(config)?;
 
     let message = match action.as_str() {
-        "uploaded" => "本地账号数据已上传到 WebDAV。".to_string(),
-        "downloaded" => "已使用 WebDAV 远端数据覆盖本地账号数据。".to_string(),
+        "uploaded" => "本地账号数据已上传到远端。".to_string(),
+        "downloaded" => "已使用远端数据覆盖本地账号数据。".to_string(),
         "merged" => "检测到双端变更，已完成自动并集合并。".to_string(),
         _ => "同步完成。".to_string(),
     };
 
-    Ok(WebDavSyncResult {
+    Ok(Some(WebDavSyncResult {
         account_key: target_key,
         status: action,
         message,
@@ -1558,6 +2272,114 @@ pub async fn webdav_sync_account(user_key: Option<String>) -> Result<WebDavSyncR
         local_changed,
         manifest_updated,
         updated_at: final_bundle.updated_at,
+    }))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavBatchFailure {
+    account_key: String,
+    message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavBatchSyncResult {
+    total: usize,
+    /// 未绑定角色或已从远端删除的账号
+    skipped: usize,
+    failed: Vec<WebDavBatchFailure>,
+    results: Vec<WebDavSyncResult>,
+}
+
+fn append_sync_warning(result: &mut WebDavSyncResult, text: &str) {
+    result.warning = Some(match result.warning.take() {
+        Some(warning) => format!("{}；{}", warning, text),
+        None => text.to_string(),
+    });
+}
+
+/// 同步全部账号：只初始化一次目录、读取一次 manifest，最后统一写入 manifest
+#[command]
+pub async fn webdav_sync_all() -> Result<WebDavBatchSyncResult, String> {
+    let mut config = load_config_data()?;
+    let webdav = extract_webdav_config(&config)?;
+    let client = SyncClient::new(&webdav, &config.network)?;
+    client.ensure_structure().await?;
+    migrate_legacy_passphrase(&mut config, &client)?;
+
+    let mut seen = BTreeSet::new();
+    let mut keys = Vec::new();
+    let mut skipped = 0;
+    for user in config.users.iter() {
+        let key = get_user_key(user);
+        if key.is_empty() || key == "none" || !seen.insert(key.clone()) {
+            continue;
+        }
+        let has_role = user
+            .role_id
+            .as_ref()
+            .is_some_and(|meta| !meta.role_id.trim().is_empty());
+        if has_role {
+            keys.push(key);
+        } else {
+            skipped += 1;
+        }
+    }
+    if keys.is_empty() {
+        return Err("当前没有可同步的账号".into());
+    }
+
+    let (state, etag) = client.load_manifest_with_etag().await?;
+    let mut updates = Vec::new();
+    let mut results = Vec::new();
+    let mut failed = Vec::new();
+    for key in keys.iter() {
+        let manifest = match &state {
+            ManifestLoadState::Existing(manifest) => Some(manifest),
+            _ => None,
+        };
+        let local_state = config.webdav_state.get(key).cloned().unwrap_or_default();
+        if deleted_at(manifest, key, &local_state).is_some() {
+            skipped += 1;
+            continue;
+        }
+
+        let mut mode = ManifestMode::Batch {
+            state: &state,
+            updates: &mut updates,
+        };
+        match sync_account_with(&client, &mut config, key, &mut mode, false).await {
+            Ok(result) => results.push(result),
+            Err(message) => failed.push(WebDavBatchFailure {
+                account_key: key.clone(),
+                message,
+            }),
+        }
+    }
+
+    if !updates.is_empty() {
+        match update_manifest_with_bundles(&client, &updates, Some((state, etag))).await {
+            Ok(Some(warning)) => {
+                for result in results.iter_mut().filter(|item| item.manifest_updated) {
+                    append_sync_warning(result, &warning);
+                }
+            }
+            Ok(None) => {}
+            Err(error) => {
+                for result in results.iter_mut().filter(|item| item.manifest_updated) {
+                    result.manifest_updated = false;
+                    append_sync_warning(result, &error);
+                }
+            }
+        }
+    }
+
+    Ok(WebDavBatchSyncResult {
+        total: seen.len(),
+        skipped,
+        failed,
+        results,
     })
 }
 
@@ -1565,39 +2387,45 @@ pub async fn webdav_sync_account(user_key: Option<String>) -> Result<WebDavSyncR
 pub async fn webdav_list_restore_accounts() -> Result<Vec<WebDavRestoreAccount>, String> {
     let config = load_config_data()?;
     let webdav = extract_webdav_config(&config)?;
-    let client = WebDavClient::new(&webdav)?;
+    let client = SyncClient::new(&webdav, &config.network)?;
     client.ensure_structure().await?;
 
     let manifest_state = client.load_manifest_state().await?;
-    let mut by_key: BTreeMap<String, AccountBundle> = BTreeMap::new();
+    let mut by_key: BTreeMap<String, (String, AccountBundle)> = BTreeMap::new();
 
     if let ManifestLoadState::Existing(manifest) = &manifest_state {
         for key in manifest.accounts.keys() {
-            let Some((_path, text)) = client
-                .download_account_text_for_key(key, Some(manifest))
+            let Some(file) = client
+                .download_account_file_for_key(key, Some(manifest))
                 .await?
             else {
                 continue;
             };
-            if let Ok(bundle) = parse_bundle_text(&text) {
-                by_key.insert(bundle.account.key.clone(), bundle);
+            if let Ok(bundle) = parse_bundle_text(&file.text) {
+                by_key.insert(bundle.account.key.clone(), (file.path, bundle));
             }
         }
     }
 
-    for bundle in scan_remote_bundles(&client).await? {
-        by_key.insert(bundle.account.key.clone(), bundle);
+    for (path, bundle) in scan_remote_bundles(&client).await? {
+        by_key.insert(bundle.account.key.clone(), (path, bundle));
+    }
+    if let ManifestLoadState::Existing(manifest) = &manifest_state {
+        by_key.retain(|key, _| !manifest.tombstones.contains_key(key));
     }
 
     let bundles: Vec<_> = by_key.into_values().collect();
     if !bundles.is_empty() {
-        let manifest = build_manifest_from_bundles(&bundles)?;
+        let mut manifest = build_manifest_from_bundles(&bundles)?;
+        if let ManifestLoadState::Existing(previous) = &manifest_state {
+            carry_over_history(&mut manifest, previous);
+        }
         let _ = client.put_json_relative("manifest.json", &manifest).await;
     }
 
     let mut result = bundles
         .into_iter()
-        .map(|bundle| WebDavRestoreAccount {
+        .map(|(_, bundle)| WebDavRestoreAccount {
             key: bundle.account.key,
             provider: bundle.account.provider,
             uid: bundle.account.uid,
@@ -1622,7 +2450,7 @@ pub async fn webdav_restore_accounts(keys: Vec<String>) -> Result<WebDavRestoreR
 
     let mut config = load_config_data()?;
     let webdav = extract_webdav_config(&config)?;
-    let client = WebDavClient::new(&webdav)?;
+    let client = SyncClient::new(&webdav, &config.network)?;
     client.ensure_structure().await?;
 
     let manifest_state = client.load_manifest_state().await?;
@@ -1634,11 +2462,11 @@ pub async fn webdav_restore_accounts(keys: Vec<String>) -> Result<WebDavRestoreR
     let mut restored = Vec::new();
 
     for key in selected_keys {
-        let Some((_path, text)) = client.download_account_text_for_key(&key, manifest).await?
-        else {
+        let Some(file) = client.download_account_file_for_key(&key, manifest).await? else {
             return Err(format!("远端账号文件不存在：{}", key));
         };
-        let bundle = parse_bundle_text(&text).map_err(|_| format!("远端账号文件损坏：{}", key))?;
+        let bundle =
+            parse_bundle_text(&file.text).map_err(|_| format!("远端账号文件损坏：{}", key))?;
         write_bundle_to_local_record(&bundle.account.key, &bundle)?;
         upsert_user_from_bundle(&mut config, &bundle, true)?;
         let hash = bundle_hash(&bundle)?;
@@ -1648,6 +2476,7 @@ pub async fn webdav_restore_accounts(keys: Vec<String>) -> Result<WebDavRestoreR
                 last_local_hash: hash.clone(),
                 last_remote_hash: hash.clone(),
                 last_sync_at: bundle.updated_at.clone(),
+                deleted_at: String::new(),
             },
         );
         restored.push(bundle.account.key.clone());
@@ -1669,3 +2498,1296 @@ pub async fn webdav_restore_accounts(keys: Vec<String>) -> Result<WebDavRestoreR
         current_user: config.current_user,
     })
 }
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavVersion {
+    path: String,
+    updated_at: String,
+    /// manifest 中未登记的历史文件为空
+    content_hash: String,
+    saved_at: String,
+    /// 与远端当前账号文件内容一致
+    current: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavVersionRestoreResult {
+    account_key: String,
+    path: String,
+    updated_at: String,
+    content_hash: String,
+}
+
+#[command]
+pub async fn webdav_list_versions(user_key: String) -> Result<Vec<WebDavVersion>, String> {
+    let user_key = normalize_string(&user_key);
+    if user_key.is_empty() {
+        return Err("请先选择一个账号".into());
+    }
+    let config = load_config_data()?;
+    let webdav = extract_webdav_config(&config)?;
+    let client = SyncClient::new(&webdav, &config.network)?;
+    client.ensure_structure().await?;
+
+    let entry = match client.load_manifest_state().await? {
+        ManifestLoadState::Existing(manifest) => manifest.accounts.get(&user_key).cloned(),
+        _ => None,
+    }
+    .unwrap_or_default();
+    let mut history = entry.history.clone();
+    // manifest 重建后丢失登记的历史文件仍可列出
+    for path in client
+        .scan_json_paths(&client.history_dir(&user_key)?)
+        .await?
+    {
+        if !history.iter().any(|item| item.path == path) {
+            history.push(ManifestVersion {
+                path,
+                ..Default::default()
+            });
+        }
+    }
+    history.sort_by(|a, b| b.path.cmp(&a.path));
+
+    Ok(history
+        .into_iter()
+        .map(|item| WebDavVersion {
+            current: !item.content_hash.is_empty() && item.content_hash == entry.content_hash,
+            path: item.path,
+            updated_at: item.updated_at,
+            content_hash: item.content_hash,
+            saved_at: item.saved_at,
+        })
+        .collect())
+}
+
+/// 将历史版本恢复到本地；下次同步时作为新版本上传并覆盖远端当前版本
+#[command]
+pub async fn webdav_restore_version(
+    user_key: String,
+    path: String,
+) -> Result<WebDavVersionRestoreResult, String> {
+    let user_key = normalize_string(&user_key);
+    let path = normalize_string(&path).trim_start_matches('/').to_string();
+    if user_key.is_empty() || !path.ends_with(".json") || path.contains("..") {
+        return Err("历史版本路径无效".into());
+    }
+
+    let mut config = load_config_data()?;
+    let webdav = extract_webdav_config(&config)?;
+    let client = SyncClient::new(&webdav, &config.network)?;
+    client.ensure_structure().await?;
+    if !path.starts_with(&format!("{}/", client.history_dir(&user_key)?)) {
+        return Err("历史版本路径无效".into());
+    }
+
+    let manifest_state = client.load_manifest_state().await?;
+    let manifest = match &manifest_state {
+        ManifestLoadState::Existing(manifest) => Some(manifest),
+        _ => None,
+    };
+    let file = client
+        .get_file_relative(&path)
+        .await?
+        .ok_or_else(|| format!("历史版本不存在：{}", path))?;
+    let bundle = parse_bundle_text(&file.text).map_err(|_| format!("历史版本损坏：{}", path))?;
+    if bundle.account.key != user_key {
+        return Err(format!("历史版本不属于账号 {}", user_key));
+    }
+    let hash = bundle_hash(&bundle)?;
+    let expected_hash = manifest
+        .and_then(|manifest| manifest.accounts.get(&user_key))
+        .and_then(|entry| entry.history.iter().find(|item| item.path == path))
+        .map(|item| item.content_hash.clone())
+        .unwrap_or_default();
+    if !expected_hash.is_empty() && expected_hash != hash {
+        return Err("历史版本校验失败：内容哈希与 manifest 不一致".into());
+    }
+
+    let remote_hash = match client
+        .download_account_file_for_key(&user_key, manifest)
+        .await?
+    {
+        Some(file) => parse_bundle_text(&file.text)
+            .and_then(|remote| bundle_hash(&remote))
+            .unwrap_or_default(),
+        None => String::new(),
+    };
+
+    write_bundle_to_local_record(&user_key, &bundle)?;
+    upsert_user_from_bundle(&mut config, &bundle, true)?;
+    // 记录远端当前哈希并清空本地哈希，使下次同步判定为本地变更而直接上传
+    let last_sync_at = config
+        .webdav_state
+        .get(&user_key)
+        .map(|item| item.last_sync_at.clone())
+        .unwrap_or_default();
+    config.webdav_state.insert(
+        user_key.clone(),
+        WebDavStateItem {
+            last_local_hash: String::new(),
+            last_remote_hash: remote_hash,
+            last_sync_at,
+            deleted_at: String::new(),
+        },
+    );
+    save_config_data(&config)?;
+
+    Ok(WebDavVersionRestoreResult {
+        account_key: user_key,
+        path,
+        updated_at: bundle.updated_at,
+        content_hash: hash,
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavDeleteResult {
+    account_key: String,
+    deleted_at: String,
+    deleted_files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavPruneResult {
+    orphans: Vec<String>,
+    /// dry_run 时为空
+    deleted: Vec<String>,
+    /// 宽限期内修改（或修改时间未知）的孤立文件，本次不删除
+    recent: Vec<String>,
+}
+
+/// 先写入删除标记再删除文件，中途失败可重新执行
+#[command]
+pub async fn webdav_delete_account(key: String) -> Result<WebDavDeleteResult, String> {
+    let key = normalize_string(&key);
+    if key.is_empty() {
+        return Err("请先选择一个账号".into());
+    }
+    let mut config = load_config_data()?;
+    let webdav = extract_webdav_config(&config)?;
+    let client = SyncClient::new(&webdav, &config.network)?;
+    client.ensure_structure().await?;
+
+    for _ in 0..MAX_WRITE_ATTEMPTS {
+        let (state, etag) = client.load_manifest_with_etag().await?;
+        let precondition = manifest_precondition(&state, etag);
+        let mut manifest = match state {
+            ManifestLoadState::Existing(manifest) => manifest,
+            ManifestLoadState::Missing => default_manifest(),
+            ManifestLoadState::Invalid => {
+                return Err("远端 manifest.json 结构错误，请先修复后再重试".into())
+            }
+        };
+
+        let mut paths = BTreeSet::from([client.account_path(&key)?]);
+        paths.extend(client.scan_json_paths(&client.history_dir(&key)?).await?);
+        let entry = manifest.accounts.remove(&key);
+        if let Some(entry) = entry.as_ref() {
+            let path = normalize_string(&entry.path)
+                .trim_start_matches('/')
+                .to_string();
+            if !path.is_empty() {
+                paths.insert(path);
+            }
+            paths.extend(entry.history.iter().map(|item| item.path.clone()));
+        }
+        let tombstone = ManifestTombstone {
+            deleted_at: now_iso_string(),
+            content_hash: entry.map(|entry| entry.content_hash).unwrap_or_default(),
+        };
+        manifest.tombstones.insert(key.clone(), tombstone.clone());
+
+        if let PutOutcome::PreconditionFailed = client
+            .put_json_conditional("manifest.json", &manifest, &precondition)
+            .await?
+        {
+            continue;
+        }
+        for path in paths.iter() {
+            client.delete_relative(path).await?;
+        }
+        // 保留删除记录，避免本地数据在下次同步时把账号写回远端
+        config.webdav_state.insert(
+            key.clone(),
+            WebDavStateItem {
+                deleted_at: tombstone.deleted_at.clone(),
+                ..Default::default()
+            },
+        );
+        save_config_data(&config)?;
+        return Ok(WebDavDeleteResult {
+            account_key: key,
+            deleted_at: tombstone.deleted_at,
+            deleted_files: paths.into_iter().collect(),
+        });
+    }
+    Err("manifest.json 被其他设备并发修改，重试后仍未写入".into())
+}
+
+/// 清理 manifest 未引用的账号文件与历史版本（含已删除账号的残留文件），跳过宽限期内修改的文件
+#[command]
+pub async fn webdav_prune_remote(dry_run: Option<bool>) -> Result<WebDavPruneResult, String> {
+    let config = load_config_data()?;
+    let webdav = extract_webdav_config(&config)?;
+    let client = SyncClient::new(&webdav, &config.network)?;
+    client.ensure_structure().await?;
+
+    let ManifestLoadState::Existing(manifest) = client.load_manifest_state().await? else {
+        return Err("manifest.json 缺失或损坏，无法判断孤立文件".into());
+    };
+    let referenced = manifest
+        .accounts
+        .values()
+        .flat_map(|entry| {
+            std::iter::once(
+                normalize_string(&entry.path)
+                    .trim_start_matches('/')
+                    .to_string(),
+            )
+            .chain(entry.history.iter().map(|item| item.path.clone()))
+        })
+        .collect::<BTreeSet<_>>();
+
+    let mut candidates = client.scan_json_entries("accounts").await?;
+    let mut names = BTreeSet::new();
+    for key in manifest.accounts.keys().chain(manifest.tombstones.keys()) {
+        names.insert(client.remote_name(key)?);
+    }
+    names.extend(
+        candidates
+            .iter()
+            .filter_map(|entry| account_file_name(&entry.path)),
+    );
+    for name in names {
+        candidates.extend(
+            client
+                .scan_json_entries(&history_dir_relative(&name))
+                .await?,
+        );
+    }
+
+    let cutoff = Utc::now() - chrono::Duration::hours(PRUNE_GRACE_HOURS);
+    let mut orphans = BTreeSet::new();
+    let mut recent = BTreeSet::new();
+    for entry in candidates
+        .into_iter()
+        .filter(|entry| !referenced.contains(&entry.path))
+    {
+        match entry.modified {
+            Some(modified) if modified < cutoff => orphans.insert(entry.path),
+            _ => recent.insert(entry.path),
+        };
+    }
+    let orphans = orphans.into_iter().collect::<Vec<_>>();
+    let mut deleted = Vec::new();
+    if !dry_run.unwrap_or(false) {
+        for path in orphans.iter() {
+            client.delete_relative(path).await?;
+            deleted.push(path.clone());
+        }
+    }
+    Ok(WebDavPruneResult {
+        orphans,
+        deleted,
+        recent: recent.into_iter().collect(),
+    })
+}
+
+/// 列出远端目录（相对 basePath，默认根目录）中的文件与子目录
+#[command]
+pub async fn webdav_list_remote_files(dir: Option<String>) -> Result<Vec<DavResource>, String> {
+    let config = load_config_data()?;
+    let webdav = extract_webdav_config(&config)?;
+    let client = SyncClient::new(&webdav, &config.network)?;
+    client.ensure_collections().await?;
+    let store = client
+        .store
+        .as_webdav()
+        .ok_or("当前同步后端不支持浏览远端目录")?;
+
+    let dir = normalize_string(dir.as_deref().unwrap_or(""))
+        .trim_matches('/')
+        .to_string();
+    if dir.split('/').any(|segment| segment == "..") {
+        return Err("目录路径无效".into());
+    }
+    let Some(resources) = store.propfind_relative(&dir, "1").await? else {
+        return Err(format!("远端目录不存在：/{}", dir));
+    };
+    let own_path = href_to_path(&store.url_for_relative(&dir));
+    let mut result = resources
+        .into_iter()
+        .filter(|item| item.path != own_path)
+        .collect::<Vec<_>>();
+    result.sort_by(|a, b| {
+        b.is_collection
+            .cmp(&a.is_collection)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    Ok(result)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavEncryptionStatus {
+    encrypted: bool,
+    key_id: String,
+    passphrase_configured: bool,
+    /// 本地口令能否解密远端数据
+    unlocked: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavEncryptionResult {
+    encrypted: bool,
+    key_id: String,
+    rewritten: Vec<String>,
+}
+
+/// 旧版配置中的明文口令解锁成功后换成派生密钥保存
+fn migrate_legacy_passphrase(
+    config: &mut AppConfigData,
+    client: &SyncClient,
+) -> Result<(), String> {
+    if config.webdav.encryption_passphrase.is_empty() {
+        return Ok(());
+    }
+    let Some(stored) = client
+        .cipher
+        .as_ref()
+        .and_then(|cipher| cipher.stored_key().ok())
+    else {
+        return Ok(());
+    };
+    config.webdav.encryption_key = Some(stored);
+    config.webdav.encryption_passphrase.clear();
+    save_config_data(config)
+}
+
+async fn encryption_status(client: &SyncClient) -> Result<WebDavEncryptionStatus, String> {
+    client.ensure_collections().await?;
+    let meta = client.load_encryption_meta().await?;
+    let unlocked = match (meta.as_ref(), client.cipher.as_ref()) {
+        (Some(meta), Some(cipher)) => cipher.unlock(meta).is_ok(),
+        (None, None) => true,
+        _ => false,
+    };
+    Ok(WebDavEncryptionStatus {
+        encrypted: meta.is_some(),
+        key_id: meta.map(|meta| meta.key_id).unwrap_or_default(),
+        passphrase_configured: client.cipher.is_some(),
+        unlocked,
+    })
+}
+
+/// 恢复前检查远端是否加密，未解锁时由前端提示输入口令
+#[command]
+pub async fn webdav_encryption_status() -> Result<WebDavEncryptionStatus, String> {
+    let mut config = load_config_data()?;
+    let webdav = extract_webdav_config(&config)?;
+    let client = SyncClient::new(&webdav, &config.network)?;
+    let status = encryption_status(&client).await?;
+    if status.unlocked {
+        migrate_legacy_passphrase(&mut config, &client)?;
+    }
+    Ok(status)
+}
+
+/// 校验口令能否解密远端数据，通过后把派生密钥保存到本地配置
+#[command]
+pub async fn webdav_unlock_encryption(
+    passphrase: String,
+) -> Result<WebDavEncryptionStatus, String> {
+    if passphrase.trim().is_empty() {
+        return Err("加密口令不能为空".into());
+    }
+    let mut config = load_config_data()?;
+    let webdav = extract_webdav_config(&config)?;
+    let client = SyncClient::with_cipher(
+        &webdav,
+        &config.network,
+        BundleCipher::new(vec![passphrase]),
+    )?;
+    client.ensure_collections().await?;
+    let meta = client
+        .load_encryption_meta()
+        .await?
+        .ok_or("远端未启用加密，无需输入口令")?;
+    let cipher = client.cipher.as_ref().ok_or("加密口令不能为空")?;
+    cipher.unlock(&meta)?;
+
+    config.webdav.encryption_key = Some(cipher.stored_key()?);
+    config.webdav.encryption_passphrase.clear();
+    save_config_data(&config)?;
+    encryption_status(&client).await
+}
+
+/// 远端需要随口令一起重写的文件：manifest、全部账号文件与历史版本
+async fn collect_encrypted_paths(client: &SyncClient) -> Result<BTreeSet<String>, String> {
+    let mut paths = BTreeSet::from(["manifest.json".to_string()]);
+    let mut names = BTreeSet::new();
+    if let ManifestLoadState::Existing(manifest) = client.load_manifest_state().await? {
+        paths.extend(
+            manifest
+                .accounts
+                .values()
+                .flat_map(|entry| entry.history.iter().map(|item| item.path.clone())),
+        );
+        paths.extend(
+            manifest
+                .accounts
+                .values()
+                .map(|entry| {
+                    normalize_string(&entry.path)
+                        .trim_start_matches('/')
+                        .to_string()
+                })
+                .filter(|path| !path.is_empty()),
+        );
+    }
+    names.extend(paths.iter().filter_map(|path| account_file_name(path)));
+    for path in client.scan_account_paths().await? {
+        names.extend(account_file_name(&path));
+        paths.insert(path);
+    }
+    for name in names {
+        paths.extend(client.scan_json_paths(&history_dir_relative(&name)).await?);
+    }
+    Ok(paths)
+}
+
+/// 账号文件或历史版本在 writer 口令下的路径
+fn renamed_path(writer: &SyncClient, path: &str, user_key: &str) -> Result<String, String> {
+    if account_file_name(path).is_some() {
+        return writer.account_path(user_key);
+    }
+    match path.rsplit_once('/') {
+        Some((dir, file)) if dir.starts_with("history/") => {
+            Ok(format!("{}/{}", writer.history_dir(user_key)?, file))
+        }
+        _ => Ok(path.to_string()),
+    }
+}
+
+fn remap_manifest_paths(manifest: &mut ManifestFile, moved: &BTreeMap<String, String>) {
+    let remap = |path: &mut String| {
+        let key = normalize_string(path).trim_start_matches('/').to_string();
+        if let Some(target) = moved.get(&key) {
+            *path = target.clone();
+        }
+    };
+    for entry in manifest.accounts.values_mut() {
+        remap(&mut entry.path);
+        for item in entry.history.iter_mut() {
+            remap(&mut item.path);
+        }
+    }
+}
+
+/// 启用、更换或关闭加密（passphrase 为空）：用旧口令解密并以新口令重写远端全部文件
+#[command]
+pub async fn webdav_set_encryption(
+    passphrase: Option<String>,
+) -> Result<WebDavEncryptionResult, String> {
+    let mut config = load_config_data()?;
+    let webdav = extract_webdav_config(&config)?;
+    let new_passphrase = passphrase.filter(|item| !item.trim().is_empty());
+
+    // 同时持有新旧口令，上次轮换中断时已用新口令写入的文件也能读取
+    let reader = SyncClient::with_cipher(
+        &webdav,
+        &config.network,
+        BundleCipher::with_stored_key(
+            webdav.encryption_key.clone(),
+            vec![
+                webdav.encryption_passphrase.clone(),
+                new_passphrase.clone().unwrap_or_default(),
+            ],
+        ),
+    )?;
+    reader.ensure_collections().await?;
+    if reader.load_encryption_meta().await?.is_some() && reader.cipher.is_none() {
+        return Err(PASSPHRASE_REQUIRED_ERROR.into());
+    }
+
+    let writer = SyncClient::with_cipher(
+        &webdav,
+        &config.network,
+        new_passphrase
+            .clone()
+            .and_then(|item| BundleCipher::new(vec![item])),
+    )?;
+    let meta = writer
+        .cipher
+        .as_ref()
+        .map(|cipher| cipher.create_meta())
+        .transpose()?;
+
+    // 账号文件名随口令变化，先写入新路径，manifest 改指向新路径后再删除旧文件
+    let mut rewritten = Vec::new();
+    let mut moved = BTreeMap::new();
+    let mut manifest_file = None;
+    for path in collect_encrypted_paths(&reader).await? {
+        let Some(file) = reader.get_file_relative(&path).await? else {
+            continue;
+        };
+        if path == "manifest.json" {
+            manifest_file = Some(file);
+            continue;
+        }
+        let target = match parse_bundle_text(&file.text) {
+            Ok(bundle) => renamed_path(&writer, &path, &bundle.account.key)?,
+            Err(_) => path.clone(),
+        };
+        let body = writer.seal_text(file.text.clone())?;
+        if target == path {
+            let precondition = PutPrecondition::for_remote(Some(&file));
+            if let PutOutcome::PreconditionFailed = writer
+                .put_text_conditional(&path, body, &precondition)
+                .await?
+            {
+                return Err(format!("{} 在重写期间被其他设备修改，请稍后重试", path));
+            }
+        } else {
+            if let Some((dir, _)) = target.rsplit_once('/') {
+                writer.store.ensure_dir(dir).await?;
+            }
+            writer
+                .put_text_conditional(&target, body, &PutPrecondition::None)
+                .await?;
+            moved.insert(path, target.clone());
+        }
+        rewritten.push(target);
+    }
+
+    if let Some(file) = manifest_file {
+        let precondition = PutPrecondition::for_remote(Some(&file));
+        let text = match serde_json::from_str::<ManifestFile>(&file.text) {
+            Ok(mut manifest) => {
+                remap_manifest_paths(&mut manifest, &moved);
+                serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?
+            }
+            Err(_) => file.text,
+        };
+        if let PutOutcome::PreconditionFailed = writer
+            .put_text_conditional("manifest.json", writer.seal_text(text)?, &precondition)
+            .await?
+        {
+            return Err("manifest.json 在重写期间被其他设备修改，请稍后重试".into());
+        }
+        rewritten.push("manifest.json".into());
+    }
+
+    // 最后更新 encryption.json，中途失败时可用新口令重新执行
+    match meta.as_ref() {
+        Some(meta) => {
+            let body = serde_json::to_string_pretty(meta).map_err(|e| e.to_string())?;
+            writer
+                .put_text_conditional(ENCRYPTION_META_PATH, body, &PutPrecondition::None)
+                .await?;
+        }
+        None => writer.delete_relative(ENCRYPTION_META_PATH).await?,
+    }
+    for path in moved.keys() {
+        writer.delete_relative(path).await?;
+    }
+
+    config.webdav.encryption_key = writer
+        .cipher
+        .as_ref()
+        .map(|cipher| cipher.stored_key())
+        .transpose()?;
+    config.webdav.encryption_passphrase.clear();
+    save_config_data(&config)?;
+    Ok(WebDavEncryptionResult {
+        encrypted: meta.is_some(),
+        key_id: meta.map(|meta| meta.key_id).unwrap_or_default(),
+        rewritten,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{self, DavState, HttpStub};
+    use std::sync::{Arc, Mutex};
+
+    const KEY: &str = "1001_r1";
+    const ACCOUNT_PATH: &str = "/ef/accounts/1001_r1.json";
+    const MANIFEST_PATH: &str = "/ef/manifest.json";
+
+    fn weapon(seq_id: &str) -> Value {
+        json!({
+            "weaponId": "wpn_sword_0006",
+            "weaponName": "熔铸火焰",
+            "weaponType": "sword",
+            "poolId": "weaponbox_1_0_1",
+            "poolName": "熔铸申领",
+            "rarity": 4,
+            "isNew": false,
+            "gachaTs": format!("17680{}000000", seq_id),
+            "seqId": seq_id,
+        })
+    }
+
+    fn setup(stub: &HttpStub, seq_ids: &[&str]) {
+        testutil::write_config(&json!({
+            "users": [testutil::hypergryph_user("1001", "r1")],
+            "currentUser": KEY,
+            "webdav": {
+                "baseUrl": stub.base_url,
+                "username": "user",
+                "password": "pass",
+                "basePath": "/ef"
+            }
+        }));
+        write_local(seq_ids);
+    }
+
+    /// 本地记录按 seqId 倒序存储
+    fn write_local(seq_ids: &[&str]) {
+        let mut records = seq_ids.iter().map(|id| weapon(id)).collect::<Vec<_>>();
+        records.reverse();
+        testutil::write_record(
+            KEY,
+            &json!({ "character": {}, "weapon": { "weaponbox_1_0_1": records } }),
+        );
+    }
+
+    fn weapon_seq_ids(value: &Value) -> Vec<String> {
+        let mut ids = value["weapon"]["weaponbox_1_0_1"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|item| item["seqId"].as_str().map(String::from))
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    fn puts_to(stub: &HttpStub, path: &str) -> Vec<testutil::StubRequest> {
+        stub.requests()
+            .into_iter()
+            .filter(|request| request.method == "PUT" && request.path() == path)
+            .collect()
+    }
+
+    fn remote(state: &Arc<Mutex<DavState>>, path: &str) -> Value {
+        state.lock().unwrap().json(path).expect("remote file")
+    }
+
+    #[test]
+    fn weak_etags_do_not_become_if_match() {
+        let strong = RemoteFile {
+            path: "accounts/a.json".into(),
+            text: String::new(),
+            etag: Some("\"abc\"".into()),
+        };
+        assert!(matches!(
+            PutPrecondition::for_remote(Some(&strong)),
+            PutPrecondition::IfMatch(etag) if etag == "\"abc\""
+        ));
+        let weak = RemoteFile {
+            etag: Some("W/\"abc\"".into()),
+            ..strong
+        };
+        assert!(matches!(
+            PutPrecondition::for_remote(Some(&weak)),
+            PutPrecondition::None
+        ));
+        assert!(matches!(
+            PutPrecondition::for_remote(None),
+            PutPrecondition::IfNoneMatch
+        ));
+        assert!(matches!(
+            manifest_precondition(&ManifestLoadState::Invalid, Some("W/\"1\"".into())),
+            PutPrecondition::None
+        ));
+    }
+
+    #[tokio::test]
+    async fn first_upload_uses_if_none_match_then_if_match() {
+        let _guard = testutil::userdata();
+        let (stub, state) = testutil::dav_server();
+        setup(&stub, &["101"]);
+
+        let result = webdav_sync_account(None, None).await.unwrap();
+        assert_eq!(result.status, "uploaded");
+        for path in [ACCOUNT_PATH, MANIFEST_PATH] {
+            let puts = puts_to(&stub, path);
+            assert_eq!(puts.len(), 1, "{}", path);
+            assert_eq!(puts[0].header("if-none-match"), Some("*"));
+            assert_eq!(puts[0].header("if-match"), None);
+        }
+        let uploaded_etag = format!("\"{}\"", state.lock().unwrap().files[ACCOUNT_PATH].1);
+
+        write_local(&["101", "102"]);
+        webdav_sync_account(None, None).await.unwrap();
+        let puts = puts_to(&stub, ACCOUNT_PATH);
+        assert_eq!(puts.len(), 2);
+        assert_eq!(puts[1].header("if-match"), Some(uploaded_etag.as_str()));
+        assert_eq!(puts[1].header("if-none-match"), None);
+        assert_eq!(
+            weapon_seq_ids(&remote(&state, ACCOUNT_PATH)),
+            vec!["101", "102"]
+        );
+    }
+
+    #[tokio::test]
+    async fn concurrent_remote_write_is_redownloaded_and_merged() {
+        let _guard = testutil::userdata();
+        let (stub, state) = testutil::dav_server();
+        setup(&stub, &["101"]);
+        webdav_sync_account(None, None).await.unwrap();
+
+        // 其他设备在本机 GET 与 PUT 之间追加了 103
+        let mut other = remote(&state, ACCOUNT_PATH);
+        other["weapon"]["weaponbox_1_0_1"]
+            .as_array_mut()
+            .unwrap()
+            .insert(0, weapon("103"));
+        state
+            .lock()
+            .unwrap()
+            .replace_after_get
+            .insert(ACCOUNT_PATH.into(), other.to_string());
+        write_local(&["101", "102"]);
+
+        webdav_sync_account(None, None).await.unwrap();
+
+        let puts = puts_to(&stub, ACCOUNT_PATH);
+        assert_eq!(puts.len(), 3);
+        assert_ne!(puts[1].header("if-match"), puts[2].header("if-match"));
+        assert_eq!(
+            weapon_seq_ids(&remote(&state, ACCOUNT_PATH)),
+            vec!["101", "102", "103"]
+        );
+        assert_eq!(
+            weapon_seq_ids(&testutil::read_record(KEY)),
+            vec!["101", "102", "103"]
+        );
+    }
+
+    #[tokio::test]
+    async fn weak_etags_fall_back_to_unconditional_put() {
+        let _guard = testutil::userdata();
+        let (stub, state) = testutil::dav_server();
+        state.lock().unwrap().weak_etags = true;
+        setup(&stub, &["101"]);
+        webdav_sync_account(None, None).await.unwrap();
+
+        write_local(&["101", "102"]);
+        webdav_sync_account(None, None).await.unwrap();
+
+        let puts = puts_to(&stub, ACCOUNT_PATH);
+        assert_eq!(puts.len(), 2);
+        assert_eq!(puts[1].header("if-match"), None);
+        assert_eq!(puts[1].header("if-none-match"), None);
+        assert_eq!(
+            weapon_seq_ids(&remote(&state, ACCOUNT_PATH)),
+            vec!["101", "102"]
+        );
+        assert!(puts_to(&stub, MANIFEST_PATH)
+            .iter()
+            .all(|put| put.header("if-match").is_none()));
+    }
+
+    fn remote_paths(state: &Arc<Mutex<DavState>>) -> Vec<String> {
+        state.lock().unwrap().files.keys().cloned().collect()
+    }
+
+    #[tokio::test]
+    async fn encrypted_remote_paths_do_not_expose_account_key() {
+        let _guard = testutil::userdata();
+        let (stub, state) = testutil::dav_server();
+        setup(&stub, &["101"]);
+        webdav_sync_account(None, None).await.unwrap();
+
+        let result = webdav_set_encryption(Some("secret".into())).await.unwrap();
+        assert!(result.encrypted);
+        let paths = remote_paths(&state);
+        assert!(paths.iter().all(|path| !path.contains(KEY)), "{:?}", paths);
+        assert!(paths.iter().any(|path| path.starts_with("/ef/accounts/")));
+
+        write_local(&["101", "102"]);
+        assert_eq!(
+            webdav_sync_account(None, None).await.unwrap().status,
+            "uploaded"
+        );
+        let paths = remote_paths(&state);
+        assert!(paths.iter().all(|path| !path.contains(KEY)), "{:?}", paths);
+        let versions = webdav_list_versions(KEY.into()).await.unwrap();
+        assert!(!versions.is_empty());
+        assert!(versions.iter().all(|item| !item.path.contains(KEY)));
+
+        let accounts = webdav_list_restore_accounts().await.unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].key, KEY);
+        write_local(&[]);
+        webdav_restore_accounts(vec![KEY.into()]).await.unwrap();
+        assert_eq!(
+            weapon_seq_ids(&testutil::read_record(KEY)),
+            vec!["101", "102"]
+        );
+    }
+
+    #[tokio::test]
+    async fn disabling_encryption_restores_readable_paths() {
+        let _guard = testutil::userdata();
+        let (stub, state) = testutil::dav_server();
+        setup(&stub, &["101"]);
+        webdav_set_encryption(Some("secret".into())).await.unwrap();
+        webdav_sync_account(None, None).await.unwrap();
+        assert!(!state.lock().unwrap().files.contains_key(ACCOUNT_PATH));
+
+        webdav_set_encryption(None).await.unwrap();
+        let paths = remote_paths(&state);
+        assert!(
+            paths
+                .iter()
+                .all(|path| !path.starts_with("/ef/accounts/") || path == ACCOUNT_PATH),
+            "{:?}",
+            paths
+        );
+        assert_eq!(weapon_seq_ids(&remote(&state, ACCOUNT_PATH)), vec!["101"]);
+        assert_eq!(
+            remote(&state, MANIFEST_PATH)["accounts"][KEY]["path"],
+            "accounts/1001_r1.json"
+        );
+    }
+
+    const BATCH_KEYS: [&str; 3] = ["1001_r1", "1002_r1", "1003_r1"];
+
+    fn setup_batch(stub: &HttpStub) {
+        let users = BATCH_KEYS
+            .iter()
+            .map(|key| testutil::hypergryph_user(&key[..4], "r1"))
+            .collect::<Vec<_>>();
+        testutil::write_config(&json!({
+            "users": users,
+            "currentUser": KEY,
+            "webdav": {
+                "baseUrl": stub.base_url,
+                "username": "user",
+                "password": "pass",
+                "basePath": "/ef"
+            }
+        }));
+        for key in BATCH_KEYS {
+            write_batch_local(key, &["101"]);
+        }
+    }
+
+    fn write_batch_local(key: &str, seq_ids: &[&str]) {
+        let records = seq_ids
+            .iter()
+            .rev()
+            .map(|id| weapon(id))
+            .collect::<Vec<_>>();
+        testutil::write_record(
+            key,
+            &json!({ "character": {}, "weapon": { "weaponbox_1_0_1": records } }),
+        );
+    }
+
+    /// 从 skip 条请求之后统计 manifest 的 GET 与 PUT 次数
+    fn manifest_requests(stub: &HttpStub, skip: usize) -> (usize, usize) {
+        let requests = stub.requests();
+        let count = |method: &str| {
+            requests[skip..]
+                .iter()
+                .filter(|request| request.method == method && request.path() == MANIFEST_PATH)
+                .count()
+        };
+        (count("GET"), count("PUT"))
+    }
+
+    #[tokio::test]
+    async fn sync_all_reads_and_writes_manifest_once() {
+        let _guard = testutil::userdata();
+        let (stub, state) = testutil::dav_server();
+        setup_batch(&stub);
+
+        let result = webdav_sync_all().await.unwrap();
+        assert_eq!(result.total, BATCH_KEYS.len());
+        assert_eq!(result.results.len(), BATCH_KEYS.len());
+        assert!(result.results.iter().all(|item| item.manifest_updated));
+        assert_eq!(manifest_requests(&stub, 0), (1, 1));
+        let manifest = remote(&state, MANIFEST_PATH);
+        for key in BATCH_KEYS {
+            assert!(manifest["accounts"].get(key).is_some(), "{}", key);
+        }
+
+        let skip = stub.requests().len();
+        for key in BATCH_KEYS {
+            write_batch_local(key, &["101", "102"]);
+        }
+        let result = webdav_sync_all().await.unwrap();
+        assert!(result.results.iter().all(|item| item.status == "uploaded"));
+        assert_eq!(manifest_requests(&stub, skip), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn sync_all_keeps_manifest_entries_when_one_account_fails() {
+        let _guard = testutil::userdata();
+        let (stub, state) = testutil::dav_server();
+        setup_batch(&stub);
+        webdav_sync_all().await.unwrap();
+        let before = remote(&state, MANIFEST_PATH);
+
+        state
+            .lock()
+            .unwrap()
+            .write("/ef/accounts/1002_r1.json", "{ broken".into());
+        for key in BATCH_KEYS {
+            write_batch_local(key, &["101", "102"]);
+        }
+        let skip = stub.requests().len();
+        let result = webdav_sync_all().await.unwrap();
+
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(result.failed[0].account_key, "1002_r1");
+        assert!(result.failed[0].message.contains("结构损坏"));
+        assert_eq!(result.results.len(), 2);
+        assert!(result.results.iter().all(|item| item.manifest_updated));
+        assert_eq!(manifest_requests(&stub, skip), (1, 1));
+
+        let manifest = remote(&state, MANIFEST_PATH);
+        for key in ["1001_r1", "1003_r1"] {
+            assert_ne!(
+                manifest["accounts"][key]["contentHash"], before["accounts"][key]["contentHash"],
+                "{}",
+                key
+            );
+        }
+        assert_eq!(
+            manifest["accounts"]["1002_r1"],
+            before["accounts"]["1002_r1"]
+        );
+    }
+
+    const HISTORY_DIR: &str = "/ef/history/1001_r1";
+
+    fn history_files(state: &Arc<Mutex<DavState>>) -> Vec<String> {
+        remote_paths(state)
+            .into_iter()
+            .filter(|path| path.starts_with(&format!("{}/", HISTORY_DIR)))
+            .collect()
+    }
+
+    /// 每次上传都生成新的历史版本；版本文件名精确到毫秒，两次同步之间稍作等待
+    async fn upload(seq_ids: &[&str]) {
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        write_local(seq_ids);
+        webdav_sync_account(None, None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn history_is_pruned_to_limit() {
+        let _guard = testutil::userdata();
+        let (stub, state) = testutil::dav_server();
+        setup(&stub, &["101"]);
+        webdav_sync_account(None, None).await.unwrap();
+        let first = history_files(&state);
+        assert_eq!(first.len(), 1);
+
+        let ids = (102..102 + HISTORY_LIMIT)
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>();
+        for count in 1..=ids.len() {
+            let mut seq_ids = vec!["101"];
+            seq_ids.extend(ids[..count].iter().map(String::as_str));
+            upload(&seq_ids).await;
+        }
+
+        let files = history_files(&state);
+        assert_eq!(files.len(), HISTORY_LIMIT);
+        assert!(!files.contains(&first[0]));
+        let history = remote(&state, MANIFEST_PATH)["accounts"][KEY]["history"]
+            .as_array()
+            .unwrap()
+            .len();
+        assert_eq!(history, HISTORY_LIMIT);
+
+        let versions = webdav_list_versions(KEY.into()).await.unwrap();
+        assert_eq!(versions.len(), HISTORY_LIMIT);
+        assert!(versions.windows(2).all(|pair| pair[0].path > pair[1].path));
+        assert!(versions[0].current);
+        assert!(versions[1..].iter().all(|item| !item.current));
+    }
+
+    #[tokio::test]
+    async fn list_versions_includes_unregistered_history_files() {
+        let _guard = testutil::userdata();
+        let (stub, state) = testutil::dav_server();
+        setup(&stub, &["101"]);
+        webdav_sync_account(None, None).await.unwrap();
+        let stray = format!("{}/20200101T000000000Z.json", HISTORY_DIR);
+        let body = remote(&state, ACCOUNT_PATH).to_string();
+        state.lock().unwrap().write(&stray, body);
+
+        let versions = webdav_list_versions(KEY.into()).await.unwrap();
+        assert_eq!(versions.len(), 2);
+        let last = versions.last().unwrap();
+        assert_eq!(format!("/ef/{}", last.path), stray);
+        assert!(last.content_hash.is_empty());
+        assert!(!last.current);
+    }
+
+    #[tokio::test]
+    async fn restored_version_is_uploaded_with_if_match() {
+        let _guard = testutil::userdata();
+        let (stub, state) = testutil::dav_server();
+        setup(&stub, &["101"]);
+        webdav_sync_account(None, None).await.unwrap();
+        upload(&["101", "102"]).await;
+
+        let versions = webdav_list_versions(KEY.into()).await.unwrap();
+        let oldest = versions.last().unwrap().path.clone();
+        assert!(
+            webdav_restore_version(KEY.into(), "history/other/a.json".into())
+                .await
+                .is_err()
+        );
+        webdav_restore_version(KEY.into(), oldest).await.unwrap();
+        assert_eq!(weapon_seq_ids(&testutil::read_record(KEY)), vec!["101"]);
+
+        let etag = format!("\"{}\"", state.lock().unwrap().files[ACCOUNT_PATH].1);
+        let result = webdav_sync_account(None, None).await.unwrap();
+        assert_eq!(result.status, "uploaded");
+        let puts = puts_to(&stub, ACCOUNT_PATH);
+        assert_eq!(puts.last().unwrap().header("if-match"), Some(etag.as_str()));
+        assert_eq!(weapon_seq_ids(&remote(&state, ACCOUNT_PATH)), vec!["101"]);
+    }
+
+    #[tokio::test]
+    async fn restored_version_does_not_overwrite_a_concurrent_remote_write() {
+        let _guard = testutil::userdata();
+        let (stub, state) = testutil::dav_server();
+        setup(&stub, &["101"]);
+        webdav_sync_account(None, None).await.unwrap();
+        upload(&["101", "102"]).await;
+        let oldest = webdav_list_versions(KEY.into())
+            .await
+            .unwrap()
+            .last()
+            .unwrap()
+            .path
+            .clone();
+        webdav_restore_version(KEY.into(), oldest).await.unwrap();
+
+        // 其他设备在本机 GET 与 PUT 之间追加了 103
+        let mut other = remote(&state, ACCOUNT_PATH);
+        other["weapon"]["weaponbox_1_0_1"]
+            .as_array_mut()
+            .unwrap()
+            .insert(0, weapon("103"));
+        state
+            .lock()
+            .unwrap()
+            .replace_after_get
+            .insert(ACCOUNT_PATH.into(), other.to_string());
+        let before = puts_to(&stub, ACCOUNT_PATH).len();
+
+        webdav_sync_account(None, None).await.unwrap();
+        let puts = puts_to(&stub, ACCOUNT_PATH);
+        assert_eq!(puts.len(), before + 2);
+        assert_ne!(
+            puts[before].header("if-match"),
+            puts[before + 1].header("if-match")
+        );
+        assert!(weapon_seq_ids(&remote(&state, ACCOUNT_PATH)).contains(&"103".to_string()));
+    }
+
+    #[tokio::test]
+    async fn restore_rejects_versions_that_do_not_match_the_manifest_hash() {
+        let _guard = testutil::userdata();
+        let (stub, state) = testutil::dav_server();
+        setup(&stub, &["101"]);
+        webdav_sync_account(None, None).await.unwrap();
+        let path = webdav_list_versions(KEY.into()).await.unwrap()[0]
+            .path
+            .clone();
+
+        let mut tampered = remote(&state, ACCOUNT_PATH);
+        tampered["weapon"]["weaponbox_1_0_1"]
+            .as_array_mut()
+            .unwrap()
+            .insert(0, weapon("999"));
+        state
+            .lock()
+            .unwrap()
+            .write(&format!("/ef/{}", path), tampered.to_string());
+
+        let error = webdav_restore_version(KEY.into(), path).await.unwrap_err();
+        assert!(error.contains("哈希"), "{}", error);
+        assert_eq!(weapon_seq_ids(&testutil::read_record(KEY)), vec!["101"]);
+    }
+
+    fn raw_webdav_config() -> Value {
+        let text = fs::read_to_string(crate::get_config_path().unwrap()).unwrap();
+        serde_json::from_str::<Value>(&text).unwrap()["webdav"].clone()
+    }
+
+    #[tokio::test]
+    async fn config_keeps_derived_key_instead_of_passphrase() {
+        let _guard = testutil::userdata();
+        let (stub, _state) = testutil::dav_server();
+        setup(&stub, &["101"]);
+        webdav_set_encryption(Some("secret".into())).await.unwrap();
+        let saved = raw_webdav_config();
+        assert!(saved.get("encryptionPassphrase").is_none());
+        assert!(!saved.to_string().contains("\"secret\""));
+        let stored = saved["encryptionKey"].clone();
+        assert!(stored.is_object());
+
+        // 另一台设备：未保存密钥时需要输入口令
+        let mut config = load_config_data().unwrap();
+        config.webdav.encryption_key = None;
+        save_config_data(&config).unwrap();
+        assert!(!webdav_encryption_status().await.unwrap().unlocked);
+        assert!(webdav_unlock_encryption("wrong".into()).await.is_err());
+        assert!(
+            webdav_unlock_encryption("secret".into())
+                .await
+                .unwrap()
+                .unlocked
+        );
+        assert_eq!(raw_webdav_config()["encryptionKey"], stored);
+        assert_eq!(
+            webdav_sync_account(None, None).await.unwrap().status,
+            "uploaded"
+        );
+    }
+
+    #[tokio::test]
+    async fn legacy_plaintext_passphrase_is_replaced_after_sync() {
+        let _guard = testutil::userdata();
+        let (stub, _state) = testutil::dav_server();
+        setup(&stub, &["101"]);
+        webdav_set_encryption(Some("secret".into())).await.unwrap();
+        let stored = raw_webdav_config()["encryptionKey"].clone();
+
+        let mut config = load_config_data().unwrap();
+        config.webdav.encryption_key = None;
+        config.webdav.encryption_passphrase = "secret".into();
+        save_config_data(&config).unwrap();
+        assert_eq!(raw_webdav_config()["encryptionPassphrase"], "secret");
+
+        webdav_sync_account(None, None).await.unwrap();
+        let saved = raw_webdav_config();
+        assert!(saved.get("encryptionPassphrase").is_none());
+        assert_eq!(saved["encryptionKey"], stored);
+    }
+
+    fn config_state(key: &str) -> Option<WebDavStateItem> {
+        load_config_data().unwrap().webdav_state.get(key).cloned()
+    }
+
+    #[tokio::test]
+    async fn deleted_account_stays_deleted_until_reupload() {
+        let _guard = testutil::userdata();
+        let (stub, state) = testutil::dav_server();
+        setup(&stub, &["101"]);
+        webdav_sync_account(None, None).await.unwrap();
+
+        webdav_delete_account(KEY.into()).await.unwrap();
+        assert!(!state.lock().unwrap().files.contains_key(ACCOUNT_PATH));
+        assert!(!config_state(KEY).unwrap().deleted_at.is_empty());
+
+        let error = webdav_sync_account(None, None).await.unwrap_err();
+        assert!(error.contains("从远端删除"), "{}", error);
+        let batch = webdav_sync_all().await.unwrap();
+        assert_eq!(batch.skipped, 1);
+        assert!(batch.results.is_empty());
+        // manifest 重建后删除标记丢失，仍以本地删除记录为准
+        state.lock().unwrap().files.remove(MANIFEST_PATH);
+        assert!(webdav_sync_account(None, None).await.is_err());
+        assert!(puts_to(&stub, ACCOUNT_PATH).len() == 1);
+
+        let result = webdav_sync_account(None, Some(true)).await.unwrap();
+        assert_eq!(result.status, "uploaded");
+        assert!(config_state(KEY).unwrap().deleted_at.is_empty());
+        let manifest = remote(&state, MANIFEST_PATH);
+        assert!(manifest["accounts"].get(KEY).is_some());
+        assert!(manifest["tombstones"].get(KEY).is_none());
+        assert_eq!(
+            webdav_sync_account(None, None).await.unwrap().status,
+            "noop"
+        );
+    }
+
+    #[tokio::test]
+    async fn account_without_local_state_does_not_clear_remote_tombstone() {
+        let _guard = testutil::userdata();
+        let (stub, state) = testutil::dav_server();
+        setup(&stub, &["101"]);
+        webdav_sync_account(None, None).await.unwrap();
+        webdav_delete_account(KEY.into()).await.unwrap();
+
+        // 另一台设备：本地有同一账号的数据，但没有同步状态
+        let mut config = load_config_data().unwrap();
+        config.webdav_state.clear();
+        save_config_data(&config).unwrap();
+        assert!(webdav_sync_account(None, None).await.is_err());
+        assert_eq!(webdav_sync_all().await.unwrap().skipped, 1);
+        assert!(!state.lock().unwrap().files.contains_key(ACCOUNT_PATH));
+        assert!(remote(&state, MANIFEST_PATH)["tombstones"]
+            .get(KEY)
+            .is_some());
+
+        let config = load_config_data().unwrap();
+        let webdav = extract_webdav_config(&config).unwrap();
+        let client = SyncClient::new(&webdav, &config.network).unwrap();
+        let bundle = build_local_bundle(&config.users[0]).unwrap();
+        let warning = update_manifest_with_bundles(
+            &client,
+            &[ManifestUpdate {
+                bundle,
+                version: None,
+                reupload: false,
+            }],
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(warning.unwrap().contains(KEY));
+        let manifest = remote(&state, MANIFEST_PATH);
+        assert!(manifest["accounts"].get(KEY).is_none());
+        assert!(manifest["tombstones"].get(KEY).is_some());
+    }
+
+    #[tokio::test]
+    async fn prune_skips_recently_modified_orphans() {
+        let _guard = testutil::userdata();
+        let (stub, state) = testutil::dav_server();
+        setup(&stub, &["101"]);
+        webdav_sync_account(None, None).await.unwrap();
+        {
+            let mut state = state.lock().unwrap();
+            state.write_at(
+                "/ef/accounts/old.json",
+                "{}".into(),
+                "Tue, 01 Sep 2026 08:00:00 GMT",
+            );
+            let now = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+            state.write_at("/ef/accounts/writing.json", "{}".into(), &now);
+        }
+
+        let result = webdav_prune_remote(None).await.unwrap();
+        assert_eq!(result.orphans, vec!["accounts/old.json"]);
+        assert_eq!(result.deleted, vec!["accounts/old.json"]);
+        assert_eq!(result.recent, vec!["accounts/writing.json"]);
+        let state = state.lock().unwrap();
+        assert!(!state.files.contains_key("/ef/accounts/old.json"));
+        assert!(state.files.contains_key("/ef/accounts/writing.json"));
+        assert!(state.files.contains_key(ACCOUNT_PATH));
+    }
+}
@@ -0,0 +1,1185 @@
+use crate::interchange::merge_bundle_into_local;
+use crate::stats::{
+    annotate_account, is_off_banner, load_account_pulls, local_datetime, parse_gacha_ts,
+    parse_pull, summarize_kind, FreePullRule, GachaKind, PityPull, PityRules, PoolCatalog,
+    JOINT_POOL_KEY, SPECIAL_POOL_KEY,
+};
+use crate::webdav::{
+    build_local_bundle, get_user_key, load_config_data, AccountBundle, RecordConflict,
+};
+use calamine::{Data, Reader, Xlsx};
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+use rust_xlsxwriter::{
+    Color, ConditionalFormatFormula, ExcelDateTime, Format, FormatAlign, Workbook, Worksheet,
+    XlsxError,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+use tauri::command;
+
+pub(crate) const CHARACTER_SHEET_PREFIX: &str = "角色";
+pub(crate) const WEAPON_SHEET_PREFIX: &str = "武器";
+const SUMMARY_SHEET_NAME: &str = "汇总";
+// 前端 useExcelExport.ts 导出的工作表名
+const LEGACY_CHARACTER_SHEET: &str = "角色记录";
+const LEGACY_WEAPON_SHEET: &str = "武器记录";
+// 缺少 seqId 的记录按时间顺序生成的占位 seqId 前缀，非纯数字不会影响增量同步的水位线
+const SYNTHETIC_SEQID_PREFIX: &str = "xlsx";
+const STANDARD_POOL_KEY: &str = "E_CharacterGachaPoolType_Standard";
+const BEGINNER_POOL_KEY: &str = "E_CharacterGachaPoolType_Beginner";
+const MAX_SHEET_NAME_LEN: usize = 31;
+
+// 表头与列宽，导入时按表头名称匹配列
+const RECORD_COLUMNS: [(&str, f64); 11] = [
+    ("时间", 20.0),
+    ("名称", 24.0),
+    ("星级", 8.0),
+    ("垫抽", 8.0),
+    ("卡池名", 24.0),
+    ("卡池 ID", 20.0),
+    ("是否 NEW", 10.0),
+    ("是否 UP", 10.0),
+    ("是否歪", 10.0),
+    ("是否为加急招募", 16.0),
+    ("seqId", 24.0),
+];
+
+const SUMMARY_COLUMNS: [(&str, f64); 11] = [
+    ("类型", 8.0),
+    ("卡池名", 24.0),
+    ("卡池 ID", 20.0),
+    ("总抽数", 10.0),
+    ("免费抽数", 10.0),
+    ("6★", 8.0),
+    ("5★", 8.0),
+    ("UP 6★", 8.0),
+    ("歪", 8.0),
+    ("6★ 出货率", 12.0),
+    ("平均出货", 10.0),
+];
+
+const RARITY6_TEXT_COLOR: u32 = 0xFF8904;
+const RARITY6_FILL_COLOR: u32 = 0xFFF1E0;
+const RARITY5_TEXT_COLOR: u32 = 0xB08800;
+const RARITY5_FILL_COLOR: u32 = 0xFFF9DB;
+const HEADER_TEXT_COLOR: u32 = 0x1F4E79;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XlsxExportOptions {
+    #[serde(default = "default_include_summary")]
+    include_summary: bool,
+    #[serde(default)]
+    free_pull_rules: HashMap<String, FreePullRule>,
+}
+
+impl Default for XlsxExportOptions {
+    fn default() -> Self {
+        Self {
+            include_summary: default_include_summary(),
+            free_pull_rules: HashMap::new(),
+        }
+    }
+}
+
+fn default_include_summary() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XlsxExportResult {
+    path: String,
+    sheet_count: usize,
+    character_count: usize,
+    weapon_count: usize,
+}
+
+struct PoolSheet<'a> {
+    kind: GachaKind,
+    pool_id: String,
+    pool_name: String,
+    items: Vec<&'a PityPull>,
+}
+
+fn xlsx_error(err: XlsxError) -> String {
+    err.to_string()
+}
+
+pub(crate) fn kind_label(kind: GachaKind) -> &'static str {
+    match kind {
+        GachaKind::Character => CHARACTER_SHEET_PREFIX,
+        GachaKind::Weapon => WEAPON_SHEET_PREFIX,
+    }
+}
+
+/// 工作表名不能包含 []:*?/\ 且最长 31 个字符，重名时追加序号
+fn unique_sheet_name(base: &str, used: &mut HashSet<String>) -> String {
+    let cleaned = base
+        .chars()
+        .map(|c| match c {
+            '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+            _ => c,
+        })
+        .collect::<String>();
+    let cleaned = cleaned.trim_matches('\'').trim();
+
+    let mut index = 1;
+    loop {
+        let suffix = if index == 1 {
+            String::new()
+        } else {
+            format!(" ({})", index)
+        };
+        let keep = MAX_SHEET_NAME_LEN - suffix.chars().count();
+        let name = format!(
+            "{}{}",
+            cleaned.chars().take(keep).collect::<String>(),
+            suffix
+        );
+        if used.insert(name.to_lowercase()) {
+            return name;
+        }
+        index += 1;
+    }
+}
+
+fn excel_datetime(ts: i64) -> Option<ExcelDateTime> {
+    let local = local_datetime(ts)?;
+    ExcelDateTime::from_ymd(local.year() as u16, local.month() as u8, local.day() as u8)
+        .and_then(|date| date.and_hms(local.hour() as u16, local.minute() as u8, local.second()))
+        .ok()
+}
+
+/// 按卡池 ID 分组，卡池按首次抽取时间排列
+fn group_pool_sheets<'a>(items: &'a [PityPull], catalog: &PoolCatalog) -> Vec<PoolSheet<'a>> {
+    let mut sheets: Vec<PoolSheet<'a>> = Vec::new();
+    for item in items {
+        if let Some(sheet) = sheets
+            .iter_mut()
+            .find(|sheet| sheet.kind == item.pull.kind && sheet.pool_id == item.pull.pool_id)
+        {
+            sheet.items.push(item);
+            continue;
+        }
+        sheets.push(PoolSheet {
+            kind: item.pull.kind,
+            pool_id: item.pull.pool_id.clone(),
+            pool_name: catalog
+                .get(&item.pull.pool_id)
+                .map(|info| info.pool_name.clone())
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| item.pull.pool_name.clone()),
+            items: vec![item],
+        });
+    }
+    sheets.sort_by_key(|sheet| sheet.kind);
+    sheets
+}
+
+fn write_header(
+    worksheet: &mut Worksheet,
+    columns: &[(&str, f64)],
+    format: &Format,
+) -> Result<(), String> {
+    for (col, (label, width)) in columns.iter().enumerate() {
+        let col = col as u16;
+        worksheet
+            .write_string_with_format(0, col, *label, format)
+            .map_err(xlsx_error)?;
+        worksheet
+            .set_column_width(col, *width)
+            .map_err(xlsx_error)?;
+    }
+    worksheet.set_freeze_panes(1, 0).map_err(xlsx_error)?;
+    Ok(())
+}
+
+fn add_rarity_formatting(worksheet: &mut Worksheet, last_row: u32) -> Result<(), String> {
+    if last_row == 0 {
+        return Ok(());
+    }
+    let last_col = (RECORD_COLUMNS.len() - 1) as u16;
+    let rules = [
+        (6, RARITY6_TEXT_COLOR, RARITY6_FILL_COLOR),
+        (5, RARITY5_TEXT_COLOR, RARITY5_FILL_COLOR),
+    ];
+    for (rarity, text_color, fill_color) in rules {
+        let format = Format::new()
+            .set_font_color(Color::RGB(text_color))
+            .set_background_color(Color::RGB(fill_color))
+            .set_bold();
+        let rule = ConditionalFormatFormula::new()
+            .set_rule(format!("=$C2={}", rarity).as_str())
+            .set_format(format);
+        worksheet
+            .add_conditional_format(1, 0, last_row, last_col, &rule)
+            .map_err(xlsx_error)?;
+    }
+    Ok(())
+}
+
+fn write_pool_sheet(
+    worksheet: &mut Worksheet,
+    sheet: &PoolSheet,
+    catalog: &PoolCatalog,
+    header_format: &Format,
+    datetime_format: &Format,
+) -> Result<(), String> {
+    write_header(worksheet, &RECORD_COLUMNS, header_format)?;
+
+    // 与前端导出一致，最新的记录在最上方
+    for (index, item) in sheet.items.iter().rev().enumerate() {
+        let row = index as u32 + 1;
+        let pull = &item.pull;
+        match excel_datetime(pull.gacha_ts) {
+            Some(datetime) => worksheet
+                .write_datetime_with_format(row, 0, &datetime, datetime_format)
+                .map_err(xlsx_error)?,
+            None => worksheet
+                .write_string(row, 0, pull.gacha_ts.to_string())
+                .map_err(xlsx_error)?,
+        };
+        worksheet
+            .write_string(row, 1, &pull.item_name)
+            .map_err(xlsx_error)?;
+        worksheet
+            .write_number(row, 2, pull.rarity)
+            .map_err(xlsx_error)?;
+        if item.pity > 0 {
+            worksheet
+                .write_number(row, 3, item.pity)
+                .map_err(xlsx_error)?;
+        }
+        worksheet
+            .write_string(row, 4, &sheet.pool_name)
+            .map_err(xlsx_error)?;
+        worksheet
+            .write_string(row, 5, &pull.pool_id)
+            .map_err(xlsx_error)?;
+        worksheet
+            .write_boolean(row, 6, pull.is_new)
+            .map_err(xlsx_error)?;
+        worksheet
+            .write_boolean(row, 7, item.is_up)
+            .map_err(xlsx_error)?;
+        worksheet
+            .write_boolean(row, 8, is_off_banner(item, catalog))
+            .map_err(xlsx_error)?;
+        worksheet
+            .write_boolean(row, 9, pull.is_free)
+            .map_err(xlsx_error)?;
+        worksheet
+            .write_string(row, 10, &pull.seq_id)
+            .map_err(xlsx_error)?;
+    }
+
+    worksheet
+        .autofilter(
+            0,
+            0,
+            sheet.items.len() as u32,
+            (RECORD_COLUMNS.len() - 1) as u16,
+        )
+        .map_err(xlsx_error)?;
+    add_rarity_formatting(worksheet, sheet.items.len() as u32)
+}
+
+fn write_summary_row(
+    worksheet: &mut Worksheet,
+    row: u32,
+    kind: GachaKind,
+    (pool_name, pool_id): (&str, &str),
+    items: &[PityPull],
+    catalog: &PoolCatalog,
+    percent_format: &Format,
+) -> Result<(), String> {
+    let stats = summarize_kind(kind, items);
+    let items = items
+        .iter()
+        .filter(|item| item.pull.kind == kind)
+        .collect::<Vec<_>>();
+    let up_count = items.iter().filter(|item| item.is_up).count();
+    let off_count = items
+        .iter()
+        .filter(|item| is_off_banner(item, catalog))
+        .count();
+
+    worksheet
+        .write_string(row, 0, kind_label(kind))
+        .map_err(xlsx_error)?;
+    worksheet
+        .write_string(row, 1, pool_name)
+        .map_err(xlsx_error)?;
+    worksheet
+        .write_string(row, 2, pool_id)
+        .map_err(xlsx_error)?;
+    worksheet
+        .write_number(row, 3, stats.total_pulls)
+        .map_err(xlsx_error)?;
+    worksheet
+        .write_number(row, 4, stats.free_pulls)
+        .map_err(xlsx_error)?;
+    worksheet
+        .write_number(row, 5, stats.count6)
+        .map_err(xlsx_error)?;
+    worksheet
+        .write_number(row, 6, stats.count5)
+        .map_err(xlsx_error)?;
+    worksheet
+        .write_number(row, 7, up_count as u32)
+        .map_err(xlsx_error)?;
+    worksheet
+        .write_number(row, 8, off_count as u32)
+        .map_err(xlsx_error)?;
+    worksheet
+        .write_number_with_format(row, 9, stats.rate6, percent_format)
+        .map_err(xlsx_error)?;
+    if let Some(avg_pity) = stats.avg_pity {
+        worksheet
+            .write_number(row, 10, (avg_pity * 100.0).round() / 100.0)
+            .map_err(xlsx_error)?;
+    }
+    Ok(())
+}
+
+fn write_summary_sheet(
+    worksheet: &mut Worksheet,
+    items: &[PityPull],
+    sheets: &[PoolSheet],
+    catalog: &PoolCatalog,
+    header_format: &Format,
+) -> Result<(), String> {
+    write_header(worksheet, &SUMMARY_COLUMNS, header_format)?;
+    let percent_format = Format::new().set_num_format("0.00%");
+    let total_format = Format::new().set_bold();
+
+    let mut row = 1;
+    for kind in [GachaKind::Character, GachaKind::Weapon] {
+        write_summary_row(
+            worksheet,
+            row,
+            kind,
+            ("全部", ""),
+            items,
+            catalog,
+            &percent_format,
+        )?;
+        worksheet
+            .set_row_format(row, &total_format)
+            .map_err(xlsx_error)?;
+        row += 1;
+
+        for sheet in sheets.iter().filter(|sheet| sheet.kind == kind) {
+            let pool_items = sheet
+                .items
+                .iter()
+                .map(|item| (*item).clone())
+                .collect::<Vec<_>>();
+            write_summary_row(
+                worksheet,
+                row,
+                kind,
+                (&sheet.pool_name, &sheet.pool_id),
+                &pool_items,
+                catalog,
+                &percent_format,
+            )?;
+            row += 1;
+        }
+    }
+    Ok(())
+}
+
+fn build_workbook(
+    user_key: &str,
+    options: &XlsxExportOptions,
+) -> Result<(Workbook, XlsxExportResult), String> {
+    let account = load_account_pulls(user_key)?;
+    let catalog = PoolCatalog::load()?;
+    let rules = PityRules::new(options.free_pull_rules.clone());
+    let items = annotate_account(&account, None, &catalog, &rules);
+    if items.is_empty() {
+        return Err("No gacha records to export".into());
+    }
+
+    let sheets = group_pool_sheets(&items, &catalog);
+    let header_format = Format::new()
+        .set_bold()
+        .set_font_color(Color::RGB(HEADER_TEXT_COLOR))
+        .set_align(FormatAlign::Center);
+    let datetime_format = Format::new().set_num_format("yyyy-mm-dd hh:mm:ss");
+
+    let mut workbook = Workbook::new();
+    let mut used_names = HashSet::new();
+
+    if options.include_summary {
+        let worksheet = workbook.add_worksheet();
+        worksheet
+            .set_name(unique_sheet_name(SUMMARY_SHEET_NAME, &mut used_names))
+            .map_err(xlsx_error)?;
+        write_summary_sheet(worksheet, &items, &sheets, &catalog, &header_format)?;
+    }
+
+    for sheet in sheets.iter() {
+        let name = unique_sheet_name(
+            &format!("{}-{}", kind_label(sheet.kind), sheet.pool_name),
+            &mut used_names,
+        );
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(name).map_err(xlsx_error)?;
+        write_pool_sheet(worksheet, sheet, &catalog, &header_format, &datetime_format)?;
+    }
+
+    let count_kind = |kind: GachaKind| items.iter().filter(|item| item.pull.kind == kind).count();
+    let result = XlsxExportResult {
+        path: String::new(),
+        sheet_count: sheets.len() + usize::from(options.include_summary),
+        character_count: count_kind(GachaKind::Character),
+        weapon_count: count_kind(GachaKind::Weapon),
+    };
+    Ok((workbook, result))
+}
+
+#[command]
+pub fn export_xlsx(
+    user_key: String,
+    path: String,
+    options: Option<XlsxExportOptions>,
+) -> Result<XlsxExportResult, String> {
+    let path = path.trim().to_string();
+    if path.is_empty() {
+        return Err("Export path cannot be empty".into());
+    }
+    let options = options.unwrap_or_default();
+    let (mut workbook, mut result) = build_workbook(user_key.trim(), &options)?;
+    workbook.save(&path).map_err(xlsx_error)?;
+    result.path = path;
+    Ok(result)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XlsxImportResult {
+    dry_run: bool,
+    user_key: String,
+    sheets: Vec<String>,
+    character_rows: usize,
+    weapon_rows: usize,
+    skipped_rows: usize,
+    reconstructed: usize,
+    character_added: usize,
+    weapon_added: usize,
+    duplicates: usize,
+    conflicts: Vec<RecordConflict>,
+}
+
+struct ImportedRow {
+    kind: GachaKind,
+    gacha_ts: i64,
+    item_name: String,
+    rarity: u8,
+    pool_id: String,
+    pool_name: String,
+    is_new: bool,
+    is_free: bool,
+    seq_id: Option<String>,
+}
+
+struct HeaderIndex {
+    time: usize,
+    name: usize,
+    rarity: usize,
+    pool_id: usize,
+    pool_name: Option<usize>,
+    is_new: Option<usize>,
+    is_free: Option<usize>,
+    seq_id: Option<usize>,
+}
+
+impl HeaderIndex {
+    fn from_row(row: &[Data]) -> Option<Self> {
+        let find = |label: &str| {
+            row.iter()
+                .position(|cell| cell_text(cell).replace(' ', "") == label.replace(' ', ""))
+        };
+        Some(Self {
+            time: find("时间")?,
+            name: find("名称")?,
+            rarity: find("星级")?,
+            pool_id: find("卡池 ID")?,
+            pool_name: find("卡池名"),
+            is_new: find("是否 NEW"),
+            is_free: find("是否为加急招募"),
+            seq_id: find("seqId"),
+        })
+    }
+}
+
+fn sheet_kind(name: &str) -> Option<GachaKind> {
+    let name = name.trim();
+    if name == LEGACY_CHARACTER_SHEET || name.starts_with(&format!("{}-", CHARACTER_SHEET_PREFIX)) {
+        Some(GachaKind::Character)
+    } else if name == LEGACY_WEAPON_SHEET || name.starts_with(&format!("{}-", WEAPON_SHEET_PREFIX))
+    {
+        Some(GachaKind::Weapon)
+    } else {
+        None
+    }
+}
+
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::String(text) | Data::DateTimeIso(text) => text.trim().to_string(),
+        Data::Int(value) => value.to_string(),
+        Data::Float(value) if value.fract() == 0.0 => format!("{}", *value as i64),
+        Data::Float(value) => value.to_string(),
+        Data::Bool(flag) => flag.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn cell_bool(cell: Option<&Data>) -> bool {
+    match cell {
+        Some(Data::Bool(flag)) => *flag,
+        Some(cell) => matches!(
+            cell_text(cell).to_lowercase().as_str(),
+            "是" | "true" | "1" | "yes"
+        ),
+        None => false,
+    }
+}
+
+/// Excel 序列日期以 1899-12-30 为起点，按本地时区还原为毫秒时间戳
+fn serial_to_ts(serial: f64) -> Option<i64> {
+    let base = NaiveDate::from_ymd_opt(1899, 12, 30)?.and_hms_opt(0, 0, 0)?;
+    let millis = (serial * 86_400_000.0).round() as i64;
+    let naive = base.checked_add_signed(chrono::Duration::milliseconds(millis))?;
+    naive_to_ts(naive)
+}
+
+fn naive_to_ts(naive: NaiveDateTime) -> Option<i64> {
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|time| time.timestamp_millis())
+}
+
+fn cell_ts(cell: &Data) -> Option<i64> {
+    match cell {
+        Data::DateTime(value) => serial_to_ts(value.as_f64()),
+        Data::Float(value) if *value < 1_000_000.0 => serial_to_ts(*value),
+        _ => {
+            let text = cell_text(cell);
+            parse_gacha_ts(&text).or_else(|| {
+                [
+                    "%Y-%m-%d %H:%M:%S",
+                    "%Y/%m/%d %H:%M:%S",
+                    "%Y-%m-%dT%H:%M:%S",
+                ]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(&text, format).ok())
+                .and_then(naive_to_ts)
+            })
+        }
+    }
+}
+
+fn parse_sheet_rows(kind: GachaKind, rows: &[&[Data]]) -> (Vec<ImportedRow>, usize) {
+    let mut result = Vec::new();
+    let mut skipped = 0;
+    let Some((header, body)) = rows.split_first() else {
+        return (result, skipped);
+    };
+    let Some(index) = HeaderIndex::from_row(header) else {
+        return (result, body.len());
+    };
+
+    for row in body {
+        let get = |col: usize| row.get(col).unwrap_or(&Data::Empty);
+        let gacha_ts = cell_ts(get(index.time));
+        let item_name = cell_text(get(index.name));
+        let rarity = cell_text(get(index.rarity)).parse::<u8>().ok();
+        let pool_id = cell_text(get(index.pool_id));
+        let (Some(gacha_ts), Some(rarity)) = (gacha_ts, rarity) else {
+            if row.iter().any(|cell| !cell_text(cell).is_empty()) {
+                skipped += 1;
+            }
+            continue;
+        };
+        if item_name.is_empty() || pool_id.is_empty() {
+            skipped += 1;
+            continue;
+        }
+        result.push(ImportedRow {
+            kind,
+            gacha_ts,
+            item_name,
+            rarity,
+            pool_id,
+            pool_name: index
+                .pool_name
+                .map(|col| cell_text(get(col)))
+                .unwrap_or_default(),
+            is_new: cell_bool(index.is_new.map(get)),
+            is_free: kind == GachaKind::Character && cell_bool(index.is_free.map(get)),
+            seq_id: index
+                .seq_id
+                .map(|col| cell_text(get(col)))
+                .filter(|value| !value.is_empty()),
+        });
+    }
+    (result, skipped)
+}
+
+fn character_pool_key(pool_id: &str, known: &HashMap<String, String>) -> String {
+    if let Some(pool_key) = known.get(pool_id) {
+        return pool_key.clone();
+    }
+    let prefix = pool_id.split('_').next().unwrap_or("");
+    match prefix {
+        "special" => SPECIAL_POOL_KEY.into(),
+        "joint" => JOINT_POOL_KEY.into(),
+        "beginner" => BEGINNER_POOL_KEY.into(),
+        _ => STANDARD_POOL_KEY.into(),
+    }
+}
+
+fn find_item_id(catalog: &PoolCatalog, pool_id: &str, name: &str) -> String {
+    let in_pool = catalog
+        .get(pool_id)
+        .and_then(|entry| entry.all.iter().find(|item| item.name == name));
+    in_pool
+        .or_else(|| {
+            catalog
+                .entries()
+                .iter()
+                .flat_map(|entry| entry.all.iter())
+                .find(|item| item.name == name)
+        })
+        .map(|item| item.id.clone())
+        .unwrap_or_default()
+}
+
+fn row_to_record(row: &ImportedRow, seq_id: &str, catalog: &PoolCatalog) -> Value {
+    let item_id = find_item_id(catalog, &row.pool_id, &row.item_name);
+    match row.kind {
+        GachaKind::Character => json!({
+            "charId": item_id,
+            "charName": row.item_name,
+            "gachaTs": row.gacha_ts.to_string(),
+            "isFree": row.is_free,
+            "isNew": row.is_new,
+            "poolId": row.pool_id,
+            "poolName": row.pool_name,
+            "rarity": row.rarity,
+            "seqId": seq_id,
+        }),
+        GachaKind::Weapon => json!({
+            "weaponId": item_id,
+            "weaponName": row.item_name,
+            "weaponType": "",
+            "poolId": row.pool_id,
+            "poolName": row.pool_name,
+            "rarity": row.rarity,
+            "isNew": row.is_new,
+            "gachaTs": row.gacha_ts.to_string(),
+            "seqId": seq_id,
+        }),
+    }
+}
+
+fn overlay_row(existing: &Value, row: &ImportedRow) -> Value {
+    let mut record = existing.as_object().cloned().unwrap_or_default();
+    record.insert("gachaTs".into(), json!(row.gacha_ts.to_string()));
+    record.insert("rarity".into(), json!(row.rarity));
+    record.insert("poolId".into(), json!(row.pool_id));
+    record.insert("isNew".into(), json!(row.is_new));
+    if row.kind == GachaKind::Character {
+        record.insert("isFree".into(), json!(row.is_free));
+    }
+    Value::Object(record)
+}
+
+fn push_record(target: &mut Map<String, Value>, group: &str, record: Value) {
+    let list = target
+        .entry(group.to_string())
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if let Some(list) = list.as_array_mut() {
+        list.push(record);
+    }
+}
+
+#[command]
+pub fn import_xlsx(
+    user_key: String,
+    path: String,
+    dry_run: bool,
+) -> Result<XlsxImportResult, String> {
+    let user_key = user_key.trim().to_string();
+    let config = load_config_data()?;
+    let user = config
+        .users
+        .iter()
+        .find(|item| get_user_key(item) == user_key)
+        .ok_or_else(|| format!("Account not found: {}", user_key))?;
+    let local = build_local_bundle(user)?;
+    let catalog = PoolCatalog::load()?;
+
+    let file = File::open(path.trim()).map_err(|e| e.to_string())?;
+    let mut workbook = Xlsx::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+    let mut sheets: Vec<String> = Vec::new();
+    let mut rows = Vec::new();
+    let mut skipped_rows = 0;
+    for name in workbook.sheet_names() {
+        let Some(kind) = sheet_kind(&name) else {
+            continue;
+        };
+        let range = workbook.worksheet_range(&name).map_err(|e| e.to_string())?;
+        let sheet_rows = range.rows().collect::<Vec<_>>();
+        let (parsed, skipped) = parse_sheet_rows(kind, &sheet_rows);
+        skipped_rows += skipped;
+        rows.extend(parsed);
+        sheets.push(name);
+    }
+    if sheets.is_empty() {
+        return Err("No recognised gacha record sheets in workbook".into());
+    }
+
+    // 本地已有记录：按 (卡池, 时间, 名称) 找回 seqId，按卡池 ID 找回角色卡池类型
+    let mut known_pool_keys = HashMap::new();
+    let mut local_records: HashMap<(GachaKind, String), (String, Value)> = HashMap::new();
+    let mut local_lookup: HashMap<(GachaKind, String, i64, String), Vec<String>> = HashMap::new();
+    for (kind, records) in [
+        (GachaKind::Character, &local.character),
+        (GachaKind::Weapon, &local.weapon),
+    ] {
+        let Some(obj) = records.as_object() else {
+            continue;
+        };
+        for (pool_key, list) in obj.iter() {
+            for item in list.as_array().map(Vec::as_slice).unwrap_or_default() {
+                let Some(pull) = parse_pull(kind, pool_key, item) else {
+                    continue;
+                };
+                if kind == GachaKind::Character {
+                    known_pool_keys.insert(pull.pool_id.clone(), pool_key.clone());
+                }
+                local_records.insert(
+                    (kind, pull.seq_id.clone()),
+                    (pool_key.clone(), item.clone()),
+                );
+                local_lookup
+                    .entry((kind, pull.pool_id, pull.gacha_ts, pull.item_name))
+                    .or_default()
+                    .push(pull.seq_id);
+            }
+        }
+    }
+    for seq_ids in local_lookup.values_mut() {
+        seq_ids.sort_by(|a, b| crate::compare_seqid(a, b));
+    }
+
+    // 表格按时间倒序排列，翻转后同一时间戳（十连）内保持原始抽取顺序
+    rows.reverse();
+    rows.sort_by_key(|row| (row.kind, row.gacha_ts));
+
+    let mut character = Map::new();
+    let mut weapon = Map::new();
+    let mut reconstructed = 0;
+    let mut known_duplicates = 0;
+    let mut matched: HashMap<(GachaKind, String, i64, String), usize> = HashMap::new();
+    let mut same_ts_index: HashMap<(GachaKind, i64), usize> = HashMap::new();
+    let (mut character_rows, mut weapon_rows) = (0, 0);
+
+    for row in rows.iter() {
+        match row.kind {
+            GachaKind::Character => character_rows += 1,
+            GachaKind::Weapon => weapon_rows += 1,
+        }
+        let ts_index = same_ts_index.entry((row.kind, row.gacha_ts)).or_insert(0);
+        *ts_index += 1;
+
+        let seq_id = match row.seq_id.clone() {
+            Some(seq_id) => seq_id,
+            None => {
+                let key = (
+                    row.kind,
+                    row.pool_id.clone(),
+                    row.gacha_ts,
+                    row.item_name.clone(),
+                );
+                let used = matched.entry(key.clone()).or_insert(0);
+                let existing = local_lookup
+                    .get(&key)
+                    .and_then(|ids| ids.get(*used))
+                    .cloned();
+                *used += 1;
+                existing.unwrap_or_else(|| {
+                    reconstructed += 1;
+                    format!(
+                        "{}_{:013}_{:03}",
+                        SYNTHETIC_SEQID_PREFIX, row.gacha_ts, ts_index
+                    )
+                })
+            }
+        };
+
+        let (group, record) = match local_records.get(&(row.kind, seq_id.clone())) {
+            // 已有记录只比较表格中可还原的字段，其余字段（ID、武器类型等）沿用本地
+            Some((pool_key, existing)) => {
+                let record = overlay_row(existing, row);
+                if &record == existing {
+                    known_duplicates += 1;
+                    continue;
+                }
+                (pool_key.clone(), record)
+            }
+            None => {
+                let group = match row.kind {
+                    GachaKind::Character => character_pool_key(&row.pool_id, &known_pool_keys),
+                    GachaKind::Weapon => row.pool_id.clone(),
+                };
+                (group, row_to_record(row, &seq_id, &catalog))
+            }
+        };
+        match row.kind {
+            GachaKind::Character => push_record(&mut character, &group, record),
+            GachaKind::Weapon => push_record(&mut weapon, &group, record),
+        }
+    }
+
+    let incoming = AccountBundle {
+        character: Value::Object(character),
+        weapon: Value::Object(weapon),
+        ..local
+    };
+    let (character_added, weapon_added, duplicates, conflicts) =
+        merge_bundle_into_local(&incoming, dry_run)?;
+
+    Ok(XlsxImportResult {
+        dry_run,
+        user_key,
+        sheets,
+        character_rows,
+        weapon_rows,
+        skipped_rows,
+        reconstructed,
+        character_added,
+        weapon_added,
+        duplicates: duplicates + known_duplicates,
+        conflicts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::SPECIAL_POOL_KEY;
+    use crate::testutil;
+
+    const SOURCE_KEY: &str = "1001_r1";
+    const TARGET_KEY: &str = "1002_r2";
+
+    fn character(seq_id: &str, ts: i64, name: &str, rarity: u8, is_free: bool) -> Value {
+        json!({
+            "charId": format!("chr_{}", seq_id),
+            "charName": name,
+            "gachaTs": ts.to_string(),
+            "isFree": is_free,
+            "isNew": rarity == 6,
+            "poolId": "special_1_0_1",
+            "poolName": "熔火灼痕",
+            "rarity": rarity,
+            "seqId": seq_id,
+        })
+    }
+
+    fn weapon(seq_id: &str, ts: i64, name: &str, rarity: u8) -> Value {
+        json!({
+            "weaponId": format!("wpn_{}", seq_id),
+            "weaponName": name,
+            "weaponType": "sword",
+            "poolId": "weaponbox_1_0_1",
+            "poolName": "熔铸申领",
+            "rarity": rarity,
+            "isNew": false,
+            "gachaTs": ts.to_string(),
+            "seqId": seq_id,
+        })
+    }
+
+    fn seed_accounts() -> Value {
+        testutil::write_config(&json!({
+            "users": [
+                testutil::hypergryph_user("1001", "r1"),
+                testutil::hypergryph_user("1002", "r2"),
+            ]
+        }));
+        // 本地记录按 seqId 倒序存储；前两抽为同一时间戳的十连
+        let record = json!({
+            "character_max_seqid": "104",
+            "weapon_max_seqid": "202",
+            "character": {
+                SPECIAL_POOL_KEY: [
+                    character("104", 1_768_003_000_000, "莱万汀", 6, true),
+                    character("103", 1_768_002_000_000, "佩丽卡", 5, false),
+                    character("102", 1_768_001_000_000, "秋栗", 4, false),
+                    character("101", 1_768_001_000_000, "埃特拉", 4, false),
+                ]
+            },
+            "weapon": {
+                "weaponbox_1_0_1": [
+                    weapon("202", 1_768_005_000_000, "熔铸火焰", 6),
+                    weapon("201", 1_768_004_000_000, "工业零点一", 4),
+                ]
+            }
+        });
+        testutil::write_record(SOURCE_KEY, &record);
+        record
+    }
+
+    fn export_source() -> String {
+        let path = testutil::temp_path("roundtrip.xlsx")
+            .to_string_lossy()
+            .to_string();
+        let result = export_xlsx(SOURCE_KEY.into(), path.clone(), None).unwrap();
+        assert_eq!(result.character_count, 4);
+        assert_eq!(result.weapon_count, 2);
+        path
+    }
+
+    fn seq_ids(record: &Value, kind: &str, group: &str) -> Vec<String> {
+        record[kind][group]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["seqId"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn reimporting_own_export_only_reports_duplicates() {
+        let _guard = testutil::userdata();
+        seed_accounts();
+        let path = export_source();
+
+        let result = import_xlsx(SOURCE_KEY.into(), path, true).unwrap();
+        assert_eq!(result.character_rows, 4);
+        assert_eq!(result.weapon_rows, 2);
+        assert_eq!(result.skipped_rows, 0);
+        assert_eq!(result.reconstructed, 0);
+        assert_eq!(result.character_added, 0);
+        assert_eq!(result.weapon_added, 0);
+        assert_eq!(result.duplicates, 6);
+        assert!(result.conflicts.is_empty());
+    }
+
+    #[test]
+    fn importing_export_into_empty_account_restores_records() {
+        let _guard = testutil::userdata();
+        let source = seed_accounts();
+        let path = export_source();
+
+        let preview = import_xlsx(TARGET_KEY.into(), path.clone(), true).unwrap();
+        assert_eq!(preview.character_added, 4);
+        assert_eq!(preview.weapon_added, 2);
+        assert!(!crate::get_record_path(TARGET_KEY).unwrap().exists());
+
+        let result = import_xlsx(TARGET_KEY.into(), path, false).unwrap();
+        assert_eq!(result.character_added, 4);
+        assert_eq!(result.weapon_added, 2);
+        assert_eq!(result.duplicates, 0);
+
+        let imported = testutil::read_record(TARGET_KEY);
+        for (kind, group) in [
+            ("character", SPECIAL_POOL_KEY),
+            ("weapon", "weaponbox_1_0_1"),
+        ] {
+            assert_eq!(
+                seq_ids(&imported, kind, group),
+                seq_ids(&source, kind, group)
+            );
+        }
+        let restored = load_account_pulls(TARGET_KEY).unwrap();
+        let original = load_account_pulls(SOURCE_KEY).unwrap();
+        for (a, b) in restored.character[SPECIAL_POOL_KEY]
+            .iter()
+            .zip(original.character[SPECIAL_POOL_KEY].iter())
+        {
+            assert_eq!(
+                (
+                    &a.item_name,
+                    a.rarity,
+                    a.is_free,
+                    a.is_new,
+                    a.gacha_ts,
+                    &a.pool_id
+                ),
+                (
+                    &b.item_name,
+                    b.rarity,
+                    b.is_free,
+                    b.is_new,
+                    b.gacha_ts,
+                    &b.pool_id
+                )
+            );
+        }
+    }
+
+    /// 旧版前端 useExcelExport 的 8 列表头
+    const LEGACY_HEADERS: [&str; 8] = [
+        "时间",
+        "名称",
+        "星级",
+        "卡池名",
+        "卡池 ID",
+        "是否 NEW",
+        "是否为加急招募",
+        "seqId",
+    ];
+
+    /// 按旧版前端导出写出“角色记录”“武器记录”两张表：时间为文本、星级为数字、按时间倒序
+    fn write_legacy_workbook(path: &str, characters: &[[&str; 8]], weapons: &[[&str; 8]]) {
+        let mut workbook = Workbook::new();
+        for (name, rows) in [
+            (LEGACY_CHARACTER_SHEET, characters),
+            (LEGACY_WEAPON_SHEET, weapons),
+        ] {
+            let worksheet = workbook.add_worksheet();
+            worksheet.set_name(name).unwrap();
+            for (col, label) in LEGACY_HEADERS.iter().enumerate() {
+                worksheet.write_string(0, col as u16, *label).unwrap();
+            }
+            for (index, cells) in rows.iter().enumerate() {
+                let row = index as u32 + 1;
+                for (col, cell) in cells.iter().enumerate() {
+                    if col == 2 {
+                        worksheet
+                            .write_number(row, col as u16, cell.parse::<f64>().unwrap())
+                            .unwrap();
+                    } else {
+                        worksheet.write_string(row, col as u16, *cell).unwrap();
+                    }
+                }
+            }
+        }
+        workbook.save(path).unwrap();
+    }
+
+    fn local_ms(hour: u32, minute: u32) -> i64 {
+        Local
+            .with_ymd_and_hms(2026, 1, 10, hour, minute, 0)
+            .earliest()
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    #[test]
+    fn imports_legacy_frontend_workbook_in_time_order() {
+        let _guard = testutil::userdata();
+        seed_accounts();
+        let path = testutil::temp_path("legacy.xlsx")
+            .to_string_lossy()
+            .to_string();
+        // 6★ 一行缺少 seqId，需要生成占位 seqId，且垫抽仍按时间计算
+        write_legacy_workbook(
+            &path,
+            &[
+                [
+                    "2026-01-10 10:04:00",
+                    "秋栗",
+                    "4",
+                    "熔火灼痕",
+                    "special_1_0_1",
+                    "否",
+                    "是",
+                    "903",
+                ],
+                [
+                    "2026-01-10 10:03:00",
+                    "莱万汀",
+                    "6",
+                    "熔火灼痕",
+                    "special_1_0_1",
+                    "是",
+                    "否",
+                    "",
+                ],
+                [
+                    "2026-01-10 10:02:00",
+                    "佩丽卡",
+                    "5",
+                    "熔火灼痕",
+                    "special_1_0_1",
+                    "否",
+                    "否",
+                    "902",
+                ],
+                [
+                    "2026-01-10 10:01:00",
+                    "埃特拉",
+                    "4",
+                    "熔火灼痕",
+                    "special_1_0_1",
+                    "否",
+                    "否",
+                    "901",
+                ],
+            ],
+            &[[
+                "2026-01-10 11:00:00",
+                "工业零点一",
+                "4",
+                "熔铸申领",
+                "weaponbox_1_0_1",
+                "否",
+                "否",
+                "951",
+            ]],
+        );
+
+        let result = import_xlsx(TARGET_KEY.into(), path, false).unwrap();
+        assert_eq!(result.sheets, [LEGACY_CHARACTER_SHEET, LEGACY_WEAPON_SHEET]);
+        assert_eq!((result.character_rows, result.weapon_rows), (4, 1));
+        assert_eq!(result.skipped_rows, 0);
+        assert_eq!(result.reconstructed, 1);
+        assert_eq!((result.character_added, result.weapon_added), (4, 1));
+
+        let imported = testutil::read_record(TARGET_KEY);
+        let stored = seq_ids(&imported, "character", SPECIAL_POOL_KEY);
+        assert_eq!(stored[..3], ["903", "902", "901"]);
+        assert!(stored[3].starts_with(SYNTHETIC_SEQID_PREFIX));
+        assert_eq!(seq_ids(&imported, "weapon", "weaponbox_1_0_1"), ["951"]);
+
+        let pulls = load_account_pulls(TARGET_KEY).unwrap().character[SPECIAL_POOL_KEY].clone();
+        let timeline = pulls
+            .iter()
+            .map(|pull| (pull.item_name.as_str(), pull.gacha_ts))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            timeline,
+            [
+                ("埃特拉", local_ms(10, 1)),
+                ("佩丽卡", local_ms(10, 2)),
+                ("莱万汀", local_ms(10, 3)),
+                ("秋栗", local_ms(10, 4)),
+            ]
+        );
+        assert!(pulls[2].is_new);
+        assert!(pulls[3].is_free);
+
+        let annotated = crate::stats::annotate_pity(
+            GachaKind::Character,
+            SPECIAL_POOL_KEY,
+            &pulls,
+            &PoolCatalog::default(),
+            &PityRules::default(),
+        );
+        assert_eq!(annotated[2].pity, 3);
+    }
+}